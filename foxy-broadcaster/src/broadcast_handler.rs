@@ -1,12 +1,17 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use serde::Deserialize;
 use tracing::{error, info, warn};
 use foxy_shared::services::cloudwatch_services::{emit_broadcast_queue_failure, OperationMetricTracker};
+use foxy_shared::database::dispatch_claims::{Dispatch, DispatchClaims};
+use foxy_shared::database::event_store::{retry_on_conflict, EventStore};
+use foxy_shared::database::nonce_leases::NonceLeases;
 use foxy_shared::database::transaction_event::TransactionEventManager;
+use foxy_shared::models::errors::TransactionError;
+use foxy_shared::models::state_machine;
 use foxy_shared::models::transactions::{BundleStatus, EventType, TransactionEvent, TransactionLeg};
-use foxy_shared::utilities::config::{get_broadcast_queue, get_rpc_url, get_transaction_event_table};
+use foxy_shared::utilities::config::{get_broadcast_queue, get_rpc_url_for, get_supported_networks, get_transaction_event_table};
 
 use aws_sdk_sqs::Client as SqsClient;
 use aws_sdk_dynamodb::Client as DynamoDbClient;
@@ -55,7 +60,15 @@ pub async fn function_handler_with_cache(
     info!("📦 Queue URL resolved: {}", queue_url);
 
     let tem = TransactionEventManager::new(dynamo_db_client, get_transaction_event_table());
-    let provider = Arc::new(Provider::<Http>::try_from(get_rpc_url())?);
+    let leases = NonceLeases::new(tem.client(), get_transaction_event_table());
+    let dispatch = DispatchClaims::new(tem.client(), get_transaction_event_table());
+    // Each leg is sent to the chain it was signed for
+    let mut providers = HashMap::new();
+    for network in get_supported_networks() {
+        let provider = Provider::<Http>::try_from(get_rpc_url_for(&network))?;
+        providers.insert(network, provider);
+    }
+    let providers = Arc::new(providers);
 
     let result = match sqs_client
         .receive_message()
@@ -105,9 +118,11 @@ pub async fn function_handler_with_cache(
         let tem = Arc::clone(&tem);
         let sqs_client = sqs_client.clone();
         let queue_url = queue_url.clone();
-        let provider = Arc::clone(&provider);
+        let providers = Arc::clone(&providers);
         let recent_tx_hashes = Arc::clone(&recent_tx_hashes);
         let tracker_for_loop = tracker.clone();
+        let leases = leases.clone();
+        let dispatch = dispatch.clone();
 
         futures.push(tokio::spawn(async move {
             let last_event = match tem.get_latest_event(&parsed_msg.bundle_id).await {
@@ -118,22 +133,43 @@ pub async fn function_handler_with_cache(
                 }
             };
 
-            let (leg, signing_data) = match (&last_event.event_type, &last_event.bundle_snapshot.status) {
-                (EventType::Sign, BundleStatus::Signed) => (TransactionLeg::Main, last_event.bundle_snapshot.main_tx.signed_tx.clone()),
-                (EventType::Confirm, BundleStatus::MainConfirmed) => (TransactionLeg::Fee, last_event.bundle_snapshot.fee_tx.signed_tx.clone()),
-                _ => {
-                    error!("{}", format!("Cannot broadcast from EventType:{} and BundleStatus:{}",
-                            &last_event.event_type, &last_event.bundle_snapshot.status));
-                    return Err(());
-                }
+            if last_event.bundle_snapshot.status == BundleStatus::Cancelled {
+                info!("🛑 Bundle {} was cancelled, dropping broadcast request", last_event.bundle_id);
+                delete_sqs_message(&sqs_client, &queue_url, &receipt_handle).await;
+                return Ok(());
+            }
+
+            // A replacement goes out in place of a leg that is already pending, and a leg whose
+            // block was reorged out goes out again as it is
+            let resend = matches!(last_event.event_type, EventType::Replace | EventType::Reorg);
+            let leg = match (resend, last_event.leg) {
+                (true, Some(leg)) => leg,
+                _ => match state_machine::next_leg(&last_event, EventType::Broadcast) {
+                    Ok((_, leg)) => leg,
+                    Err(e) => {
+                        error!("Cannot broadcast bundle {}: {}", &last_event.bundle_id, e);
+                        return Err(());
+                    }
+                },
             };
+            let signing_data = last_event.bundle_snapshot.leg(leg).signed_tx.clone();
+            let network = last_event.bundle_snapshot.leg(leg).network.clone();
 
             //skip if this is a 0 value fee tx
-            if leg == TransactionLeg::Fee && last_event.bundle_snapshot.fee_tx.transaction_value == 0{
+            if !resend && leg == TransactionLeg::Fee && last_event.bundle_snapshot.fee_tx.transaction_value == 0{
                 info!("📌 Skipping broadcast for bundle {}", last_event.bundle_id);
-                match TransactionEvent::on_skip(&last_event, &last_event.bundle_snapshot.fee_tx, tem.clone()).await {
-                    Ok(_) => {
+                let skipped = retry_on_conflict(tem.as_ref(), last_event.clone(), |latest| {
+                    let tem = tem.clone();
+                    async move { TransactionEvent::on_skip(&latest, &latest.bundle_snapshot.fee_tx, tem).await }
+                }).await;
+
+                match skipped {
+                    Ok(skipped) => {
                         info!("📦 Broadcast event successfully recorded for bundle {}", last_event.bundle_id);
+                        // The fee leg's nonce is never sent, so the next bundle can have it
+                        if let Err(e) = leases.release(&skipped.bundle_snapshot).await {
+                            error!("❌ Failed to release nonces for bundle {}: {:?}", last_event.bundle_id, e);
+                        }
                     }
                     Err(e) => {
                         error!("❌ Failed to emit Broadcast event for bundle {}: {:?}", last_event.bundle_id, e);
@@ -151,6 +187,11 @@ pub async fn function_handler_with_cache(
 
             info!("📨 Signing Data: {}", signing_data);
 
+            let Some(provider) = providers.get(&network) else {
+                error!("❌ Bundle {} targets {}, which this deployment does not serve", last_event.bundle_id, network);
+                return Err(());
+            };
+
             info!("Broadcasting signing data: {}", signing_data.to_string());
             let tx_bytes = Bytes::from(match hex::decode(signing_data.trim_start_matches("0x")) {
                 Ok(bytes) => bytes,
@@ -160,6 +201,39 @@ pub async fn function_handler_with_cache(
                 }
             });
 
+            // Claimed before the leg can reach the chain, as the Broadcast event is only recorded
+            // after it has. A cancel that claimed the bundle first wins, but it may yet fail, so
+            // the message is left to come back until the bundle shows as cancelled.
+            match dispatch.claim(&last_event.bundle_id, Dispatch::Send).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    info!("🛑 Bundle {} is being cancelled, leaving broadcast request for redelivery", last_event.bundle_id);
+                    return Ok(());
+                }
+                Err(e) => {
+                    error!("❌ Could not claim bundle {} for sending: {:?}", last_event.bundle_id, e);
+                    return Err(());
+                }
+            }
+
+            // A cancel recorded since the event was read, whose claim came and went
+            match tem.get_latest_event(&last_event.bundle_id).await {
+                Ok(latest) if latest.bundle_snapshot.status == BundleStatus::Cancelled => {
+                    info!("🛑 Bundle {} was cancelled, dropping broadcast request", last_event.bundle_id);
+                    delete_sqs_message(&sqs_client, &queue_url, &receipt_handle).await;
+                    return Ok(());
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error!("❌ Could not get latest event: {:?}", e);
+                    // Nothing went out, so a cancel may still be taken in the meantime
+                    if let Err(e) = dispatch.release(&last_event.bundle_id, Dispatch::Send).await {
+                        error!("❌ Failed to release send claim on bundle {}: {:?}", last_event.bundle_id, e);
+                    }
+                    return Err(());
+                }
+            }
+
             let tx_hash = H256::from(keccak256(&tx_bytes));
 
             {
@@ -184,20 +258,40 @@ pub async fn function_handler_with_cache(
 
             match provider.send_raw_transaction(tx_bytes.clone()).await {
                 Ok(pending) => {
-                    info!("✅ Broadcasted to Optimism with tx hash: {:#x}", pending.tx_hash());
+                    info!("✅ Broadcasted to {} with tx hash: {:#x}", network, pending.tx_hash());
 
+                    // The Replace or Reorg event already recorded the leg's hashes
+                    if resend {
+                        delete_sqs_message(&sqs_client, &queue_url, &receipt_handle).await;
+                        return Ok(());
+                    }
+
+                    // Recorded against the leg that went out, which may no longer be the next one
+                    // to go out by the time a conflict reloads the bundle
                     info!("📌 Emitting Broadcast event for bundle {} with tx_hash: {:#x}", last_event.bundle_id, tx_hash);
-                    match TransactionEvent::on_broadcast(&last_event, tx_hash, tem.clone()).await {
+                    let broadcasted = retry_on_conflict(tem.as_ref(), last_event.clone(), |latest| {
+                        let tem = tem.clone();
+                        async move { TransactionEvent::on_broadcast_leg(&latest, leg, tx_hash, tem).await }
+                    }).await;
+
+                    match broadcasted {
                         Ok(_) => {
                             info!("📦 Broadcast event successfully recorded for bundle {}", last_event.bundle_id);
+                            delete_sqs_message(&sqs_client, &queue_url, &receipt_handle).await;
+                            Ok(())
+                        }
+                        Err(e @ TransactionError::InvalidStateTransition { .. }) => {
+                            error!("🚨 {} leg of bundle {} went out as {:#x} but can no longer be recorded: {}", leg, last_event.bundle_id, tx_hash, e);
+                            let _ = &tracker_for_loop.emit("UnrecordedBroadcast", 1.0, "Count", &[("Network", &network.to_string())]).await;
+                            delete_sqs_message(&sqs_client, &queue_url, &receipt_handle).await;
+                            Err(())
                         }
                         Err(e) => {
                             error!("❌ Failed to emit Broadcast event for bundle {}: {:?}", last_event.bundle_id, e);
+                            delete_sqs_message(&sqs_client, &queue_url, &receipt_handle).await;
+                            Ok(())
                         }
                     }
-
-                    delete_sqs_message(&sqs_client, &queue_url, &receipt_handle).await;
-                    Ok(())
                 }
                 Err(e) => {
                     warn!("⚠️ Broadcast failed: {:?}", e);
@@ -217,7 +311,23 @@ pub async fn function_handler_with_cache(
                         }
                     }
 
-                    let _ = TransactionEvent::on_fail(&last_event, leg, tem).await;
+                    // The transaction already sent under this nonce is still pending and may yet be mined
+                    if resend {
+                        warn!("⚠️ Resend for bundle {} rejected, the {} leg stays pending", last_event.bundle_id, leg);
+                        delete_sqs_message(&sqs_client, &queue_url, &receipt_handle).await;
+                        let _ = &tracker_for_loop.emit("ReplacementRejected", 1.0, "Count", &[]).await;
+                        return Ok(());
+                    }
+
+                    let failed = retry_on_conflict(tem.as_ref(), last_event.clone(), |latest| {
+                        let tem = tem.clone();
+                        async move { TransactionEvent::on_fail(&latest, leg, tem).await }
+                    }).await;
+                    if let Ok(failed) = failed {
+                        if let Err(e) = leases.release(&failed.bundle_snapshot).await {
+                            error!("❌ Failed to release nonces for bundle {}: {:?}", last_event.bundle_id, e);
+                        }
+                    }
                     delete_sqs_message(&sqs_client, &queue_url, &receipt_handle).await;
                     tracker_for_loop.emit_fatal("OptimismBroadcast").await;
                     Err(())
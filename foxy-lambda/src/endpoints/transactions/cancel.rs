@@ -0,0 +1,111 @@
+use std::sync::Arc;
+use std::time::Instant;
+use aws_sdk_cloudwatch::Client as CloudWatchClient;
+use aws_sdk_cloudwatch::types::StandardUnit;
+use aws_sdk_dynamodb::Client as DynamoDbClient;
+use http::{Response, StatusCode};
+use lambda_http::{Body, Request};
+use serde_json::json;
+use foxy_shared::database::client::get_dynamodb_client;
+use foxy_shared::database::dispatch_claims::{Dispatch, DispatchClaims};
+use foxy_shared::database::errors::DynamoDbError;
+use foxy_shared::database::event_store::{retry_on_conflict, EventStore};
+use foxy_shared::database::nonce_leases::NonceLeases;
+use foxy_shared::database::transaction_event::TransactionEventManager;
+use foxy_shared::models::errors::TransactionError;
+use foxy_shared::models::state_machine;
+use foxy_shared::models::transactions::{EventType, TransactionEvent};
+use foxy_shared::services::cloudwatch_services::{create_cloudwatch_client, emit_metric};
+use foxy_shared::utilities::authentication::with_valid_user;
+use foxy_shared::utilities::config::get_transaction_event_table;
+use foxy_shared::utilities::requests::extract_bearer_token;
+use foxy_shared::utilities::responses::{error_response, response_with_code, success_response};
+
+pub async fn handler(event: Request, bundle_id: &str) -> Result<Response<Body>, lambda_http::Error> {
+    let token = extract_bearer_token(&event);
+    let cloudwatch_client = create_cloudwatch_client().await;
+    let dynamo_db_client = get_dynamodb_client().await;
+    log::info!("Cancelling transaction {}", bundle_id);
+
+    match token {
+        None => error_response("Missing authorization token"),
+        Some(token) => {
+            match cancel_transaction(token, &dynamo_db_client, &cloudwatch_client, bundle_id).await {
+                Ok(cancelled) => {
+                    let json = json!({
+                        "bundle_id": cancelled.bundle_id,
+                        "status": cancelled.bundle_status,
+                        "message": "Transaction cancelled."});
+
+                    success_response(json)
+                }
+                Err(TransactionError::NotFound(msg)) => response_with_code(&msg, StatusCode::NOT_FOUND),
+                Err(TransactionError::Unauthorized) => response_with_code("Not the owner of this transaction", StatusCode::FORBIDDEN),
+                Err(err @ TransactionError::InvalidStateTransition { .. }) => response_with_code(err.to_string(), StatusCode::CONFLICT),
+                Err(err) => error_response(format!("{:?}", err)),
+            }
+        }
+    }
+}
+
+async fn cancel_transaction(
+    token: &str,
+    dynamo_db_client: &DynamoDbClient,
+    cloudwatch_client: &CloudWatchClient,
+    bundle_id: &str,
+) -> Result<TransactionEvent, TransactionError> {
+    with_valid_user(token, |user_id| async move {
+        let start = Instant::now();
+        let tem = TransactionEventManager::new(Arc::new(dynamo_db_client.clone()), get_transaction_event_table());
+
+        let last_event = tem.get_latest_event(bundle_id).await.map_err(|e| match e {
+            DynamoDbError::NotFound => TransactionError::NotFound(format!("No transaction found for bundle {}", bundle_id)),
+            other => other.into(),
+        })?;
+
+        // Checked before the claim is taken, as holding it keeps the bundle from being sent
+        if last_event.bundle_snapshot.user_id != user_id {
+            return Err(TransactionError::Unauthorized);
+        }
+        state_machine::lookup(&last_event, EventType::Cancel, None)?;
+
+        // The broadcaster claims the bundle before its first leg goes out, which may be before
+        // the Broadcast event is recorded. Once it has, the leg may already be on chain.
+        let dispatch = DispatchClaims::new(tem.client(), get_transaction_event_table());
+        if !dispatch.claim(bundle_id, Dispatch::Cancel).await? {
+            return Err(TransactionError::InvalidStateTransition {
+                event: "Cancel after Send".to_string(),
+                status: last_event.bundle_snapshot.status.to_string(),
+            });
+        }
+
+        let cancelled = retry_on_conflict(tem.as_ref(), last_event, |latest| {
+            let tem = tem.clone();
+            let user_id = user_id.clone();
+            async move { TransactionEvent::on_cancel(&latest, &user_id, tem).await }
+        }).await;
+
+        // Safe even if the cancel was recorded after all, as the broadcaster rereads the bundle
+        // once it holds the claim
+        let cancelled = match cancelled {
+            Ok(cancelled) => cancelled,
+            Err(e) => {
+                if let Err(release) = dispatch.release(bundle_id, Dispatch::Cancel).await {
+                    log::warn!("Failed to release the cancel claim on bundle {}: {}", bundle_id, release);
+                }
+                return Err(e);
+            }
+        };
+
+        log::info!("Bundle {} cancelled by {}", bundle_id, user_id);
+
+        let leases = NonceLeases::new(Arc::new(dynamo_db_client.clone()), get_transaction_event_table());
+        if let Err(e) = leases.release(&cancelled.bundle_snapshot).await {
+            log::warn!("Failed to release nonces of bundle {}: {}", bundle_id, e);
+        }
+        emit_metric(cloudwatch_client, "CancelLatency", start.elapsed().as_millis() as f64, StandardUnit::Milliseconds).await;
+        emit_metric(cloudwatch_client, "CancelledCount", 1.0, StandardUnit::Count).await;
+
+        Ok(cancelled)
+    }).await
+}
@@ -1,17 +1,21 @@
 use std::sync::Arc;
 use std::time::Instant;
-use http::Response;
+use chrono::Utc;
+use http::{Response, StatusCode};
 use lambda_http::{Body, Request};
 use serde_json::{json, Value};
+use foxy_shared::database::event_store::{retry_on_conflict, EventStore};
 use foxy_shared::database::transaction_event::TransactionEventManager;
 use foxy_shared::database::client::get_dynamodb_client;
+use foxy_shared::database::nonce_leases::NonceLeases;
 use foxy_shared::models::errors::TransactionError;
-use foxy_shared::models::transactions::TransactionEvent;
+use foxy_shared::models::transactions::{BundleStatus, TransactionEvent, TransactionLeg, UnsignedTransaction};
 use foxy_shared::utilities::authentication::with_valid_user;
+use foxy_shared::utilities::signed_tx;
 use foxy_shared::services::cloudwatch_services::{create_cloudwatch_client, emit_broadcast_queue_failure, emit_metric};
-use foxy_shared::utilities::requests::extract_bearer_token;
-use foxy_shared::utilities::responses::{error_response, success_response};
-use foxy_shared::utilities::config::get_transaction_event_table;
+use foxy_shared::utilities::requests::{extract_bearer_token, extract_idempotency_key};
+use foxy_shared::utilities::responses::{error_response, response_with_code, success_response};
+use foxy_shared::utilities::config::{get_bundle_ttl, get_transaction_event_table};
 use aws_sdk_dynamodb::Client as DynamoDbClient;
 use aws_sdk_cloudwatch::Client as CloudWatchClient;
 use aws_sdk_cloudwatch::types::StandardUnit;
@@ -21,6 +25,7 @@ use crate::models::transactions::{SignedTransactionError, SignedTransactionPaylo
 
 pub async fn handler(event: Request, body: Value) -> Result<Response<Body>, lambda_http::Error> {
     let token = extract_bearer_token(&event);
+    let idempotency_key = extract_idempotency_key(&event);
     let cloudwatch_client = create_cloudwatch_client().await;
 
     let dynamo_db_client = get_dynamodb_client().await;
@@ -32,7 +37,7 @@ pub async fn handler(event: Request, body: Value) -> Result<Response<Body>, lamb
     match token{
         None => error_response("Missing authorization token"),
         Some(token) => {
-            match handle_signing(token, &payload.unwrap(), &dynamo_db_client, &cloudwatch_client).await {
+            match handle_signing(token, &payload.unwrap(), idempotency_key, &dynamo_db_client, &cloudwatch_client).await {
                 Ok(new_event) => {
                     let id = new_event.bundle_id.clone();
                     let json = json!({
@@ -42,6 +47,7 @@ pub async fn handler(event: Request, body: Value) -> Result<Response<Body>, lamb
 
                     success_response(json)
                 }
+                Err(err @ TransactionError::BundleExpired(_)) => response_with_code(err.to_string(), StatusCode::GONE),
                 Err(err) => error_response(format!("{:?}", err)),
             }
         }
@@ -50,6 +56,7 @@ pub async fn handler(event: Request, body: Value) -> Result<Response<Body>, lamb
 
 pub async fn handle_signing(token: &str,
                             payload: &SignedTransactionPayload,
+                            idempotency_key: Option<&str>,
                             dynamo_db_client: &DynamoDbClient,
                             cloudwatch_client: &CloudWatchClient)-> Result<TransactionEvent, TransactionError>
 {
@@ -60,13 +67,46 @@ pub async fn handle_signing(token: &str,
         let tem = TransactionEventManager::new(Arc::new(dynamo_db_client.clone()), get_transaction_event_table());
         let event = tem.get_latest_event(&payload.bundle_id).await?;
 
-        let new_event = match TransactionEvent::on_signed(&event,
-                                                                   &payload.fee_signed_tx,
-                                                                   &payload.main_signed_tx,
-                                                                   tem).await {
-            Ok(ev) => ev,
-            Err(e) => { return Err(e) }
-        };
+        // A retried request that already signed the bundle gets the event it signed it with back
+        if let Some(key) = idempotency_key {
+            if let Some(event_id) = tem.find_idempotent(&payload.bundle_id, key).await? {
+                log::info!("Bundle {} already committed under idempotency key {}", payload.bundle_id, key);
+                return tem.get_events(&payload.bundle_id).await?
+                    .into_iter()
+                    .find(|e| e.event_id == event_id)
+                    .ok_or_else(|| TransactionError::NotFound(format!("Event {} of bundle {}", event_id, payload.bundle_id)));
+            }
+        }
+
+        if event.bundle_snapshot.status == BundleStatus::Expired {
+            return Err(TransactionError::BundleExpired(format!("Bundle {} has expired, please start a new transaction", event.bundle_id)));
+        }
+
+        // The sweeper may not have reached this bundle yet; expire it now rather than sign a stale quote.
+        if event.bundle_snapshot.is_expired(Utc::now(), get_bundle_ttl()) {
+            let expired = retry_on_conflict(tem.as_ref(), event.clone(), |latest| {
+                let tem = tem.clone();
+                async move { TransactionEvent::on_expire(&latest, tem).await }
+            }).await;
+            match expired {
+                Ok(expired) => {
+                    let leases = NonceLeases::new(Arc::new(dynamo_db_client.clone()), get_transaction_event_table());
+                    if let Err(e) = leases.release(&expired.bundle_snapshot).await {
+                        log::warn!("Failed to release nonces of bundle {}: {}", event.bundle_id, e);
+                    }
+                }
+                Err(e) => log::warn!("Failed to record expiry of bundle {}: {}", event.bundle_id, e),
+            }
+            return Err(TransactionError::BundleExpired(format!("Bundle {} has expired, please start a new transaction", event.bundle_id)));
+        }
+
+        verify_signatures(&event, payload)?;
+
+        let mut new_event = TransactionEvent::sign(&event, &payload.fee_signed_tx, &payload.main_signed_tx, &payload.split_signed_txs)?;
+        if let Some(key) = idempotency_key {
+            new_event = new_event.with_idempotency_key(key);
+        }
+        new_event.event_id = tem.persist(&new_event).await?;
 
         log::info!("new transaction event: {:?}", &new_event);
         
@@ -90,6 +130,21 @@ pub async fn handle_signing(token: &str,
 
 }
 
+/// Rejects signed legs that differ from the unsigned ones initiate issued, or that the bundle
+/// sender did not sign, before anything is stored or queued for broadcast.
+fn verify_signatures(event: &TransactionEvent, payload: &SignedTransactionPayload) -> Result<(), TransactionError> {
+    let bundle = &event.bundle_snapshot;
+    // The user's wallet signs both legs; it is the fee leg's sender
+    let sender = &bundle.fee_tx.sender_address;
+
+    // A missing or extra split leg is rejected when the bundle is signed
+    for (i, (split_signed_tx, split_tx)) in payload.split_signed_txs.iter().zip(&bundle.split_txs).enumerate() {
+        signed_tx::verify(TransactionLeg::Split(i as u8), split_signed_tx, &UnsignedTransaction::try_from(split_tx)?, sender)?;
+    }
+    signed_tx::verify(TransactionLeg::Fee, &payload.fee_signed_tx, &UnsignedTransaction::try_from(&bundle.fee_tx)?, sender)?;
+    signed_tx::verify(TransactionLeg::Main, &payload.main_signed_tx, &UnsignedTransaction::try_from(&bundle.main_tx)?, sender)
+}
+
 #[cfg(test)]
 mod tests {
     use foxy_shared::services::authentication::generate_tokens;
@@ -4,7 +4,7 @@ use chrono::Utc;
 use foxy_shared::database::client::get_dynamodb_client;
 use foxy_shared::models::errors::TransactionError;
 use foxy_shared::utilities::{fees, gas};
-use foxy_shared::models::transactions::{FeeBreakdown, GasPricing, TransactionEstimateRequest, TransactionEstimateResponse};
+use foxy_shared::models::transactions::{FeeBreakdown, GasPricing, SpeedOption, SplitEstimate, SplitShare, TokenType, TransactionEstimateRequest, TransactionEstimateResponse, MAX_SPLIT_RECIPIENTS};
 use foxy_shared::services::cloudwatch_services::{create_cloudwatch_client, OperationMetricTracker};
 use aws_sdk_cloudwatch::Client as CloudWatchClient;
 use aws_sdk_dynamodb::Client as DynamoDbClient;
@@ -18,6 +18,9 @@ use foxy_shared::utilities::authentication::with_valid_user;
 use foxy_shared::utilities::exchange::ExchangeRateManager;
 use foxy_shared::utilities::requests::extract_bearer_token;
 use foxy_shared::utilities::responses::{error_response, response_with_code};
+use foxy_shared::utilities::config::{get_quote_signing_key, is_supported_network};
+use foxy_shared::utilities::quote::Quote;
+use foxy_shared::utilities::token_registry::{token_registry_for, TokenConfig};
 
 pub async fn handler(event: Request, body: Value) -> Result<Response<Body>, lambda_http::Error> {
     let token = extract_bearer_token(&event);
@@ -73,47 +76,73 @@ async fn estimate_transaction(token: &str,
                                   cloudwatch_client: &CloudWatchClient)
                                   -> Result<TransactionEstimateResponse, TransactionError> {
 
-    with_valid_user(token, |_| async move {
+    with_valid_user(token, |user_id| async move {
         let tracker = OperationMetricTracker::new(cloudwatch_client.clone(), "Estimate");
         track_ok!(tracker, async {
             if let Some(response) = early_exit_if_wallets_invalid(&request) {
                 return Ok(response);
             }
 
+            if !is_supported_network(&request.network) {
+                return Err(TransactionError::InvalidNetwork);
+            }
+            // A payment request without an amount comes through with neither; it cannot be priced
+            if request.fiat_value == 0 && request.transaction_value.is_none_or(|units| units == 0) {
+                return Err(TransactionError::InvalidAmount);
+            }
+            if request.splits.len() > MAX_SPLIT_RECIPIENTS
+                || request.splits.iter().any(|split| split.fiat_value == 0 || split.recipient_address == request.sender_address) {
+                return Err(TransactionError::InvalidRequest);
+            }
+            let token = token_registry_for(&request.network).resolve(&request.token_type)?;
             let mut status = EstimateFlags::empty();
             let exchange_rate;
 
             let exchange = ExchangeRateManager::new();
 
             //We have to get the exchange rate before we can price
-            match exchange.get_latest_rate(&request.fiat_currency, &request.token_type).await{
+            match exchange.get_latest_rate(&request.fiat_currency, token).await{
                 Ok(rate) => {
                     exchange_rate = rate;
                 }
                 Err(e) => {
                     //We should just bail now, no other figures can be calculated. Gotos are fine apparently.
-                    let mut response = TransactionEstimateResponse::default();
-                    response.token_type = request.token_type;
-                    response.fiat_amount_minor = request.fiat_value;
-                    response.fiat_currency = request.fiat_currency.clone();
-                    response.status = EstimateFlags::SUCCESS | EstimateFlags::EXCHANGE_RATE_UNAVAILABLE;
-                    response.message = Some(format!("Unable to fetch exchange rate: {}", e));
-                    return Ok(response);
+                    return Ok(exchange_rate_unavailable(&request, e));
                 }
             }
+
+            // Gas and the base fee are priced in ETH, whatever the token
+            let eth_rate = match token.is_native() {
+                true => exchange_rate,
+                false => {
+                    let eth = token_registry_for(&request.network).resolve(&TokenType::ETH)?;
+                    match exchange.get_latest_rate(&request.fiat_currency, eth).await {
+                        Ok(rate) => rate,
+                        Err(e) => return Ok(exchange_rate_unavailable(&request, e)),
+                    }
+                }
+            };
             /* The correct formula should be:
-                estimated_wei=(fiat_amount×10¹⁸/exchange_rate*100)
+                estimated_units=(fiat_amount×10^decimals/exchange_rate*100)
 
-                This ensures that fiat minor units (e.g., 1000 = £10.00) correctly map to WEI (10¹⁸ per ETH).
+                This ensures that fiat minor units (e.g., 1000 = £10.00) correctly map to the token's
+                base units (10¹⁸ wei per ETH, 10⁶ per USDC).
                 The exchange rate from the exchange needs to be converted into minor units,
              */
             let mut request = request.clone();
-            
-            let pounds = (request.fiat_value as f64) / 100.0;
-            let eth_amount = pounds / exchange_rate;
-            let estimated_wei = (eth_amount * 1e18).floor() as u128;
-            
+
+            let (estimated_wei, fiat_value) = price_amount(request.fiat_value, request.transaction_value, exchange_rate, token);
             request.transaction_value = Some(estimated_wei);
+            request.fiat_value = fiat_value;
+
+            // Split shares are priced at the same rate; the fee is charged on the whole bill
+            let splits: Vec<SplitShare> = request.splits.iter().map(|split| SplitShare {
+                recipient_address: split.recipient_address.clone(),
+                fiat_value: split.fiat_value,
+                transaction_value: token.to_base_units((split.fiat_value as f64) / 100.0 / exchange_rate),
+            }).collect();
+            let total_wei = estimated_wei + splits.iter().map(|split| split.transaction_value).sum::<u128>();
+            let total_fiat = request.fiat_value + splits.iter().map(|split| split.fiat_value).sum::<u64>();
 
             let gas_estimate = match gas::estimate_gas(&request).await {
                 Ok(estimate) => {
@@ -127,8 +156,12 @@ async fn estimate_transaction(token: &str,
             };
 
             let (service_fee, service_fee_minor) = match request.transaction_value {
-                Some(amount_wei) => {
-                    let service_fee_wei = match fees::calculate_service_fee(dynamodb_client, amount_wei).await {
+                Some(_) => {
+                    let base_fee_units = |wei| match token.is_native() {
+                        true => wei,
+                        false => fees::wei_to_token_units(wei, eth_rate, exchange_rate, token),
+                    };
+                    let service_fee_units = match fees::calculate_service_fee(dynamodb_client, total_wei, base_fee_units).await {
                         Ok(fee) => fee,
                         Err(_) => {
                             status.insert(EstimateFlags::SERVICE_FEE_UNAVAILABLE);
@@ -136,7 +169,8 @@ async fn estimate_transaction(token: &str,
                         }
                     };
 
-                    let service_fee_minor = match fees::calculate_service_fee(dynamodb_client, request.fiat_value as u128).await {
+                    let base_fee_minor = |wei| fees::wei_to_fiat_minor(wei, eth_rate);
+                    let service_fee_minor = match fees::calculate_service_fee(dynamodb_client, total_fiat as u128, base_fee_minor).await {
                         Ok(fee) => fee,
                         Err(_) => {
                             status.insert(EstimateFlags::SERVICE_FEE_UNAVAILABLE);
@@ -144,7 +178,7 @@ async fn estimate_transaction(token: &str,
                         }
                     };
 
-                    (service_fee_wei, service_fee_minor)
+                    (service_fee_units, service_fee_minor)
                 }
                 None => {
                     status.insert(EstimateFlags::SERVICE_FEE_UNAVAILABLE);
@@ -153,48 +187,103 @@ async fn estimate_transaction(token: &str,
             };
 
 
-            let total_fee = gas_estimate.network_fee + service_fee as u128;
             let exchange_rate_expires_at = Utc::now() + chrono::Duration::seconds(60);
 
-            let total_gas_cost_wei = gas_estimate.network_fee * 2;
+            // The fee leg pays gas for every leg: main, fee and one per split
+            let total_gas_cost_wei = gas_estimate.network_fee * (2 + splits.len() as u128);
             info!("Total gas cost: {}", total_gas_cost_wei);
-            let fee_tx_value_wei = service_fee.saturating_sub(total_gas_cost_wei);
-            info!("Fee tx value: {}", fee_tx_value_wei);
-            let fee_tx_value_eth = format!("{:.8}", wei_to_eth(fee_tx_value_wei));
-            info!("Fee tx value (eth): {}", fee_tx_value_eth);
+
+            // The service fee is in the token's units, but gas is always paid in ETH. Only an
+            // ETH fee leg can cover the gas out of the fee; a token fee leg carries all of it.
+            let fee_tx_value_units = match token.is_native() {
+                true => service_fee.saturating_sub(total_gas_cost_wei),
+                false => service_fee,
+            };
+            info!("Fee tx value: {}", fee_tx_value_units);
+            let fee_tx_value_amount = format!("{:.8}", token.from_base_units(fee_tx_value_units));
+            info!("Fee tx value ({}): {}", request.token_type, fee_tx_value_amount);
+
+            // Only the fiat total can add up fees in two currencies
+            let total_fee_minor = service_fee_minor + fees::wei_to_fiat_minor(gas_estimate.network_fee, eth_rate);
+            let total_fee_wei = service_fee + gas_estimate.network_fee;
+            let eth_only = |figure: String| token.is_native().then_some(figure);
 
             status = infer_estimate_success(status);
 
+            let gas = GasPricing {
+                estimated_gas: gas_estimate.gas_limit.to_string(),
+                gas_price: gas_estimate.gas_price.to_string(),
+                max_fee_per_gas: gas_estimate.max_fee_per_gas.to_string(),
+                max_priority_fee_per_gas: gas_estimate.max_priority_fee_per_gas.to_string(),
+            };
+
+            // Initiate prices the bundle from this, not from what the client sends back
+            let quote = Quote {
+                quote_id: Quote::new_id(),
+                user_id,
+                sender_address: request.sender_address.clone(),
+                recipient_address: request.recipient_address.clone(),
+                token_type: request.token_type.clone(),
+                network: request.network.clone(),
+                transaction_value: estimated_wei,
+                fiat_value: request.fiat_value,
+                fiat_currency: request.fiat_currency.clone(),
+                splits: splits.clone(),
+                exchange_rate,
+                service_fee: fee_tx_value_units,
+                service_fee_minor,
+                priority_level: request.priority_level.clone(),
+                gas_pricing: gas.clone(),
+                expires_at: exchange_rate_expires_at,
+            }.sign(&get_quote_signing_key())?;
+
             Ok(TransactionEstimateResponse {
                 token_type: request.token_type,
                 fiat_amount_minor: request.fiat_value,
                 fiat_currency: request.fiat_currency.clone(),
-                eth_amount: format!("{:.8}", wei_to_eth(estimated_wei)),  // helper function
+                eth_amount: format!("{:.8}", token.from_base_units(estimated_wei)),
                 wei_amount: estimated_wei.to_string(),
 
                 fees: FeeBreakdown {
-                    service_fee_wei: service_fee.to_string(),
-                    service_fee_eth: format!("{:.8}", wei_to_eth(service_fee as u128)),
+                    service_fee_units: service_fee.to_string(),
+                    service_fee_amount: format!("{:.8}", token.from_base_units(service_fee)),
+                    fee_tx_value_units: fee_tx_value_units.to_string(),
+                    fee_tx_value_amount: fee_tx_value_amount.clone(),
                     network_fee_wei: gas_estimate.network_fee.to_string(),
                     network_fee_eth: format!("{:.8}", wei_to_eth(gas_estimate.network_fee)),
-                    total_fee_wei: total_fee.to_string(),
-                    total_fee_eth: format!("{:.8}", wei_to_eth(total_fee)),
+                    total_fee_minor: total_fee_minor.to_string(),
                     display_total_fee: service_fee_minor.to_string(),
-                    fee_tx_value_eth: fee_tx_value_eth.to_string(),
-                    fee_tx_value_wei: fee_tx_value_wei.to_string(),
                     service_fee_minor: service_fee_minor.to_string(),
+                    service_fee_wei: eth_only(service_fee.to_string()),
+                    service_fee_eth: eth_only(format!("{:.8}", wei_to_eth(service_fee))),
+                    total_fee_wei: eth_only(total_fee_wei.to_string()),
+                    total_fee_eth: eth_only(format!("{:.8}", wei_to_eth(total_fee_wei))),
+                    fee_tx_value_eth: eth_only(fee_tx_value_amount),
+                    fee_tx_value_wei: eth_only(fee_tx_value_units.to_string()),
                 },
 
-                gas: GasPricing {
-                    estimated_gas: gas_estimate.gas_limit.to_string(),
-                    gas_price: gas_estimate.gas_price.to_string(),
-                    max_fee_per_gas: gas_estimate.max_fee_per_gas.to_string(),
-                    max_priority_fee_per_gas: gas_estimate.max_priority_fee_per_gas.to_string(),
-                },
+                gas,
+
+                priority_level: request.priority_level.clone(),
+                speeds: gas_estimate.priority_fees.iter().map(|fee| SpeedOption {
+                    priority_level: fee.priority_level.clone(),
+                    max_fee_per_gas: fee.max_fee_per_gas.to_string(),
+                    max_priority_fee_per_gas: fee.max_priority_fee_per_gas.to_string(),
+                    network_fee_wei: fee.network_fee.to_string(),
+                    network_fee_eth: format!("{:.8}", wei_to_eth(fee.network_fee)),
+                    expected_inclusion_secs: fee.expected_inclusion_secs,
+                }).collect(),
 
                 exchange_rate,
                 exchange_rate_expires_at,
+                quote,
                 recipient_address: request.recipient_address,
+                splits: splits.iter().map(|split| SplitEstimate {
+                    recipient_address: split.recipient_address.clone(),
+                    fiat_amount_minor: split.fiat_value,
+                    eth_amount: format!("{:.8}", token.from_base_units(split.transaction_value)),
+                    wei_amount: split.transaction_value.to_string(),
+                }).collect(),
                 status,
                 message: None,
             })
@@ -202,6 +291,26 @@ async fn estimate_transaction(token: &str,
     }).await
 }
 
+/// The amount to send in base units and its fiat value in minor units. A supplied amount,
+/// as a payment request names, is sent exactly and the fiat value derived from it; otherwise
+/// the amount is priced from the fiat value.
+fn price_amount(fiat_value: u64, transaction_value: Option<u128>, exchange_rate: f64, token: &TokenConfig) -> (u128, u64) {
+    match transaction_value {
+        Some(units) if units > 0 => (units, (token.from_base_units(units) * exchange_rate * 100.0).round() as u64),
+        _ => (token.to_base_units((fiat_value as f64) / 100.0 / exchange_rate), fiat_value),
+    }
+}
+
+fn exchange_rate_unavailable(request: &TransactionEstimateRequest, e: impl std::fmt::Display) -> TransactionEstimateResponse {
+    let mut response = TransactionEstimateResponse::default();
+    response.token_type = request.token_type.clone();
+    response.fiat_amount_minor = request.fiat_value;
+    response.fiat_currency = request.fiat_currency.clone();
+    response.status = EstimateFlags::SUCCESS | EstimateFlags::EXCHANGE_RATE_UNAVAILABLE;
+    response.message = Some(format!("Unable to fetch exchange rate: {}", e));
+    response
+}
+
 fn wei_to_eth(wei: u128) -> f64 {
     wei as f64 / 1e18
 }
@@ -209,6 +318,7 @@ fn wei_to_eth(wei: u128) -> f64 {
 fn early_exit_if_wallets_invalid(request: &TransactionEstimateRequest) -> Option<TransactionEstimateResponse> {
     if !validate_wallet_address(&request.sender_address)
         || !validate_wallet_address(&request.recipient_address)
+        || !request.splits.iter().all(|split| validate_wallet_address(&split.recipient_address))
     {
         let mut response = TransactionEstimateResponse::default();
         response.token_type = request.token_type.clone();
@@ -255,7 +365,7 @@ mod tests {
     use foxy_shared::services::authentication::generate_tokens;
 use dotenv::dotenv;
 use super::*;
-    use foxy_shared::models::transactions::TokenType;
+    use foxy_shared::models::transactions::{Network, PriorityLevel};
     use foxy_shared::services::cloudwatch_services::create_cloudwatch_client;
     use foxy_shared::utilities::test::{get_cognito_client_with_assumed_role, get_dynamodb_client_with_assumed_role, init_tracing};
 
@@ -276,7 +386,10 @@ use super::*;
             sender_address: "0xC4027B0df7B2d1fAf281169D78E252f8D86E4cdC".to_string(),
             recipient_address: "0x1aB7Bc9CA7586fa0D9c6293A27d5c001622E08C7".to_string(),
             token_type: TokenType::ETH,
+            network: Network::OptimismSepolia,
+            priority_level: PriorityLevel::Standard,
             transaction_value: None,
+            splits: Vec::new(),
         };
 
         match estimate_transaction(&access_token, valid_request.clone(), &dynamodb_client, &create_cloudwatch_client().await).await {
@@ -294,18 +407,20 @@ use super::*;
                 assert!(response.exchange_rate > 0.0, "exchange_rate should not be empty");
 
                 // Fee breakdown checks
-                assert!(!response.fees.service_fee_wei.is_empty(), "service_fee_wei should not be empty");
+                assert!(response.fees.service_fee_wei.is_some(), "service_fee_wei should be set for ETH");
                 assert!(!response.fees.network_fee_wei.is_empty(), "network_fee_wei should not be empty");
-                assert!(!response.fees.total_fee_wei.is_empty(), "total_fee_wei should not be empty");
-                assert!(!response.fees.service_fee_eth.is_empty(), "service_fee_eth should not be empty");
+                assert!(response.fees.total_fee_wei.is_some(), "total_fee_wei should be set for ETH");
+                assert!(response.fees.service_fee_eth.is_some(), "service_fee_eth should be set for ETH");
                 assert!(!response.fees.network_fee_eth.is_empty(), "network_fee_eth should not be empty");
-                assert!(!response.fees.total_fee_eth.is_empty(), "total_fee_eth should not be empty");
+                assert!(response.fees.total_fee_eth.is_some(), "total_fee_eth should be set for ETH");
+                assert!(!response.fees.total_fee_minor.is_empty(), "total_fee_minor should not be empty");
 
                 // Gas pricing checks
                 assert!(!response.gas.estimated_gas.is_empty(), "estimated_gas should not be empty");
                 assert!(!response.gas.gas_price.is_empty(), "gas_price should not be empty");
                 assert!(!response.gas.max_fee_per_gas.is_empty(), "max_fee_per_gas should not be empty");
                 assert!(!response.gas.max_priority_fee_per_gas.is_empty(), "max_priority_fee_per_gas should not be empty");
+                assert_eq!(response.speeds.len(), PriorityLevel::ALL.len(), "every priority level should be priced");
 
                 // Status flags
                 assert!(
@@ -345,7 +460,10 @@ use super::*;
             sender_address: "0xC4027B0df7B2d1fAf281169D78E252f8D86E4cdC".to_string(),
             recipient_address: "0x1aB7Bc9CA7586fa0D9c6293A27d5c001622E08C7".to_string(),
             token_type: TokenType::ETH,
+            network: Network::OptimismSepolia,
+            priority_level: PriorityLevel::Standard,
             transaction_value: None,
+            splits: Vec::new(),
         };
 
         let response = estimate_transaction(&access_token, request.clone(), &dynamodb_client, &create_cloudwatch_client().await).await
@@ -357,11 +475,11 @@ use super::*;
             "wei_amount should be > 0 even for small fiat values"
         );
         assert!(
-            response.fees.service_fee_wei.parse::<u128>().unwrap_or(0) > 0,
+            response.fees.service_fee_units.parse::<u128>().unwrap_or(0) > 0,
             "service_fee_wei should still apply"
         );
         assert!(
-            response.fees.fee_tx_value_wei.parse::<u128>().unwrap_or(0) == 0,
+            response.fees.fee_tx_value_units.parse::<u128>().unwrap_or(0) == 0,
             "fee_tx_value_wei should be 0 where the gas fee is > tx value"
         );
         assert!(
@@ -387,14 +505,17 @@ use super::*;
             sender_address: "0xC4027B0df7B2d1fAf281169D78E252f8D86E4cdC".to_string(),
             recipient_address: "0x1aB7Bc9CA7586fa0D9c6293A27d5c001622E08C7".to_string(),
             token_type: TokenType::ETH,
+            network: Network::OptimismSepolia,
+            priority_level: PriorityLevel::Standard,
             transaction_value: None,
+            splits: Vec::new(),
         };
 
         let response = estimate_transaction(&access_token, request.clone(), &dynamodb_client, &create_cloudwatch_client().await).await
             .expect("Expected successful estimate");
 
         let wei = response.wei_amount.parse::<u128>().unwrap_or(0);
-        let service_fee = response.fees.service_fee_wei.parse::<u128>().unwrap_or(0);
+        let service_fee = response.fees.service_fee_units.parse::<u128>().unwrap_or(0);
 
         assert!(wei > 10u128.pow(18), "Should be worth more than 1 ETH");
         assert!(service_fee > 0, "Large value should have a substantial service fee");
@@ -407,6 +528,23 @@ use super::*;
     }
 
     
+    #[test]
+    fn a_supplied_amount_is_sent_exactly() {
+        let usdc: TokenConfig = serde_json::from_value(serde_json::json!({
+            "symbol": "USDC",
+            "decimals": 6,
+            "contract_address": "0x5fd84259d66Cd46123540766Be93DFE6D43130D7",
+            "price_feed": { "base": "USDC", "sources": ["coinbase"] }
+        })).unwrap();
+
+        // 2.5 USDC at £0.80 each, whatever fiat value came with it
+        assert_eq!(price_amount(0, Some(2_500_000), 0.8, &usdc), (2_500_000, 200));
+        assert_eq!(price_amount(150, Some(2_500_123), 0.8, &usdc), (2_500_123, 200));
+
+        // Without one the amount is priced from the fiat value
+        assert_eq!(price_amount(160, None, 0.8, &usdc), (2_000_000, 160));
+    }
+
     #[test]
     fn test_fiat_to_wei_conversion() {
         struct TestCase {
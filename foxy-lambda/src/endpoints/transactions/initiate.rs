@@ -12,7 +12,10 @@ use foxy_shared::services::cloudwatch_services::{create_cloudwatch_client, emit_
 use foxy_shared::utilities::authentication::with_valid_user;
 use foxy_shared::utilities::requests::extract_bearer_token;
 use foxy_shared::utilities::responses::{error_response, success_response};
-use foxy_shared::utilities::config::get_transaction_event_table;
+use foxy_shared::utilities::config::{get_transaction_event_table, is_supported_network};
+use foxy_shared::utilities::token_registry::token_registry_for;
+use foxy_shared::database::event_store::EventStore;
+use foxy_shared::database::nonce_leases::NonceLeases;
 use foxy_shared::database::transaction_event::TransactionEventManager;
 use foxy_shared::database::client::get_dynamodb_client;
 use aws_sdk_cognitoidentityprovider::Client as CognitoClient;
@@ -73,19 +76,32 @@ async fn handle_transaction_initiation(
 
         match TransactionBundle::from_request(user_id, request, cognito_client,dynamo_db_client).await {
             Ok(bundle) => {
+                //We need to return unsigned transactions; build them first so a leg we cannot
+                //encode never reaches the event store
+                let unsigned_fee_tx = UnsignedTransaction::try_from(&bundle.fee_tx)?;
+                let unsigned_main_tx = UnsignedTransaction::try_from(&bundle.main_tx)?;
+                let unsigned_split_txs = bundle.split_txs.iter()
+                    .map(UnsignedTransaction::try_from)
+                    .collect::<Result<Vec<_>, _>>()?;
+
                 let manager = TransactionEventManager::new(
                                                         Arc::new(dynamo_db_client.clone()),
                                                         get_transaction_event_table(),
                                                     );
-                manager.persist_initial_event(&bundle).await?;
+                if let Err(e) = manager.persist_initial_event(&bundle).await {
+                    // Nothing will cancel or expire a bundle that was never stored
+                    let leases = NonceLeases::new(Arc::new(dynamo_db_client.clone()), get_transaction_event_table());
+                    if let Err(release) = leases.release(&bundle).await {
+                        log::warn!("Failed to release nonces of bundle {}: {}", bundle.bundle_id, release);
+                    }
+                    return Err(e.into());
+                }
 
-                //We need to return unsigned transactions
-                let unsigned_fee_tx = UnsignedTransaction::from(&bundle.fee_tx);
-                let unsigned_main_tx = UnsignedTransaction::from(&bundle.main_tx);
                 let unsigned_pair = UnsignedTransactionPair{
                     bundle_id: bundle.bundle_id,
                     fee: unsigned_fee_tx,
                     main: unsigned_main_tx,
+                    splits: unsigned_split_txs,
                 };
 
                 // Log metrics
@@ -95,6 +111,11 @@ async fn handle_transaction_initiation(
 
                 Ok(unsigned_pair)
             }
+            // The client has to fetch a fresh estimate, so it needs to know
+            Err(err @ TransactionError::InvalidQuote(_)) => {
+                log::warn!("Rejected quote: {:?}", err);
+                Err(err)
+            }
             Err(err) => {
                 log::error!("Transaction creation failed: {:?}", err);
                 Err(TransactionError::InvalidRequest)
@@ -112,6 +133,12 @@ fn validate_transaction_request(request: &TransactionRequest) -> Result<(), Tran
         return Err(TransactionError::InvalidAmount);
     }
 
+    if !is_supported_network(&request.network) {
+        return Err(TransactionError::InvalidNetwork);
+    }
+
+    token_registry_for(&request.network).resolve(&request.token_type)?;
+
     if !is_valid_address(&request.sender_address) || !is_valid_address(&request.recipient_address) {
         return Err(TransactionError::InvalidAddress);
     }
@@ -162,10 +189,11 @@ fn validate_transaction_request(request: &TransactionRequest) -> Result<(), Tran
 #[cfg(test)]
 mod tests {
     use super::*;
-    use foxy_shared::models::transactions::{TransactionRequest, TokenType, GasPricing};
+    use foxy_shared::models::transactions::{TransactionRequest, TokenType, GasPricing, Network, PriorityLevel};
     use foxy_shared::models::user_device::UserDevice;
     use foxy_shared::services::authentication::generate_tokens;
     use foxy_shared::utilities::config;
+    use foxy_shared::utilities::quote::Quote;
     use foxy_shared::utilities::test::{get_cognito_client_with_assumed_role, get_dynamodb_client_with_assumed_role, init_tracing};
 
     #[tokio::test]
@@ -191,6 +219,8 @@ mod tests {
             fiat_currency_code: "GBP".to_string(),
             transaction_value: 1_000_000_000_000_000u128, // 0.001 ETH in wei
             token_type: TokenType::ETH,
+            network: Network::OptimismSepolia,
+            priority_level: PriorityLevel::Standard,
             message: Some("Here’s £50".to_string()),
             exchange_rate: 2000.0,
             service_fee: 1000,
@@ -205,6 +235,29 @@ mod tests {
             user_device: UserDevice::new("0eacf2aa-e788-4b54-bc1c-a95a05fc7d62".to_string(),
             "f30M3RyRSpKlDY7lbJBBKu:APA91bGH7m_zXvyYsCHdE5L7DDaT4ObWIe9y_5d3JKANJiM0zC6BJYcrTn1h9cfcaFgpK_hg2Sc32V951WQbP_kuv6ZwjITkhORb7G2pzx1RvbSsVyiu5eI".to_string(),
             "Android".to_string(), "0.1.0".to_string()),
+            quote: None,
+        };
+        let quote = Quote {
+            quote_id: Quote::new_id(),
+            user_id: test_user_id.to_string(),
+            sender_address: request.sender_address.clone(),
+            recipient_address: request.recipient_address.clone(),
+            token_type: request.token_type.clone(),
+            network: request.network.clone(),
+            transaction_value: request.transaction_value,
+            fiat_value: request.fiat_value,
+            fiat_currency: request.fiat_currency_code.clone(),
+            splits: Vec::new(),
+            exchange_rate: request.exchange_rate,
+            service_fee: request.service_fee,
+            service_fee_minor: request.service_fee_minor,
+            priority_level: request.priority_level.clone(),
+            gas_pricing: request.gas_pricing.clone().unwrap(),
+            expires_at: chrono::Utc::now() + chrono::Duration::minutes(5),
+        };
+        let request = TransactionRequest {
+            quote: Some(quote.sign(&config::get_quote_signing_key())?),
+            ..request
         };
 
         match handle_transaction_initiation(access_token.as_str(),
@@ -3,3 +3,8 @@ pub mod estimate;
 pub mod commit;
 pub mod history;
 pub mod single;
+pub mod cancel;
+pub mod request;
+pub mod resolve;
+pub mod speed_up;
+pub mod replace;
@@ -0,0 +1,123 @@
+use std::sync::Arc;
+use std::time::Instant;
+use aws_sdk_cloudwatch::Client as CloudWatchClient;
+use aws_sdk_cloudwatch::types::StandardUnit;
+use aws_sdk_dynamodb::Client as DynamoDbClient;
+use http::{Response, StatusCode};
+use lambda_http::{Body, Request};
+use serde_json::{json, Value};
+use foxy_shared::database::client::get_dynamodb_client;
+use foxy_shared::database::errors::DynamoDbError;
+use foxy_shared::database::event_store::{retry_on_conflict, EventStore};
+use foxy_shared::database::transaction_event::TransactionEventManager;
+use foxy_shared::models::errors::TransactionError;
+use foxy_shared::models::transactions::{TransactionEvent, UnsignedTransaction};
+use foxy_shared::services::cloudwatch_services::{create_cloudwatch_client, emit_broadcast_queue_failure, emit_metric};
+use foxy_shared::services::queue_services::{get_sqs_client, push_to_broadcast_queue};
+use foxy_shared::utilities::authentication::with_valid_user;
+use foxy_shared::utilities::config::{get_broadcast_queue, get_transaction_event_table};
+use foxy_shared::utilities::replacement;
+use foxy_shared::utilities::requests::extract_bearer_token;
+use foxy_shared::utilities::responses::{error_response, response_with_code, success_response};
+use foxy_shared::utilities::signed_tx;
+use crate::models::transactions::SignedReplacementPayload;
+
+/// Records a re-signed replacement for the bundle's pending leg and queues it for broadcast.
+pub async fn handler(event: Request, body: Value, bundle_id: &str) -> Result<Response<Body>, lambda_http::Error> {
+    let token = extract_bearer_token(&event);
+    let payload: SignedReplacementPayload = match serde_json::from_value(body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            log::error!("Deserialization error: {:?}", e);
+            return error_response(format!("{:?}", TransactionError::InvalidRequest));
+        }
+    };
+
+    let cloudwatch_client = create_cloudwatch_client().await;
+    let dynamo_db_client = get_dynamodb_client().await;
+    log::info!("Replacing the pending transaction of bundle {}", bundle_id);
+
+    match token {
+        None => error_response("Missing authorization token"),
+        Some(token) => match replace_transaction(token, &payload, &dynamo_db_client, &cloudwatch_client, bundle_id).await {
+            Ok(replaced) => {
+                let json = json!({
+                    "bundle_id": replaced.bundle_id,
+                    "status": replaced.bundle_status,
+                    "leg": replaced.leg,
+                    "transaction_hash": replaced.leg.map(|leg| replaced.bundle_snapshot.leg(leg).transaction_hash.clone()),
+                    "message": "Replacement queued for broadcast."});
+
+                success_response(json)
+            }
+            Err(TransactionError::NotFound(msg)) => response_with_code(&msg, StatusCode::NOT_FOUND),
+            Err(TransactionError::Unauthorized) => response_with_code("Not the owner of this transaction", StatusCode::FORBIDDEN),
+            Err(err @ TransactionError::NotReplaceable(_)) => response_with_code(err.to_string(), StatusCode::CONFLICT),
+            Err(err @ TransactionError::InvalidStateTransition { .. }) => response_with_code(err.to_string(), StatusCode::CONFLICT),
+            Err(err) => error_response(format!("{:?}", err)),
+        },
+    }
+}
+
+async fn replace_transaction(
+    token: &str,
+    payload: &SignedReplacementPayload,
+    dynamo_db_client: &DynamoDbClient,
+    cloudwatch_client: &CloudWatchClient,
+    bundle_id: &str,
+) -> Result<TransactionEvent, TransactionError> {
+    with_valid_user(token, |user_id| async move {
+        let start = Instant::now();
+        let tem = TransactionEventManager::new(Arc::new(dynamo_db_client.clone()), get_transaction_event_table());
+
+        let last_event = tem.get_latest_event(bundle_id).await.map_err(|e| match e {
+            DynamoDbError::NotFound => TransactionError::NotFound(format!("No transaction found for bundle {}", bundle_id)),
+            other => other.into(),
+        })?;
+
+        let bundle = &last_event.bundle_snapshot;
+        if bundle.user_id != user_id {
+            return Err(TransactionError::Unauthorized);
+        }
+
+        let leg = replacement::pending_leg(bundle)
+            .ok_or_else(|| TransactionError::NotReplaceable(format!("Bundle {} has no pending transaction", bundle_id)))?;
+
+        // The same transfer and nonce, at no less than the fees a node will take in its place
+        let mut intent = bundle.leg(leg).clone();
+        let (max_fee, max_priority_fee) = replacement::minimum_replacement_fees(&intent);
+        intent.max_fee_per_gas = Some(max_fee);
+        intent.max_priority_fee_per_gas = Some(max_priority_fee);
+
+        // The user's wallet signs both legs; it is the fee leg's sender
+        let (max_fee, max_priority_fee) = signed_tx::verify_replacement(
+            leg,
+            &payload.signed_tx,
+            &UnsignedTransaction::try_from(&intent)?,
+            &bundle.fee_tx.sender_address,
+        )?;
+
+        // A confirmation landing first wins; the retry then sees it and rejects the replacement.
+        let replaced = retry_on_conflict(tem.as_ref(), last_event.clone(), |latest| {
+            let tem = tem.clone();
+            let signed_tx = payload.signed_tx.clone();
+            async move { TransactionEvent::on_replace(&latest, leg, &signed_tx, max_fee, max_priority_fee, tem).await }
+        }).await?;
+
+        log::info!(
+            "Bundle {} {} leg replaced: {:?} -> {:?}",
+            bundle_id, leg, last_event.bundle_snapshot.leg(leg).transaction_hash, replaced.bundle_snapshot.leg(leg).transaction_hash
+        );
+
+        let sqs_client = get_sqs_client().await?;
+        if let Err(err) = push_to_broadcast_queue(&sqs_client, &get_broadcast_queue(), bundle_id, &user_id).await {
+            emit_broadcast_queue_failure(cloudwatch_client);
+            log::error!("Failed to queue replacement of {} for broadcast: {}", bundle_id, err);
+        }
+
+        emit_metric(cloudwatch_client, "ReplaceLatency", start.elapsed().as_millis() as f64, StandardUnit::Milliseconds).await;
+        emit_metric(cloudwatch_client, "ReplacedCount", 1.0, StandardUnit::Count).await;
+
+        Ok(replaced)
+    }).await
+}
@@ -0,0 +1,74 @@
+use std::time::Instant;
+use aws_sdk_cloudwatch::Client as CloudWatchClient;
+use aws_sdk_cloudwatch::types::StandardUnit;
+use aws_sdk_cognitoidentityprovider::Client as CognitoClient;
+use http::Response;
+use lambda_http::{Body, Request};
+use serde_json::Value;
+use foxy_shared::models::errors::TransactionError;
+use foxy_shared::services::cloudwatch_services::{create_cloudwatch_client, emit_metric};
+use foxy_shared::services::cognito_services::{get_cognito_client, get_user_data};
+use foxy_shared::utilities::authentication::with_valid_user;
+use foxy_shared::utilities::config::is_supported_network;
+use foxy_shared::utilities::eip681::PaymentRequest;
+use foxy_shared::utilities::requests::extract_bearer_token;
+use foxy_shared::utilities::responses::{error_response, success_response};
+use foxy_shared::utilities::token_registry::token_registry_for;
+use crate::models::transactions::{PaymentRequestParams, PaymentRequestUri};
+
+/// Builds an EIP-681 URI asking to be paid into the caller's wallet, for display as a QR code.
+pub async fn handler(event: Request, body: Value) -> Result<Response<Body>, lambda_http::Error> {
+    let token = extract_bearer_token(&event);
+    let params: PaymentRequestParams = match serde_json::from_value(body) {
+        Ok(params) => params,
+        Err(e) => {
+            log::error!("Deserialization error: {:?}", e);
+            return error_response(format!("{:?}", TransactionError::InvalidRequest));
+        }
+    };
+
+    let cognito_client = get_cognito_client().await;
+    let cloudwatch_client = create_cloudwatch_client().await;
+
+    match token {
+        Some(token) => match create_payment_request(token, params, &cognito_client, &cloudwatch_client).await {
+            Ok(response) => success_response(response),
+            Err(err) => error_response(format!("{:?}", err)),
+        },
+        None => error_response("Missing authorization token"),
+    }
+}
+
+async fn create_payment_request(
+    token: &str,
+    params: PaymentRequestParams,
+    cognito_client: &CognitoClient,
+    cloudwatch_client: &CloudWatchClient,
+) -> Result<PaymentRequestUri, TransactionError> {
+    with_valid_user(token, |user_id| async move {
+        let start_time = Instant::now();
+
+        if !is_supported_network(&params.network) {
+            return Err(TransactionError::InvalidNetwork);
+        }
+        let token_config = token_registry_for(&params.network).resolve(&params.token_type)?;
+        let amount = params.amount
+            .map(|a| a.parse::<u128>().map_err(|_| TransactionError::InvalidAmount))
+            .transpose()?;
+
+        let wallet_address = get_user_data(cognito_client, &user_id)
+            .await
+            .map_err(|e| TransactionError::NotFound(format!("Failed to fetch user data: {:?}", e)))?
+            .wallet_address
+            .ok_or_else(|| TransactionError::NotFound(format!("No wallet for user {}", user_id)))?;
+
+        let uri = PaymentRequest::new(&wallet_address, token_config, &params.network)
+            .with_amount(amount)
+            .with_memo(params.memo)
+            .to_uri();
+
+        let duration = start_time.elapsed().as_secs_f64();
+        emit_metric(cloudwatch_client, "CreatePaymentRequest", duration, StandardUnit::Seconds).await;
+        Ok(PaymentRequestUri { uri })
+    }).await
+}
@@ -0,0 +1,91 @@
+use std::time::Instant;
+use aws_sdk_cloudwatch::Client as CloudWatchClient;
+use aws_sdk_cloudwatch::types::StandardUnit;
+use aws_sdk_cognitoidentityprovider::Client as CognitoClient;
+use http::Response;
+use lambda_http::{Body, Request};
+use serde_json::Value;
+use foxy_shared::models::errors::TransactionError;
+use foxy_shared::models::transactions::{PriorityLevel, TransactionEstimateRequest};
+use foxy_shared::services::cloudwatch_services::{create_cloudwatch_client, emit_metric};
+use foxy_shared::services::cognito_services::{get_cognito_client, get_user_data};
+use foxy_shared::utilities::authentication::with_valid_user;
+use foxy_shared::utilities::eip681::PaymentRequest;
+use foxy_shared::utilities::exchange::ExchangeRateManager;
+use foxy_shared::utilities::requests::extract_bearer_token;
+use foxy_shared::utilities::responses::{error_response, success_response};
+use crate::models::transactions::{PaymentRequestUri, ResolvedPaymentRequest};
+
+/// Turns a scanned EIP-681 URI into an estimate request paying it from the caller's wallet.
+pub async fn handler(event: Request, body: Value) -> Result<Response<Body>, lambda_http::Error> {
+    let token = extract_bearer_token(&event);
+    let scanned: PaymentRequestUri = match serde_json::from_value(body) {
+        Ok(scanned) => scanned,
+        Err(e) => {
+            log::error!("Deserialization error: {:?}", e);
+            return error_response(format!("{:?}", TransactionError::InvalidRequest));
+        }
+    };
+
+    let cognito_client = get_cognito_client().await;
+    let cloudwatch_client = create_cloudwatch_client().await;
+
+    match token {
+        Some(token) => match resolve_payment_request(token, &scanned.uri, &cognito_client, &cloudwatch_client).await {
+            Ok(response) => success_response(response),
+            Err(err) => error_response(format!("{:?}", err)),
+        },
+        None => error_response("Missing authorization token"),
+    }
+}
+
+async fn resolve_payment_request(
+    token: &str,
+    uri: &str,
+    cognito_client: &CognitoClient,
+    cloudwatch_client: &CloudWatchClient,
+) -> Result<ResolvedPaymentRequest, TransactionError> {
+    with_valid_user(token, |user_id| async move {
+        let start_time = Instant::now();
+
+        let request = PaymentRequest::parse(uri).map_err(TransactionError::InvalidPaymentRequest)?;
+        let (network, token_config) = request.resolve()?;
+
+        let user_profile = get_user_data(cognito_client, &user_id)
+            .await
+            .map_err(|e| TransactionError::NotFound(format!("Failed to fetch user data: {:?}", e)))?;
+        let sender_address = user_profile.wallet_address
+            .ok_or_else(|| TransactionError::NotFound(format!("No wallet for user {}", user_id)))?;
+        let fiat_currency = user_profile.currency.unwrap_or_else(|| "GBP".to_string());
+
+        if sender_address.eq_ignore_ascii_case(&request.recipient) {
+            return Err(TransactionError::SameSenderReceiver);
+        }
+
+        // The estimate sends a requested amount exactly; the fiat value is only for display. An
+        // amount-less request has neither, and is flagged for the payer to fill in.
+        let fiat_value = match request.amount {
+            Some(amount) => {
+                let rate = ExchangeRateManager::new().get_latest_rate(&fiat_currency, token_config).await?;
+                (token_config.from_base_units(amount) * rate * 100.0).round() as u64
+            }
+            None => 0,
+        };
+
+        let estimate_request = TransactionEstimateRequest {
+            fiat_value,
+            fiat_currency,
+            sender_address,
+            recipient_address: request.recipient.clone(),
+            token_type: token_config.symbol.clone(),
+            network,
+            priority_level: PriorityLevel::default(),
+            transaction_value: request.amount,
+            splits: Vec::new(),
+        };
+
+        let duration = start_time.elapsed().as_secs_f64();
+        emit_metric(cloudwatch_client, "ResolvePaymentRequest", duration, StandardUnit::Seconds).await;
+        Ok(ResolvedPaymentRequest { estimate_request, amount_required: request.amount.is_none(), memo: request.memo })
+    }).await
+}
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+use std::time::Instant;
+use aws_sdk_cloudwatch::Client as CloudWatchClient;
+use aws_sdk_cloudwatch::types::StandardUnit;
+use aws_sdk_dynamodb::Client as DynamoDbClient;
+use http::{Response, StatusCode};
+use lambda_http::{Body, Request};
+use serde_json::Value;
+use foxy_shared::database::client::get_dynamodb_client;
+use foxy_shared::database::errors::DynamoDbError;
+use foxy_shared::database::event_store::EventStore;
+use foxy_shared::database::transaction_event::TransactionEventManager;
+use foxy_shared::models::errors::TransactionError;
+use foxy_shared::models::transactions::UnsignedTransaction;
+use foxy_shared::services::cloudwatch_services::{create_cloudwatch_client, emit_metric};
+use foxy_shared::utilities::authentication::with_valid_user;
+use foxy_shared::utilities::config::get_transaction_event_table;
+use foxy_shared::utilities::gas::fetch_gas_from_api;
+use foxy_shared::utilities::replacement;
+use foxy_shared::utilities::requests::extract_bearer_token;
+use foxy_shared::utilities::responses::{error_response, response_with_code, success_response};
+use crate::models::transactions::{SpeedUpQuote, SpeedUpRequest};
+
+/// Prices a replacement for the bundle's pending leg, for the wallet to sign and send to
+/// `/transactions/{bundle_id}/replace`.
+pub async fn handler(event: Request, body: Value, bundle_id: &str) -> Result<Response<Body>, lambda_http::Error> {
+    let token = extract_bearer_token(&event);
+    let request: SpeedUpRequest = match serde_json::from_value(body) {
+        Ok(request) => request,
+        Err(e) => {
+            log::error!("Deserialization error: {:?}", e);
+            return error_response(format!("{:?}", TransactionError::InvalidRequest));
+        }
+    };
+
+    let cloudwatch_client = create_cloudwatch_client().await;
+    let dynamo_db_client = get_dynamodb_client().await;
+    log::info!("Pricing a speed-up of bundle {}", bundle_id);
+
+    match token {
+        None => error_response("Missing authorization token"),
+        Some(token) => match price_replacement(token, request, &dynamo_db_client, &cloudwatch_client, bundle_id).await {
+            Ok(quote) => success_response(quote),
+            Err(TransactionError::NotFound(msg)) => response_with_code(&msg, StatusCode::NOT_FOUND),
+            Err(TransactionError::Unauthorized) => response_with_code("Not the owner of this transaction", StatusCode::FORBIDDEN),
+            Err(err @ TransactionError::NotReplaceable(_)) => response_with_code(err.to_string(), StatusCode::CONFLICT),
+            Err(err) => error_response(format!("{:?}", err)),
+        },
+    }
+}
+
+async fn price_replacement(
+    token: &str,
+    request: SpeedUpRequest,
+    dynamo_db_client: &DynamoDbClient,
+    cloudwatch_client: &CloudWatchClient,
+    bundle_id: &str,
+) -> Result<SpeedUpQuote, TransactionError> {
+    with_valid_user(token, |user_id| async move {
+        let start = Instant::now();
+        let tem = TransactionEventManager::new(Arc::new(dynamo_db_client.clone()), get_transaction_event_table());
+
+        let last_event = tem.get_latest_event(bundle_id).await.map_err(|e| match e {
+            DynamoDbError::NotFound => TransactionError::NotFound(format!("No transaction found for bundle {}", bundle_id)),
+            other => other.into(),
+        })?;
+
+        let bundle = &last_event.bundle_snapshot;
+        if bundle.user_id != user_id {
+            return Err(TransactionError::Unauthorized);
+        }
+
+        let leg = replacement::pending_leg(bundle)
+            .ok_or_else(|| TransactionError::NotReplaceable(format!("Bundle {} has no pending transaction", bundle_id)))?;
+        let tx = bundle.leg(leg);
+        let priority_level = request.priority_level.unwrap_or_else(|| tx.priority_level.clone());
+
+        // The user's wallet sends both legs; it is the fee leg's sender
+        let market = fetch_gas_from_api(
+            &bundle.fee_tx.sender_address,
+            &tx.recipient_address,
+            Some(tx.transaction_value),
+            &tx.token_type,
+            &tx.network,
+            &priority_level,
+        ).await?;
+
+        let repriced = replacement::reprice(tx, market.max_fee_per_gas, market.max_priority_fee_per_gas)?;
+        log::info!(
+            "Bundle {} {} leg repriced from {:?} to {:?} max fee per gas",
+            bundle_id, leg, tx.max_fee_per_gas, repriced.max_fee_per_gas
+        );
+
+        emit_metric(cloudwatch_client, "SpeedUpQuoteLatency", start.elapsed().as_millis() as f64, StandardUnit::Milliseconds).await;
+
+        Ok(SpeedUpQuote {
+            bundle_id: bundle_id.to_string(),
+            leg,
+            replaces: tx.transaction_hash.clone(),
+            transaction: UnsignedTransaction::try_from(&repriced)?,
+        })
+    }).await
+}
@@ -1,27 +1,45 @@
+use std::str::FromStr;
 use std::time::Instant;
 use http::Response;
-use lambda_http::{Body, Request};
+use lambda_http::{Body, Request, RequestExt};
 use foxy_shared::services::cognito_services::{get_cognito_client, get_user_data};
 use foxy_shared::models::errors::WalletError;
 use foxy_shared::models::wallet::{BalanceResponse, FiatBalance};
 use foxy_shared::utilities::authentication::with_valid_user;
 use foxy_shared::utilities::requests::extract_bearer_token;
 use foxy_shared::utilities::responses::{error_response, success_response};
-use foxy_shared::utilities::wallet::{format_wei_to_eth_f64, format_wei_to_eth_string, get_wallet_balance};
+use foxy_shared::utilities::wallet::{format_token_units_f64, format_token_units_string, get_token_balance};
 use foxy_shared::services::cloudwatch_services::{create_cloudwatch_client, emit_metric};
 use aws_sdk_cloudwatch::Client as CloudWatchClient;
 use aws_sdk_cloudwatch::types::StandardUnit;
 use aws_sdk_cognitoidentityprovider::Client as CognitoClient;
-use foxy_shared::models::transactions::TokenType;
+use foxy_shared::models::transactions::{Network, TokenType};
+use foxy_shared::utilities::config::{get_network, is_supported_network};
 use foxy_shared::utilities::exchange::ExchangeRateManager;
+use foxy_shared::utilities::token_registry::{token_registry_for, TokenConfig};
 
 pub async fn handler(event: Request) -> Result<Response<Body>, lambda_http::Error> {
     let token = extract_bearer_token(&event);
+    let token_type = match event.query_string_parameters().first("token").map(TokenType::from_str) {
+        None => TokenType::ETH,
+        Some(Ok(token_type)) => token_type,
+        Some(Err(e)) => return error_response(e),
+    };
+    let network = match event.query_string_parameters().first("network").map(Network::from_str) {
+        None => get_network(),
+        Some(Ok(network)) if is_supported_network(&network) => network,
+        Some(Ok(network)) => return error_response(format!("Unsupported network: {}", network)),
+        Some(Err(e)) => return error_response(e),
+    };
+    let token_config = match token_registry_for(&network).resolve(&token_type) {
+        Ok(token_config) => token_config,
+        Err(e) => return error_response(e.to_string()),
+    };
     let client = get_cognito_client().await;
     let cloudwatch_client = create_cloudwatch_client().await;
 
     match token {
-        Some(token) => match fetch_balance(token, &client, &cloudwatch_client).await {
+        Some(token) => match fetch_balance(token, token_config, &network, &client, &cloudwatch_client).await {
             Ok(response) => success_response(response),
             Err(err) => error_response(format!("{:?}", err)),
         },
@@ -29,7 +47,7 @@ pub async fn handler(event: Request) -> Result<Response<Body>, lambda_http::Erro
     }
 }
 
-async fn fetch_balance(token: &str, cognito_client: &CognitoClient, cloudwatch_client: &CloudWatchClient) -> Result<BalanceResponse, WalletError> {
+async fn fetch_balance(token: &str, token_config: &TokenConfig, network: &Network, cognito_client: &CognitoClient, cloudwatch_client: &CloudWatchClient) -> Result<BalanceResponse, WalletError> {
     with_valid_user(token, |user_id| async move {
         log::info!("Fetching balance for user: {}", user_id);
         let start_time = Instant::now();
@@ -41,24 +59,23 @@ async fn fetch_balance(token: &str, cognito_client: &CognitoClient, cloudwatch_c
         let wallet_address = user_profile.wallet_address.unwrap();
         let default_currency = user_profile.currency.unwrap_or_else(|| "GBP".to_string());
 
-        match get_wallet_balance(&wallet_address).await {
+        match get_token_balance(&wallet_address, token_config, network).await {
             Ok(balance) => {
-                let eth = format_wei_to_eth_f64(balance); // you'll need this as f64
-                let token_type = TokenType::ETH;
+                let amount = format_token_units_f64(balance, token_config);
 
                 let erm = ExchangeRateManager::new();
-                let rate = erm.get_latest_rate(&default_currency, &token_type)
+                let rate = erm.get_latest_rate(&default_currency, token_config)
                     .await
                     .map_err(|e| WalletError::Network(format!("Exchange rate error: {}", e)))?;
 
-                let fiat_value = eth * rate;
+                let fiat_value = amount * rate;
 
                 let duration = start_time.elapsed().as_secs_f64();
                 emit_metric(cloudwatch_client, "GetBalance", duration, StandardUnit::Seconds).await;
                 Ok(BalanceResponse {
                     wei: balance.to_string(),
-                    token: "ETH".to_string(),
-                    balance: format_wei_to_eth_string(balance, 6),
+                    token: token_config.symbol.to_string(),
+                    balance: format_token_units_string(balance, token_config, 6),
                     fiat: FiatBalance {
                         value: format!("{:.2}", fiat_value),
                         currency: default_currency,
@@ -89,7 +106,9 @@ mod tests {
         let access_token = token_result.access_token.expect("Access token missing");
         let cloudwatch_client = create_cloudwatch_client().await;
 
-        match fetch_balance(&access_token, &cognito_client, &cloudwatch_client).await {
+        let network = get_network();
+        let eth = token_registry_for(&network).resolve(&TokenType::ETH).unwrap();
+        match fetch_balance(&access_token, eth, &network, &cognito_client, &cloudwatch_client).await {
             Ok(balance) => {
                 println!("Balance: {:?}", balance);
                 assert!(balance.balance.len() > 0, "Balance does not exist");
@@ -3,13 +3,16 @@
 
 use std::fmt;
 use serde::{Deserialize, Serialize};
-use foxy_shared::models::transactions::UnsignedTransaction;
+use foxy_shared::models::transactions::{Network, PriorityLevel, TokenType, TransactionEstimateRequest, TransactionLeg, UnsignedTransaction};
+use foxy_shared::utilities::config::get_network;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct UnsignedTransactionPair {
     pub bundle_id: String,
     pub fee: UnsignedTransaction,
     pub main: UnsignedTransaction,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub splits: Vec<UnsignedTransaction>, // signed in this order, as split_signed_txs
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -17,6 +20,26 @@ pub struct SignedTransactionPayload {
     pub bundle_id: String,
     pub fee_signed_tx: String,   // RLP-encoded or hex string
     pub main_signed_tx: String,  // RLP-encoded or hex string
+    #[serde(default)]
+    pub split_signed_txs: Vec<String>, // one per split leg, in the order issued
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SpeedUpRequest {
+    pub priority_level: Option<PriorityLevel>, // defaults to the leg's own
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SpeedUpQuote {
+    pub bundle_id: String,
+    pub leg: TransactionLeg,
+    pub replaces: Option<String>, // hash of the pending transaction
+    pub transaction: UnsignedTransaction, // same nonce, higher fees
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SignedReplacementPayload {
+    pub signed_tx: String, // hex string
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -30,4 +53,25 @@ impl fmt::Display for SignedTransactionError {
             SignedTransactionError::InvalidPayload(msg) => write!(f, "Invalid payload: {}", msg),
         }
     }
-}
\ No newline at end of file
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PaymentRequestParams {
+    pub token_type: TokenType,
+    #[serde(default = "get_network")]
+    pub network: Network,
+    pub amount: Option<String>, // base units, stringified like the rest of the API
+    pub memo: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PaymentRequestUri {
+    pub uri: String, // also the QR payload
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResolvedPaymentRequest {
+    pub estimate_request: TransactionEstimateRequest,
+    /// The request named no amount, so the payer has to enter one before it can be estimated.
+    pub amount_required: bool,
+    pub memo: Option<String>,
+}
@@ -44,8 +44,22 @@ pub async fn handle_lambda(event: Request) -> Result<Response<Body>, lambda_http
         (POST, "/transactions/initiate") => transactions::initiate::handler(event, event_body).await,
         (POST, "/transactions/estimate") => transactions::estimate::handler(event, event_body).await,
         (POST, "/transactions/commit") => transactions::commit::handler(event, event_body).await,
+        (POST, "/transactions/request") => transactions::request::handler(event, event_body).await,
+        (POST, "/transactions/request/resolve") => transactions::resolve::handler(event, event_body).await,
         (GET, "/transactions/recent") => transactions::history::handler(event, event_body).await,
         (POST, "/transactions/recent") => transactions::history::handler(event, event_body).await,
+        (POST, _) if path.starts_with("/transactions/") && path.ends_with("/cancel") => {
+            let id = path.trim_start_matches("/transactions/").trim_end_matches("/cancel").to_string();
+            transactions::cancel::handler(event, &id).await
+        }
+        (POST, _) if path.starts_with("/transactions/") && path.ends_with("/speed-up") => {
+            let id = path.trim_start_matches("/transactions/").trim_end_matches("/speed-up").to_string();
+            transactions::speed_up::handler(event, event_body, &id).await
+        }
+        (POST, _) if path.starts_with("/transactions/") && path.ends_with("/replace") => {
+            let id = path.trim_start_matches("/transactions/").trim_end_matches("/replace").to_string();
+            transactions::replace::handler(event, event_body, &id).await
+        }
         (GET, _) if path.starts_with("/transactions/") => {
             let id = path.trim_start_matches("/transactions/").to_string();
             transactions::single::handler(event, &id).await
@@ -60,10 +60,13 @@ async fn test_transaction_estimate_success() -> Result<(), Box<dyn std::error::E
     // Fees must be included and non-empty
     let fees = &body["fees"];
     for field in &[
-        "service_fee_wei",
-        "service_fee_eth",
+        "service_fee_units",
+        "service_fee_amount",
         "network_fee_wei",
         "network_fee_eth",
+        "total_fee_minor",
+        "service_fee_wei",
+        "service_fee_eth",
         "total_fee_wei",
         "total_fee_eth",
         "max_priority_fee_per_gas",
@@ -0,0 +1,63 @@
+use std::sync::Arc;
+use aws_sdk_dynamodb::{Client as DynamoDbClient, types::AttributeValue};
+use aws_sdk_dynamodb::operation::put_item::PutItemError;
+use chrono::Utc;
+use crate::database::errors::DynamoDbError;
+use crate::models::transactions::TransactionEvent;
+
+/// Records which events have handed their bundle's next leg to the broadcast queue, in the
+/// event table, so the leg is queued once however often the event is seen.
+#[derive(Clone)]
+pub struct BroadcastClaims {
+    client: Arc<DynamoDbClient>,
+    table_name: String,
+}
+
+impl BroadcastClaims {
+    // table_name is the event log table, probably from get_transaction_event_table()
+    pub fn new(client: Arc<DynamoDbClient>, table_name: String) -> Self {
+        Self { client, table_name }
+    }
+
+    /// Claims queuing the leg that follows `event`. False if it was already claimed.
+    pub async fn claim(&self, event: &TransactionEvent) -> Result<bool, DynamoDbError> {
+        let result = self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .item("PK", AttributeValue::S(claim_partition(event)))
+            .item("SK", AttributeValue::S(claim_sort_key(event)))
+            .item("BundleID", AttributeValue::S(event.bundle_id.clone()))
+            .item("EventID", AttributeValue::S(event.event_id.clone()))
+            .item("QueuedAt", AttributeValue::S(Utc::now().to_rfc3339()))
+            .condition_expression("attribute_not_exists(SK)")
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(e) if matches!(e.as_service_error(), Some(PutItemError::ConditionalCheckFailedException(_))) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Gives up a claim whose message never reached the queue, so it can be claimed again.
+    pub async fn release(&self, event: &TransactionEvent) -> Result<(), DynamoDbError> {
+        self.client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("PK", AttributeValue::S(claim_partition(event)))
+            .key("SK", AttributeValue::S(claim_sort_key(event)))
+            .send()
+            .await?;
+        Ok(())
+    }
+}
+
+fn claim_partition(event: &TransactionEvent) -> String {
+    format!("Broadcast#{}", event.bundle_id)
+}
+
+/// Zero-padded so claims sort by sequence.
+fn claim_sort_key(event: &TransactionEvent) -> String {
+    format!("After#{:020}", event.sequence)
+}
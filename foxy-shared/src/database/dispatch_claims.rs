@@ -0,0 +1,88 @@
+use std::fmt;
+use std::sync::Arc;
+use aws_sdk_dynamodb::{Client as DynamoDbClient, types::AttributeValue};
+use aws_sdk_dynamodb::operation::delete_item::DeleteItemError;
+use aws_sdk_dynamodb::operation::put_item::PutItemError;
+use chrono::Utc;
+use crate::database::errors::DynamoDbError;
+
+const CLAIM_SORT_KEY: &str = "Claim";
+
+/// What a bundle was claimed for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dispatch {
+    Send,
+    Cancel,
+}
+
+impl fmt::Display for Dispatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dispatch::Send => write!(f, "Send"),
+            Dispatch::Cancel => write!(f, "Cancel"),
+        }
+    }
+}
+
+/// Settles the race between sending a bundle and cancelling it, in the event table. The
+/// broadcaster claims the bundle before its first leg reaches the chain, and a cancel claims
+/// it before recording the cancellation; whichever comes second backs off. Nothing else
+/// orders the two, since the Broadcast event is only appended once the leg is sent.
+#[derive(Clone)]
+pub struct DispatchClaims {
+    client: Arc<DynamoDbClient>,
+    table_name: String,
+}
+
+impl DispatchClaims {
+    // table_name is the event log table, probably from get_transaction_event_table()
+    pub fn new(client: Arc<DynamoDbClient>, table_name: String) -> Self {
+        Self { client, table_name }
+    }
+
+    /// Claims the bundle for `dispatch`. False if it was claimed for the other one. Claiming
+    /// again for the same one succeeds, so every leg and every retry can claim.
+    pub async fn claim(&self, bundle_id: &str, dispatch: Dispatch) -> Result<bool, DynamoDbError> {
+        let result = self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .item("PK", AttributeValue::S(claim_partition(bundle_id)))
+            .item("SK", AttributeValue::S(CLAIM_SORT_KEY.to_string()))
+            .item("Dispatch", AttributeValue::S(dispatch.to_string()))
+            .item("ClaimedAt", AttributeValue::S(Utc::now().to_rfc3339()))
+            .condition_expression("attribute_not_exists(SK) OR Dispatch = :dispatch")
+            .expression_attribute_values(":dispatch", AttributeValue::S(dispatch.to_string()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(e) if matches!(e.as_service_error(), Some(PutItemError::ConditionalCheckFailedException(_))) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Gives up a claim for `dispatch` that was never acted on, e.g. a cancel that could not
+    /// be recorded, so the bundle can still be sent.
+    pub async fn release(&self, bundle_id: &str, dispatch: Dispatch) -> Result<(), DynamoDbError> {
+        let result = self.client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("PK", AttributeValue::S(claim_partition(bundle_id)))
+            .key("SK", AttributeValue::S(CLAIM_SORT_KEY.to_string()))
+            .condition_expression("Dispatch = :dispatch")
+            .expression_attribute_values(":dispatch", AttributeValue::S(dispatch.to_string()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if matches!(e.as_service_error(), Some(DeleteItemError::ConditionalCheckFailedException(_))) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+fn claim_partition(bundle_id: &str) -> String {
+    format!("Dispatch#{}", bundle_id)
+}
@@ -4,6 +4,10 @@ use aws_sdk_dynamodb::operation::put_item::PutItemError;
 use aws_sdk_cloudwatch::error::BuildError;
 use aws_sdk_dynamodb::operation::batch_get_item::BatchGetItemError;
 use aws_sdk_dynamodb::operation::query::QueryError;
+use aws_sdk_dynamodb::operation::transact_write_items::TransactWriteItemsError;
+use aws_sdk_dynamodb::operation::update_item::UpdateItemError;
+use aws_sdk_dynamodb::operation::delete_item::DeleteItemError;
+use aws_sdk_dynamodb::operation::get_item::GetItemError;
 
 #[derive(Debug)]
 pub enum DynamoDbError {
@@ -17,6 +21,7 @@ pub enum DynamoDbError {
     Serialization(String),
     AlreadyPersisted(String),
     Deserialization(String),
+    ConcurrencyConflict(String),
     NotFound,
 }
 
@@ -50,6 +55,37 @@ impl From<SdkError<QueryError>> for DynamoDbError {
     }
 }
 
+impl From<SdkError<GetItemError>> for DynamoDbError {
+    fn from(err: SdkError<GetItemError>) -> Self {
+        DynamoDbError::DynamoDbOperation(format!("DynamoDB GetItem error: {}", err))
+    }
+}
+
+impl From<SdkError<UpdateItemError>> for DynamoDbError {
+    fn from(err: SdkError<UpdateItemError>) -> Self {
+        DynamoDbError::DynamoDbOperation(format!("DynamoDB UpdateItem error: {}", err))
+    }
+}
+
+impl From<SdkError<DeleteItemError>> for DynamoDbError {
+    fn from(err: SdkError<DeleteItemError>) -> Self {
+        DynamoDbError::DynamoDbOperation(format!("DynamoDB DeleteItem error: {}", err))
+    }
+}
+
+impl From<SdkError<TransactWriteItemsError>> for DynamoDbError {
+    fn from(err: SdkError<TransactWriteItemsError>) -> Self {
+        match err.as_service_error() {
+            Some(TransactWriteItemsError::TransactionCanceledException(e))
+                if e.cancellation_reasons().iter().any(|r| r.code() == Some("ConditionalCheckFailed")) =>
+            {
+                DynamoDbError::ConcurrencyConflict(format!("Conditional check failed: {}", e))
+            }
+            _ => DynamoDbError::DynamoDbOperation(format!("DynamoDB TransactWriteItems error: {}", err)),
+        }
+    }
+}
+
 impl From<aws_sdk_cloudwatch::error::BuildError> for DynamoDbError {
     fn from(err: BuildError) -> Self {
         DynamoDbError::CloudWatchOperation(format!("CloudWatch error: {}", err))
@@ -70,6 +106,7 @@ impl fmt::Display for DynamoDbError {
             DynamoDbError::Serialization(e) => write!(f, "DynameoDb operation failed: Serialization error: {}", e),
             DynamoDbError::AlreadyPersisted(e) => write!(f, "DynameoDb operation failed: Already persisted error: {}", e),
             DynamoDbError::Deserialization(e) => write!(f, "DynameoDb operation failed: Deserialization error: {}", e),
+            DynamoDbError::ConcurrencyConflict(e) => write!(f, "DynameoDb operation failed: Concurrent write conflict: {}", e),
         }
     }
 }
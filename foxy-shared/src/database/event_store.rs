@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use async_trait::async_trait;
+use crate::database::errors::DynamoDbError;
+use crate::models::errors::TransactionError;
+use crate::models::transactions::{TransactionBundle, TransactionEvent};
+
+const MAX_APPEND_ATTEMPTS: usize = 3;
+
+/// Append-only storage for each bundle's event stream, with a DynamoDB backend and an
+/// in-memory one for tests.
+///
+/// Events are appended one at a time at the next sequence: an append only lands if the
+/// stream's last event is at `event.sequence - 1`. A writer that lost the race gets
+/// `ConcurrencyConflict`, which `retry_on_conflict` answers by reloading the latest event and
+/// rebuilding the transition. Reads return a bundle's events in sequence order, oldest first.
+#[async_trait]
+pub trait EventStore: Send + Sync {
+    /// Appends an event to its bundle's stream and returns its event ID. If an event with the
+    /// same `dedup_key` was already appended, nothing is written and that event's ID is returned.
+    /// Fails with `ConcurrencyConflict` if the stream has moved past `event.sequence - 1`.
+    async fn persist(&self, event: &TransactionEvent) -> Result<String, DynamoDbError>;
+
+    /// The ID of the event persisted under `key` (an idempotency key or event ID), if any.
+    async fn find_idempotent(&self, bundle_id: &str, key: &str) -> Result<Option<String>, DynamoDbError>;
+
+    async fn get_latest_event(&self, bundle_id: &str) -> Result<TransactionEvent, DynamoDbError>;
+
+    /// Returns the full event history for a bundle, oldest first, with event IDs populated.
+    async fn get_events(&self, bundle_id: &str) -> Result<Vec<TransactionEvent>, DynamoDbError>;
+
+    async fn persist_initial_event(&self, bundle: &TransactionBundle) -> Result<(), DynamoDbError> {
+        match TransactionEvent::initiate(bundle.clone()) {
+            Ok(event) => {
+                self.persist(&event).await?;
+                Ok(())
+            }
+            Err(e) => { Err(DynamoDbError::DynamoDbOperation(format!("Unable to persist event: {}", e))) }
+        }
+    }
+
+    /// Rebuilds the bundle by folding its recorded history through the state machine,
+    /// failing if any recorded event disagrees with the transition it claims to be.
+    async fn replay(&self, bundle_id: &str) -> Result<TransactionBundle, TransactionError> {
+        let events = self.get_events(bundle_id).await?;
+        TransactionEvent::replay(&events)
+    }
+}
+
+/// Applies `transition` to `last_event`, reloading the latest event and trying again
+/// whenever another writer appended to the bundle first.
+pub async fn retry_on_conflict<F, Fut>(
+    store: &dyn EventStore,
+    last_event: TransactionEvent,
+    mut transition: F,
+) -> Result<TransactionEvent, TransactionError>
+where
+    F: FnMut(TransactionEvent) -> Fut,
+    Fut: Future<Output = Result<TransactionEvent, TransactionError>>,
+{
+    let bundle_id = last_event.bundle_id.clone();
+    let mut current = last_event;
+    let mut attempt = 1;
+
+    loop {
+        match transition(current).await {
+            Err(TransactionError::ConcurrencyConflict(msg)) if attempt < MAX_APPEND_ATTEMPTS => {
+                tracing::warn!(%bundle_id, attempt, %msg, "🔁 Reloading latest event after conflict");
+                current = store.get_latest_event(&bundle_id).await?;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// In-process implementation with the same append rules as the DynamoDB store,
+/// for running the bundle lifecycle without AWS. Views are not projected.
+#[derive(Default)]
+pub struct InMemoryEventStore {
+    streams: Mutex<HashMap<String, Vec<TransactionEvent>>>,
+}
+
+impl InMemoryEventStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seeds the store with already-persisted events, e.g. to start a test mid-lifecycle.
+    pub fn with_history(events: Vec<TransactionEvent>) -> Self {
+        let store = Self::new();
+        {
+            let mut streams = store.streams.lock().expect("event store lock poisoned");
+            for event in events {
+                streams.entry(event.bundle_id.clone()).or_default().push(event);
+            }
+        }
+        store
+    }
+}
+
+#[async_trait]
+impl EventStore for InMemoryEventStore {
+    async fn persist(&self, event: &TransactionEvent) -> Result<String, DynamoDbError> {
+        if event.event_id.is_empty() {
+            return Err(DynamoDbError::DynamoDbOperation(format!(
+                "Event for bundle {} has no event ID", event.bundle_id
+            )));
+        }
+
+        if event.sequence == 0 {
+            return Err(DynamoDbError::DynamoDbOperation(format!(
+                "Event for bundle {} has no sequence number", event.bundle_id
+            )));
+        }
+
+        let mut streams = self.streams.lock().expect("event store lock poisoned");
+        let stream = streams.entry(event.bundle_id.clone()).or_default();
+
+        if let Some(existing) = stream.iter().find(|e| e.dedup_key() == event.dedup_key()) {
+            return Ok(existing.event_id.clone());
+        }
+
+        let head = stream.last().map(|e| e.sequence).unwrap_or(0);
+        if head != event.sequence - 1 {
+            return Err(DynamoDbError::ConcurrencyConflict(format!(
+                "Bundle {} is at sequence {}, cannot append sequence {}",
+                event.bundle_id, head, event.sequence
+            )));
+        }
+
+        stream.push(event.clone());
+        Ok(event.event_id.clone())
+    }
+
+    async fn find_idempotent(&self, bundle_id: &str, key: &str) -> Result<Option<String>, DynamoDbError> {
+        let streams = self.streams.lock().expect("event store lock poisoned");
+        Ok(streams
+            .get(bundle_id)
+            .and_then(|stream| stream.iter().find(|e| e.dedup_key() == key))
+            .map(|e| e.event_id.clone()))
+    }
+
+    async fn get_latest_event(&self, bundle_id: &str) -> Result<TransactionEvent, DynamoDbError> {
+        let streams = self.streams.lock().expect("event store lock poisoned");
+        streams
+            .get(bundle_id)
+            .and_then(|stream| stream.last())
+            .cloned()
+            .ok_or(DynamoDbError::NotFound)
+    }
+
+    async fn get_events(&self, bundle_id: &str) -> Result<Vec<TransactionEvent>, DynamoDbError> {
+        let streams = self.streams.lock().expect("event store lock poisoned");
+        streams
+            .get(bundle_id)
+            .filter(|stream| !stream.is_empty())
+            .cloned()
+            .ok_or(DynamoDbError::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use ethers_core::types::H256;
+    use crate::models::transactions::{BundleMetadata, BundleStatus, EventType, TokenType, Transaction, TransactionLeg, TransactionStatus};
+
+    static SIGNED_TX: &str = "0xf86b...";
+
+    fn bundle() -> TransactionBundle {
+        let fee_tx = Transaction::new("user".into(), "from".into(), "foxy".into(), 100, TokenType::ETH, 1, "GBP".into(), 1);
+        let main_tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 0);
+        TransactionBundle::new("user".into(), fee_tx, main_tx, Some(BundleMetadata::default()))
+    }
+
+    #[tokio::test]
+    async fn runs_full_bundle_lifecycle_offline() {
+        let store: Arc<dyn EventStore> = Arc::new(InMemoryEventStore::new());
+        let bundle = bundle();
+        store.persist_initial_event(&bundle).await.unwrap();
+
+        let initiated = store.get_latest_event(&bundle.bundle_id).await.unwrap();
+        let signed = TransactionEvent::on_signed(&initiated, SIGNED_TX, SIGNED_TX, &[], store.clone()).await.unwrap();
+        let main_sent = TransactionEvent::on_broadcast(&signed, H256::repeat_byte(1), store.clone()).await.unwrap();
+        let main_tx = main_sent.bundle_snapshot.main_tx.clone().with_status(TransactionStatus::Confirmed);
+        let main_confirmed = TransactionEvent::on_confirmed(&main_sent, &main_tx, store.clone()).await.unwrap();
+        let fee_sent = TransactionEvent::on_broadcast(&main_confirmed, H256::repeat_byte(2), store.clone()).await.unwrap();
+        let fee_tx = fee_sent.bundle_snapshot.fee_tx.clone().with_status(TransactionStatus::Confirmed);
+        let completed = TransactionEvent::on_confirmed(&fee_sent, &fee_tx, store.clone()).await.unwrap();
+
+        assert_eq!(completed.bundle_status, Some(BundleStatus::Completed));
+        assert!(!completed.event_id.is_empty());
+
+        let events = store.get_events(&bundle.bundle_id).await.unwrap();
+        assert_eq!(events.len(), 6);
+        assert_eq!(events.last().unwrap().event_id, completed.event_id);
+
+        let rebuilt = store.replay(&bundle.bundle_id).await.unwrap();
+        assert_eq!(rebuilt.status, BundleStatus::Completed);
+    }
+
+    #[tokio::test]
+    async fn rejects_append_from_stale_event() {
+        let store: Arc<dyn EventStore> = Arc::new(InMemoryEventStore::new());
+        let bundle = bundle();
+        store.persist_initial_event(&bundle).await.unwrap();
+        let initiated = store.get_latest_event(&bundle.bundle_id).await.unwrap();
+        let signed = TransactionEvent::on_signed(&initiated, SIGNED_TX, SIGNED_TX, &[], store.clone()).await.unwrap();
+
+        TransactionEvent::on_broadcast(&signed, H256::repeat_byte(1), store.clone()).await.unwrap();
+        let second = TransactionEvent::on_broadcast(&signed, H256::repeat_byte(1), store.clone()).await;
+
+        assert!(matches!(second, Err(TransactionError::ConcurrencyConflict(_))));
+        assert_eq!(store.get_events(&bundle.bundle_id).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn retry_reloads_latest_event_after_conflict() {
+        let store: Arc<dyn EventStore> = Arc::new(InMemoryEventStore::new());
+        let bundle = bundle();
+        store.persist_initial_event(&bundle).await.unwrap();
+        let initiated = store.get_latest_event(&bundle.bundle_id).await.unwrap();
+        let signed = TransactionEvent::on_signed(&initiated, SIGNED_TX, SIGNED_TX, &[], store.clone()).await.unwrap();
+        let main_sent = TransactionEvent::on_broadcast(&signed, H256::repeat_byte(1), store.clone()).await.unwrap();
+
+        // A stale writer still holding the Sign event fails the leg; the retry sees the broadcast.
+        let failed = retry_on_conflict(store.as_ref(), signed, |latest| {
+            let store = store.clone();
+            async move { TransactionEvent::on_fail(&latest, TransactionLeg::Main, store).await }
+        }).await.unwrap();
+
+        assert_eq!(failed.sequence, main_sent.sequence + 1);
+        assert_eq!(failed.event_type, EventType::Fail);
+        assert_eq!(failed.bundle_snapshot.main_tx.transaction_hash, main_sent.bundle_snapshot.main_tx.transaction_hash);
+    }
+
+    #[tokio::test]
+    async fn re_persisting_an_event_is_a_no_op() {
+        let store = InMemoryEventStore::new();
+        let event = TransactionEvent::initiate(bundle()).unwrap();
+
+        let first = store.persist(&event).await.unwrap();
+        let second = store.persist(&event).await.unwrap();
+
+        assert_eq!(first, event.event_id);
+        assert_eq!(second, first);
+        assert_eq!(store.get_events(&event.bundle_id).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn idempotency_key_returns_original_event_id() {
+        let store: Arc<dyn EventStore> = Arc::new(InMemoryEventStore::new());
+        let bundle = bundle();
+        store.persist_initial_event(&bundle).await.unwrap();
+        let initiated = store.get_latest_event(&bundle.bundle_id).await.unwrap();
+
+        // A client retry rebuilds the event, so it gets a fresh event ID but the same key
+        let first = TransactionEvent::sign(&initiated, SIGNED_TX, SIGNED_TX, &[]).unwrap().with_idempotency_key("request-1");
+        let retry = TransactionEvent::sign(&initiated, SIGNED_TX, SIGNED_TX, &[]).unwrap().with_idempotency_key("request-1");
+        assert_ne!(first.event_id, retry.event_id);
+
+        let original = store.persist(&first).await.unwrap();
+        assert_eq!(store.persist(&retry).await.unwrap(), original);
+        assert_eq!(store.find_idempotent(&bundle.bundle_id, "request-1").await.unwrap(), Some(original));
+        assert_eq!(store.get_events(&bundle.bundle_id).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn rejects_event_without_id() {
+        let store = InMemoryEventStore::new();
+        let mut event = TransactionEvent::initiate(bundle()).unwrap();
+        event.event_id = String::new();
+
+        assert!(matches!(store.persist(&event).await, Err(DynamoDbError::DynamoDbOperation(_))));
+        assert!(matches!(store.get_events(&event.bundle_id).await, Err(DynamoDbError::NotFound)));
+    }
+}
@@ -1,5 +1,11 @@
 pub mod dynamo_identity;
 pub mod errors;
+pub mod event_store;
+pub mod snapshot_schema;
 pub mod transaction_event;
+pub mod quote_ledger;
+pub mod nonce_leases;
+pub mod broadcast_claims;
+pub mod dispatch_claims;
 pub mod client;
 mod queries;
\ No newline at end of file
@@ -0,0 +1,305 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use aws_sdk_dynamodb::{Client as DynamoDbClient, types::AttributeValue};
+use aws_sdk_dynamodb::operation::delete_item::DeleteItemError;
+use aws_sdk_dynamodb::types::{Put, TransactWriteItem};
+use chrono::Utc;
+use tracing::{info, warn};
+use crate::database::errors::DynamoDbError;
+use crate::models::errors::TransactionError;
+use crate::models::state_machine;
+use crate::models::transactions::{BundleStatus, Network, TransactionBundle};
+use crate::utilities::nonce_manager::NonceManager;
+
+/// Attempts at reserving before giving up to a busy wallet.
+const MAX_RESERVE_ATTEMPTS: u32 = 5;
+
+fn lease_partition(network: &Network, address: &str) -> String {
+    format!("Nonce#{}#{}", network, address.to_lowercase())
+}
+
+/// Zero-padded so leases sort by nonce.
+fn lease_sort_key(nonce: u64) -> String {
+    format!("Lease#{:020}", nonce)
+}
+
+/// The first run of `count` nonces from `on_chain` up that no lease holds. Released nonces
+/// are taken again before new ones, so a cancelled bundle does not leave a gap behind.
+pub fn first_free(on_chain: u64, leased: &[u64], count: u64) -> u64 {
+    let mut start = on_chain;
+    while let Some(taken) = leased.iter().filter(|n| **n >= start && **n < start + count).max() {
+        start = taken + 1;
+    }
+    start
+}
+
+/// A nonce held for a bundle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lease {
+    pub nonce: u64,
+    pub bundle_id: String,
+}
+
+/// Splits `leases` into the stale and the live. A lease is stale once the chain has mined
+/// past its nonce, or when its bundle is finished without the nonce having reached the
+/// chain, e.g. a skipped fee leg or a bundle that failed before it was sent.
+pub fn split_stale(mined: u64, pending: u64, leases: Vec<Lease>, settled: &HashSet<String>) -> (Vec<Lease>, Vec<Lease>) {
+    leases.into_iter().partition(|lease| {
+        lease.nonce < mined || (lease.nonce >= pending && settled.contains(&lease.bundle_id))
+    })
+}
+
+/// The nonces of `bundle`'s legs that were never sent.
+pub fn unsent_nonces(bundle: &TransactionBundle) -> Vec<u64> {
+    bundle.legs()
+        .map(|leg| bundle.leg(leg))
+        .filter(|tx| tx.transaction_hash.is_none())
+        .filter_map(|tx| tx.nonce)
+        .collect()
+}
+
+/// Nonces handed to bundles that have not reached the chain yet, per wallet and network, so
+/// concurrent bundles from one wallet never share a nonce. Kept in the event table.
+#[derive(Clone)]
+pub struct NonceLeases {
+    client: Arc<DynamoDbClient>,
+    table_name: String,
+}
+
+impl NonceLeases {
+    // table_name is the event log table, probably from get_transaction_event_table()
+    pub fn new(client: Arc<DynamoDbClient>, table_name: String) -> Self {
+        Self { client, table_name }
+    }
+
+    /// Leases `count` consecutive nonces to `bundle_id` and returns the first. Stale leases are
+    /// dropped first (see `split_stale`), so a nonce that was never sent is handed out again.
+    pub async fn reserve(
+        &self,
+        network: &Network,
+        address: &str,
+        bundle_id: &str,
+        count: u64,
+    ) -> Result<u64, TransactionError> {
+        let nonces = NonceManager::for_network(network)?;
+        let mined = nonces.get_mined_nonce(address).await?;
+        let pending = nonces.get_nonce(address).await?;
+        let partition = lease_partition(network, address);
+
+        for attempt in 1..=MAX_RESERVE_ATTEMPTS {
+            let leased = self.leased(&partition).await?;
+
+            // Only leases the chain has not seen can belong to a bundle that will never send them
+            let mut settled = HashSet::new();
+            let unseen: HashSet<&str> = leased.iter().filter(|l| l.nonce >= pending).map(|l| l.bundle_id.as_str()).collect();
+            for bundle_id in unseen {
+                if self.is_settled(bundle_id).await? {
+                    settled.insert(bundle_id.to_string());
+                }
+            }
+
+            let (stale, live) = split_stale(mined, pending, leased, &settled);
+            for lease in stale {
+                self.delete(&partition, lease.nonce, Some(&lease.bundle_id)).await?;
+            }
+
+            let live: Vec<u64> = live.iter().map(|l| l.nonce).collect();
+            let start = first_free(pending, &live, count);
+            match self.put_leases(&partition, start, count, bundle_id).await {
+                Ok(()) => {
+                    info!(%partition, start, count, %bundle_id, "🔒 Leased nonces");
+                    return Ok(start);
+                }
+                // Another bundle took one of them first
+                Err(DynamoDbError::ConcurrencyConflict(_)) => {
+                    warn!(%partition, start, attempt, "Nonce lease conflict, retrying");
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Err(TransactionError::NonceUnavailable)
+    }
+
+    /// Frees the nonces of the bundle's unsent legs for the next bundle from the same wallet,
+    /// once it is cancelled, expired, failed or errored, or its fee leg is skipped. Leases since
+    /// taken by another bundle are left alone.
+    pub async fn release(&self, bundle: &TransactionBundle) -> Result<(), DynamoDbError> {
+        let partition = lease_partition(&bundle.fee_tx.network, &bundle.fee_tx.sender_address);
+
+        for nonce in unsent_nonces(bundle) {
+            self.delete(&partition, nonce, Some(&bundle.bundle_id)).await?;
+        }
+        info!(%partition, bundle_id = %bundle.bundle_id, "🔓 Released nonces");
+        Ok(())
+    }
+
+    async fn leased(&self, partition: &str) -> Result<Vec<Lease>, DynamoDbError> {
+        let result = self.client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("PK = :pk AND begins_with(SK, :prefix)")
+            .expression_attribute_values(":pk", AttributeValue::S(partition.to_string()))
+            .expression_attribute_values(":prefix", AttributeValue::S("Lease#".to_string()))
+            .send()
+            .await?;
+
+        result.items()
+            .iter()
+            .map(|item| {
+                let nonce = item.get("Nonce")
+                    .and_then(|v| v.as_n().ok())
+                    .and_then(|n| n.parse::<u64>().ok())
+                    .ok_or_else(|| DynamoDbError::Deserialization("Lease without a Nonce".to_string()))?;
+                let bundle_id = item.get("BundleID")
+                    .and_then(|v| v.as_s().ok())
+                    .cloned()
+                    .ok_or_else(|| DynamoDbError::Deserialization("Lease without a BundleID".to_string()))?;
+                Ok(Lease { nonce, bundle_id })
+            })
+            .collect()
+    }
+
+    /// Whether the bundle's latest event leaves it finished.
+    async fn is_settled(&self, bundle_id: &str) -> Result<bool, DynamoDbError> {
+        let result = self.client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("PK = :pk AND begins_with(SK, :prefix)")
+            .expression_attribute_values(":pk", AttributeValue::S(format!("Bundle#{}", bundle_id)))
+            .expression_attribute_values(":prefix", AttributeValue::S("Event#".to_string()))
+            .projection_expression("BundleStatus")
+            .scan_index_forward(false)
+            .limit(1)
+            .send()
+            .await?;
+
+        Ok(result.items()
+            .first()
+            .and_then(|item| item.get("BundleStatus"))
+            .and_then(|v| v.as_s().ok())
+            .and_then(|s| s.parse::<BundleStatus>().ok())
+            .is_some_and(|status| state_machine::is_terminal(&status)))
+    }
+
+    async fn put_leases(&self, partition: &str, start: u64, count: u64, bundle_id: &str) -> Result<(), DynamoDbError> {
+        let mut request = self.client.transact_write_items();
+
+        for nonce in start..start + count {
+            let mut item = HashMap::new();
+            item.insert("PK".to_string(), AttributeValue::S(partition.to_string()));
+            item.insert("SK".to_string(), AttributeValue::S(lease_sort_key(nonce)));
+            item.insert("Nonce".to_string(), AttributeValue::N(nonce.to_string()));
+            item.insert("BundleID".to_string(), AttributeValue::S(bundle_id.to_string()));
+            item.insert("LeasedAt".to_string(), AttributeValue::S(Utc::now().to_rfc3339()));
+
+            let put = Put::builder()
+                .table_name(&self.table_name)
+                .set_item(Some(item))
+                .condition_expression("attribute_not_exists(SK)")
+                .build()
+                .map_err(|e| DynamoDbError::KeyBuildFailed(e.to_string()))?;
+            request = request.transact_items(TransactWriteItem::builder().put(put).build());
+        }
+
+        request.send().await?;
+        Ok(())
+    }
+
+    async fn delete(&self, partition: &str, nonce: u64, bundle_id: Option<&str>) -> Result<(), DynamoDbError> {
+        let mut request = self.client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("PK", AttributeValue::S(partition.to_string()))
+            .key("SK", AttributeValue::S(lease_sort_key(nonce)));
+
+        if let Some(bundle_id) = bundle_id {
+            request = request
+                .condition_expression("BundleID = :bundle_id")
+                .expression_attribute_values(":bundle_id", AttributeValue::S(bundle_id.to_string()));
+        }
+
+        match request.send().await {
+            Ok(_) => Ok(()),
+            Err(e) if matches!(e.as_service_error(), Some(DeleteItemError::ConditionalCheckFailedException(_))) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers_core::types::H256;
+    use crate::models::transactions::{BundleMetadata, TokenType, Transaction, TransactionEvent};
+
+    fn lease(nonce: u64, bundle_id: &str) -> Lease {
+        Lease { nonce, bundle_id: bundle_id.into() }
+    }
+
+    /// Where the next bundle's nonces start, given the leases left in the table.
+    fn next_start(mined: u64, pending: u64, leases: Vec<Lease>, settled: &HashSet<String>, count: u64) -> u64 {
+        let (_, live) = split_stale(mined, pending, leases, settled);
+        let live: Vec<u64> = live.iter().map(|l| l.nonce).collect();
+        first_free(pending, &live, count)
+    }
+
+    #[test]
+    fn starts_at_the_on_chain_count() {
+        assert_eq!(first_free(5, &[], 2), 5);
+    }
+
+    #[test]
+    fn skips_nonces_leased_to_pending_bundles() {
+        assert_eq!(first_free(5, &[5, 6], 2), 7);
+        assert_eq!(first_free(5, &[5, 6, 7, 8], 2), 9);
+    }
+
+    #[test]
+    fn refills_a_released_gap_that_fits() {
+        // 5 and 6 were released by a cancelled bundle; 7 and 8 are still pending
+        assert_eq!(first_free(5, &[7, 8], 2), 5);
+        // Only one free nonce before the next lease is not enough for a bundle
+        assert_eq!(first_free(5, &[6, 7], 2), 8);
+    }
+
+    #[test]
+    fn a_skipped_fee_leg_frees_its_nonce_for_the_next_bundle() {
+        // Bundle 1 leased 5 for its main leg and 6 for a zero-value fee leg
+        let fee_tx = Transaction::new("user".into(), "from".into(), "foxy".into(), 0, TokenType::ETH, 0, "GBP".into(), 6);
+        let main_tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 5);
+        let bundle = TransactionBundle::new("user".into(), fee_tx, main_tx, Some(BundleMetadata::default()));
+        let leases = vec![lease(5, &bundle.bundle_id), lease(6, &bundle.bundle_id)];
+
+        let initiated = TransactionEvent::initiate(bundle).unwrap();
+        let signed = TransactionEvent::sign(&initiated, "0x01", "0x02", &[]).unwrap();
+        let main_sent = TransactionEvent::broadcast(&signed, H256::repeat_byte(1)).unwrap();
+        let main_tx = main_sent.bundle_snapshot.main_tx.clone();
+        let main_confirmed = TransactionEvent::confirm(&main_sent, &main_tx).unwrap();
+        let fee_tx = main_confirmed.bundle_snapshot.fee_tx.clone();
+        let skipped = TransactionEvent::skip(&main_confirmed, &fee_tx).unwrap();
+        let bundle_1 = skipped.bundle_snapshot;
+
+        // The skip releases the fee leg's lease, and the chain has only mined the main leg
+        let released = unsent_nonces(&bundle_1);
+        assert_eq!(released, vec![6]);
+        let remaining: Vec<Lease> = leases.iter().filter(|l| !released.contains(&l.nonce)).cloned().collect();
+        assert_eq!(next_start(6, 6, remaining, &HashSet::new(), 2), 6);
+
+        // Had the release been lost, the finished bundle's lease is still not in the way
+        let settled = HashSet::from([bundle_1.bundle_id.clone()]);
+        assert_eq!(next_start(6, 6, leases.clone(), &settled, 2), 6);
+
+        // Whereas a bundle still on its way to send it keeps its nonce
+        assert_eq!(next_start(6, 6, leases, &HashSet::new(), 2), 7);
+    }
+
+    #[test]
+    fn leases_in_the_mempool_are_kept_until_mined() {
+        let settled = HashSet::from(["done".to_string()]);
+        let (stale, live) = split_stale(5, 7, vec![lease(4, "done"), lease(5, "done"), lease(7, "done"), lease(8, "live")], &settled);
+
+        assert_eq!(stale, vec![lease(4, "done"), lease(7, "done")]);
+        assert_eq!(live, vec![lease(5, "done"), lease(8, "live")]);
+    }
+}
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+use aws_sdk_dynamodb::{Client as DynamoDbClient, types::AttributeValue};
+use aws_sdk_dynamodb::operation::delete_item::DeleteItemError;
+use aws_sdk_dynamodb::operation::put_item::PutItemError;
+use crate::database::errors::DynamoDbError;
+use crate::models::errors::TransactionError;
+use crate::utilities::quote::Quote;
+
+/// Records which quotes have started a bundle, in the event table, so each is used once.
+#[derive(Clone)]
+pub struct QuoteLedger {
+    client: Arc<DynamoDbClient>,
+    table_name: String,
+}
+
+impl QuoteLedger {
+    // table_name is the event log table, probably from get_transaction_event_table()
+    pub fn new(client: Arc<DynamoDbClient>, table_name: String) -> Self {
+        Self { client, table_name }
+    }
+
+    /// Marks the quote used, failing if it already was.
+    pub async fn claim(&self, quote: &Quote, bundle_id: &str) -> Result<(), TransactionError> {
+        let result = self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .item("PK", AttributeValue::S(format!("Quote#{}", quote.quote_id)))
+            .item("SK", AttributeValue::S("Used".to_string()))
+            .item("UserID", AttributeValue::S(quote.user_id.clone()))
+            .item("BundleID", AttributeValue::S(bundle_id.to_string()))
+            .item("ExpiresAt", AttributeValue::S(quote.expires_at.to_rfc3339()))
+            .condition_expression("attribute_not_exists(PK)")
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if matches!(e.as_service_error(), Some(PutItemError::ConditionalCheckFailedException(_))) => {
+                Err(TransactionError::InvalidQuote(format!("Quote {} has already been used", quote.quote_id)))
+            }
+            Err(e) => Err(DynamoDbError::from(e).into()),
+        }
+    }
+
+    /// Frees a quote claimed for `bundle_id` when the bundle could not be started after all,
+    /// so it can be submitted again. A claim by another bundle is left alone.
+    pub async fn release(&self, quote: &Quote, bundle_id: &str) -> Result<(), DynamoDbError> {
+        let result = self.client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("PK", AttributeValue::S(format!("Quote#{}", quote.quote_id)))
+            .key("SK", AttributeValue::S("Used".to_string()))
+            .condition_expression("BundleID = :bundle_id")
+            .expression_attribute_values(":bundle_id", AttributeValue::S(bundle_id.to_string()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if matches!(e.as_service_error(), Some(DeleteItemError::ConditionalCheckFailedException(_))) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
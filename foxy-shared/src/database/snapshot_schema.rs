@@ -0,0 +1,174 @@
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+use crate::database::errors::DynamoDbError;
+use crate::models::transactions::TransactionBundle;
+
+/// Written to the `SchemaVersion` attribute of every new event. Bump it together with a
+/// new step in `UPCASTERS` whenever the shape of `TransactionBundle` changes.
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+/// Events persisted before snapshots were versioned have no `SchemaVersion` attribute.
+pub const LEGACY_SCHEMA_VERSION: u32 = 1;
+
+type Upcaster = fn(Value) -> Result<Value, String>;
+
+/// `UPCASTERS[n]` migrates a snapshot from version `n + 1` to version `n + 2`.
+static UPCASTERS: &[Upcaster] = &[v1_to_v2];
+
+pub fn encode(bundle: &TransactionBundle) -> Result<String, DynamoDbError> {
+    serde_json::to_string(bundle).map_err(|e| DynamoDbError::Serialization(e.to_string()))
+}
+
+/// Reads a snapshot written at `version`, migrating it to the current shape first.
+pub fn decode(version: u32, json: &str) -> Result<TransactionBundle, DynamoDbError> {
+    if version == 0 || version > CURRENT_SCHEMA_VERSION {
+        return Err(DynamoDbError::Deserialization(format!(
+            "Unsupported snapshot schema version {} (current is {})", version, CURRENT_SCHEMA_VERSION
+        )));
+    }
+
+    if version == CURRENT_SCHEMA_VERSION {
+        return serde_json::from_str(json).map_err(|e| DynamoDbError::Deserialization(e.to_string()));
+    }
+
+    let original: Value = serde_json::from_str(json)
+        .map_err(|e| DynamoDbError::Deserialization(e.to_string()))?;
+
+    let mut upcast = original.clone();
+    for (from, step) in (version..).zip(&UPCASTERS[version as usize - 1..]) {
+        upcast = step(upcast).map_err(|e| DynamoDbError::Deserialization(format!(
+            "Cannot upcast snapshot from schema version {}: {}", from, e
+        )))?;
+    }
+
+    // Value only holds integers up to u64::MAX, so wei amounts beyond that survive only if
+    // we read the original text. That is safe whenever no migration step had anything to do.
+    let bundle = if upcast == original {
+        serde_json::from_str(json)
+    } else {
+        serde_json::from_value(upcast)
+    };
+
+    bundle.map_err(|e| DynamoDbError::Deserialization(e.to_string()))
+}
+
+/// v1 -> v2: display metadata used to live on each leg, with `from`/`to` parties that had
+/// no user ID. It moved to the bundle, which also gained pricing and device details.
+fn v1_to_v2(mut snapshot: Value) -> Result<Value, String> {
+    let bundle = snapshot.as_object_mut().ok_or("snapshot is not a JSON object")?;
+
+    let mut leg_metadata = None;
+    for leg in ["main_tx", "fee_tx"] {
+        let tx = bundle.get_mut(leg).and_then(Value::as_object_mut).ok_or(format!("{} is missing", leg))?;
+        if let Some(metadata) = tx.remove("metadata").filter(|m| !m.is_null()) {
+            leg_metadata.get_or_insert(metadata);
+        }
+    }
+
+    if matches!(bundle.get("metadata"), None | Some(Value::Null)) {
+        bundle.insert("metadata".into(), leg_metadata.unwrap_or(Value::Null));
+    }
+
+    if !bundle.contains_key("updated_at") {
+        let created_at = bundle.get("created_at").cloned().ok_or("created_at is missing")?;
+        bundle.insert("updated_at".into(), created_at);
+    }
+
+    if let Some(metadata) = bundle.get_mut("metadata").and_then(Value::as_object_mut) {
+        rename(metadata, "from", "sender");
+        rename(metadata, "to", "recipient");
+
+        for party in ["sender", "recipient"] {
+            if let Some(party) = metadata.get_mut(party).and_then(Value::as_object_mut) {
+                party.entry("user_id").or_insert_with(|| json!(Uuid::nil().to_string()));
+            }
+        }
+
+        metadata.entry("service_fee").or_insert(json!(0));
+        metadata.entry("exchange_rate").or_insert(json!(0.0));
+        metadata.entry("gas_pricing").or_insert_with(|| json!({
+            "estimated_gas": "0",
+            "gas_price": "0",
+            "max_fee_per_gas": "0",
+            "max_priority_fee_per_gas": "0",
+        }));
+        metadata.entry("user_device").or_insert_with(|| json!({
+            "device_fingerprint": "",
+            "push_token": "",
+            "platform": "",
+            "app_version": "",
+        }));
+    }
+
+    Ok(snapshot)
+}
+
+fn rename(object: &mut Map<String, Value>, from: &str, to: &str) {
+    if let Some(value) = object.remove(from) {
+        object.entry(to).or_insert(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::transactions::{BundleStatus, TransactionStatus};
+
+    const V1_LEG_METADATA: &str = include_str!("../../tests/fixtures/snapshots/v1_leg_metadata.json");
+    const V1_BUNDLE_METADATA: &str = include_str!("../../tests/fixtures/snapshots/v1_bundle_metadata.json");
+    const V2_SIGNED: &str = include_str!("../../tests/fixtures/snapshots/v2_signed.json");
+
+    #[test]
+    fn every_version_has_an_upcaster() {
+        assert_eq!(UPCASTERS.len() as u32, CURRENT_SCHEMA_VERSION - 1);
+    }
+
+    #[test]
+    fn upcasts_leg_metadata_onto_the_bundle() {
+        let bundle = decode(LEGACY_SCHEMA_VERSION, V1_LEG_METADATA).unwrap();
+
+        assert_eq!(bundle.bundle_id, "0b6f2c55-5f0e-4a53-9d4c-0f5d1f0c8a11");
+        assert_eq!(bundle.status, BundleStatus::Initiated);
+        assert_eq!(bundle.updated_at, bundle.created_at);
+
+        let metadata = bundle.metadata.expect("metadata should be lifted from the main leg");
+        assert_eq!(metadata.display_currency, "GBP");
+        assert_eq!(metadata.expected_currency_amount, 500);
+        assert_eq!(metadata.message.as_deref(), Some("Coffee"));
+        assert_eq!(metadata.service_fee, 0);
+        assert_eq!(metadata.gas_pricing.gas_price, "0");
+
+        let sender = metadata.sender.expect("from should become sender");
+        assert_eq!(sender.name, "Alice");
+        assert_eq!(sender.user_id, Uuid::nil().to_string());
+        assert_eq!(metadata.recipient.expect("to should become recipient").wallet, "0xa826d3484625b29dfcbdaee6ca636a1acb439bf8");
+    }
+
+    #[test]
+    fn reads_unversioned_snapshot_already_in_current_shape() {
+        let bundle = decode(LEGACY_SCHEMA_VERSION, V1_BUNDLE_METADATA).unwrap();
+
+        let metadata = bundle.metadata.unwrap();
+        assert_eq!(metadata.sender.unwrap().user_id, "112527246877271240195");
+        assert_eq!(metadata.user_device.platform, "android");
+        // Above u64::MAX, so this only survives because no migration was needed
+        assert_eq!(bundle.main_tx.transaction_value, 25_000_000_000_000_000_000);
+    }
+
+    #[test]
+    fn reads_current_snapshot_and_round_trips() {
+        let bundle = decode(CURRENT_SCHEMA_VERSION, V2_SIGNED).unwrap();
+        assert_eq!(bundle.status, BundleStatus::Signed);
+        assert_eq!(bundle.main_tx.status, TransactionStatus::Signed);
+
+        let again = decode(CURRENT_SCHEMA_VERSION, &encode(&bundle).unwrap()).unwrap();
+        assert_eq!(again.bundle_id, bundle.bundle_id);
+        assert_eq!(again.main_tx.signed_tx, bundle.main_tx.signed_tx);
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        assert!(matches!(decode(0, V2_SIGNED), Err(DynamoDbError::Deserialization(_))));
+        assert!(matches!(decode(CURRENT_SCHEMA_VERSION + 1, V2_SIGNED), Err(DynamoDbError::Deserialization(_))));
+    }
+}
@@ -1,14 +1,32 @@
 use aws_sdk_dynamodb::{Client as DynamoDbClient, types::AttributeValue};
+use aws_sdk_dynamodb::error::SdkError;
+use aws_sdk_dynamodb::operation::transact_write_items::TransactWriteItemsError;
+use aws_sdk_dynamodb::types::{Put, TransactWriteItem, Update};
 use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 use std::sync::Arc;
-use uuid::Uuid;
+use async_trait::async_trait;
 use crate::database::errors::DynamoDbError;
-use crate::models::transactions::{BundleStatus, EventType, TransactionBundle, TransactionEvent, TransactionLeg, TransactionStatus};
-use crate::utilities::config::{get_history_view_table, get_transaction_view_table};
-use crate::views::history_view::TransactionHistoryViewManager;
-use crate::views::status_view::TransactionStatusViewManager;
+use crate::database::event_store::EventStore;
+use crate::database::snapshot_schema::{self, CURRENT_SCHEMA_VERSION, LEGACY_SCHEMA_VERSION};
+use crate::models::transactions::{BundleStatus, EventType, TransactionEvent, TransactionLeg, TransactionStatus};
+use crate::views::outbox::{self, OutboxEntry, ProjectionOutbox};
 
+const HEAD_SORT_KEY: &str = "Head";
+
+// Position of the idempotency marker in the append transaction
+const MARKER_ITEM_INDEX: usize = 3;
+
+/// Zero-padded so events sort by sequence, and after the timestamp keys of older events.
+fn event_sort_key(sequence: u64) -> String {
+    format!("Event#Seq#{:020}", sequence)
+}
+
+fn marker_sort_key(key: &str) -> String {
+    format!("Idem#{}", key)
+}
+
+#[derive(Clone)]
 pub struct TransactionEventManager {
     client: Arc<DynamoDbClient>,
     table_name: String,
@@ -23,74 +41,26 @@ impl TransactionEventManager {
     pub fn client(&self) -> Arc<DynamoDbClient> {
         self.client.clone()
     }
-    pub async fn persist(
-        self: Arc<Self>,
-        event: &TransactionEvent,
-    ) -> Result<String, DynamoDbError> {
-        if !event.event_id.is_empty() {
-            return Err(DynamoDbError::AlreadyPersisted(format!(
-                "Attempted to persist an event that already has event_id: {}",
-                event.event_id
-            )));
-        }
-
-        let item = self.to_dynamo_item(event)?;
-
-        //TODO: We should create constants for item fields
-        let event_id_str = item.get("EventID")
-            .and_then(|v| v.as_s().ok())
-            .ok_or_else(|| DynamoDbError::Deserialization("Missing or invalid EventID".into()))?
-            .to_string();
-
-        self.client
-            .put_item()
-            .table_name(&self.table_name)
-            .set_item(Some(item))
-            .send()
-            .await
-            .map_err(DynamoDbError::from)?;
-
-        let projector = TransactionStatusViewManager::new(
-            get_transaction_view_table(),
-            self.client.clone(),
-            self.clone(),
-        );
-
-        if let Err(e) = projector.project(&event.bundle_id).await {
-            tracing::error!(?e, "Failed to project status view");
-        }
-
-        let history_view = TransactionHistoryViewManager::new(
-            get_history_view_table(),
-            self.client.clone(),
-        );
-
-        if let Err(e) = history_view.project_from_event(event).await {
-            tracing::error!(?e, "Failed to project history view");
-        }
-
-        Ok(event_id_str)
-    }
-
+    /// `event` must already carry the ID and timestamp it is stored under.
     fn to_dynamo_item(
         &self,
         event: &TransactionEvent,
     ) -> Result<HashMap<String, AttributeValue>, DynamoDbError> {
         let mut item = HashMap::new();
-        let event_id = Uuid::new_v4().to_string();
-        let timestamp = Utc::now().to_rfc3339();
+        let timestamp = event.created_at.to_rfc3339();
 
-        let bundle_json = serde_json::to_string(&event.bundle_snapshot)
-            .map_err(|e| DynamoDbError::Serialization(e.to_string()))?;
+        let bundle_json = snapshot_schema::encode(&event.bundle_snapshot)?;
 
         item.insert("PK".to_string(), AttributeValue::S(format!("Bundle#{}", event.bundle_id)));
-        item.insert("SK".to_string(), AttributeValue::S(format!("Event#{}", timestamp)));
+        item.insert("SK".to_string(), AttributeValue::S(event_sort_key(event.sequence)));
 
-        item.insert("EventID".to_string(), AttributeValue::S(event_id));
+        item.insert("EventID".to_string(), AttributeValue::S(event.event_id.clone()));
+        item.insert("Sequence".to_string(), AttributeValue::N(event.sequence.to_string()));
         item.insert("UserID".to_string(), AttributeValue::S(event.user_id.clone()));
         item.insert("EventType".to_string(), AttributeValue::S(event.event_type.to_string()));
         item.insert("CreatedAt".to_string(), AttributeValue::S(timestamp));
         item.insert("BundleSnapshot".to_string(), AttributeValue::S(bundle_json));
+        item.insert("SchemaVersion".to_string(), AttributeValue::N(CURRENT_SCHEMA_VERSION.to_string()));
 
         if let Some(leg) = event.leg {
             item.insert("Leg".to_string(), AttributeValue::S(leg.to_string()));
@@ -104,46 +74,103 @@ impl TransactionEventManager {
             item.insert("BundleStatus".to_string(), AttributeValue::S(bundle_status.to_string()));
         }
 
+        if let Some(key) = &event.idempotency_key {
+            item.insert("IdempotencyKey".to_string(), AttributeValue::S(key.clone()));
+        }
+
         Ok(item)
     }
 
-    pub async fn persist_initial_event(self: Arc<Self>, bundle: &TransactionBundle) -> Result<(), DynamoDbError> {
-        match TransactionEvent::initiate(bundle.clone()) {
-            Ok(event) => {
-                self.persist(&event).await?;
-                Ok(())
-            }
-            Err(e) => { Err(DynamoDbError::DynamoDbOperation(format!("Unable to persist event: {}", e))) }
+    /// The writes that append `event`: the event itself, the head bump, its outbox entry and
+    /// its idempotency marker, in that order. Built from `event` alone, so a retried append
+    /// sends the same request.
+    fn append_items(&self, event: &TransactionEvent) -> Result<Vec<TransactWriteItem>, DynamoDbError> {
+        let item = self.to_dynamo_item(event)?;
+        let event_id_str = event.event_id.clone();
+
+        // The head item tracks the last appended sequence; the event put and the head bump
+        // commit together, so two writers appending from the same stale event cannot both win.
+        let expected_sequence = event.sequence - 1;
+        let put_event = Put::builder()
+            .table_name(&self.table_name)
+            .set_item(Some(item))
+            .condition_expression("attribute_not_exists(SK)")
+            .build()
+            .map_err(|e| DynamoDbError::KeyBuildFailed(e.to_string()))?;
+
+        let head_condition = if expected_sequence == 0 {
+            "attribute_not_exists(PK)"
+        } else {
+            "Version = :expected"
+        };
+
+        let mut bump_head = Update::builder()
+            .table_name(&self.table_name)
+            .key("PK", AttributeValue::S(format!("Bundle#{}", event.bundle_id)))
+            .key("SK", AttributeValue::S(HEAD_SORT_KEY.to_string()))
+            .update_expression("SET Version = :next, LastEventID = :event_id")
+            .condition_expression(head_condition)
+            .expression_attribute_values(":next", AttributeValue::N(event.sequence.to_string()))
+            .expression_attribute_values(":event_id", AttributeValue::S(event_id_str.clone()));
+
+        if expected_sequence > 0 {
+            bump_head = bump_head.expression_attribute_values(":expected", AttributeValue::N(expected_sequence.to_string()));
         }
+
+        let bump_head = bump_head
+            .build()
+            .map_err(|e| DynamoDbError::KeyBuildFailed(e.to_string()))?;
+
+        // The outbox entry commits with the event, so the views are always eventually told about it.
+        let outbox = ProjectionOutbox::new(self.client.clone(), self.table_name.clone());
+        let outbox_entry = OutboxEntry::for_event(event);
+        let put_outbox = outbox.put(&outbox_entry)?;
+
+        // Claims the idempotency key; if it is taken, the whole append is a no-op.
+        let mut marker = HashMap::new();
+        marker.insert("PK".to_string(), AttributeValue::S(format!("Bundle#{}", event.bundle_id)));
+        marker.insert("SK".to_string(), AttributeValue::S(marker_sort_key(event.dedup_key())));
+        marker.insert("EventID".to_string(), AttributeValue::S(event_id_str.clone()));
+        let put_marker = Put::builder()
+            .table_name(&self.table_name)
+            .set_item(Some(marker))
+            .condition_expression("attribute_not_exists(SK)")
+            .build()
+            .map_err(|e| DynamoDbError::KeyBuildFailed(e.to_string()))?;
+
+        Ok([
+            TransactWriteItem::builder().put(put_event).build(),
+            TransactWriteItem::builder().update(bump_head).build(),
+            TransactWriteItem::builder().put(put_outbox).build(),
+            TransactWriteItem::builder().put(put_marker).build(),
+        ].into())
     }
 
-    pub async fn get_latest_event(
-        &self,
+    fn failed_condition(err: &SdkError<TransactWriteItemsError>, index: usize) -> bool {
+        match err.as_service_error() {
+            Some(TransactWriteItemsError::TransactionCanceledException(e)) => e
+                .cancellation_reasons()
+                .get(index)
+                .and_then(|r| r.code())
+                == Some("ConditionalCheckFailed"),
+            _ => false,
+        }
+    }
+
+    fn from_dynamo_item(
         bundle_id: &str,
+        item: &HashMap<String, AttributeValue>,
     ) -> Result<TransactionEvent, DynamoDbError> {
-        let pk_value = format!("Bundle#{}", bundle_id);
-        tracing::info!(%bundle_id, %pk_value, "🔎 Querying DynamoDB for latest event");
-        tracing::info!(table = %self.table_name, "📋 Using table");
-
-        let result = self.client
-            .query()
-            .table_name(&self.table_name)
-            .key_condition_expression("PK = :pk and begins_with(SK, :sk)")
-            .expression_attribute_values(":pk", AttributeValue::S(format!("Bundle#{}", bundle_id)))
-            .expression_attribute_values(":sk", AttributeValue::S("Event#".to_string()))
-            .scan_index_forward(false)
-            .limit(1)
-            .send()
-            .await
-            .map_err(DynamoDbError::from)?;
-
-        let item_count = result.items.as_ref().map(|items| items.len()).unwrap_or(0);
-        tracing::info!(bundle_id = %bundle_id, item_count, "🔍 Query returned items");
+        let event_id = item.get("EventID")
+            .and_then(|v| v.as_s().ok().map(ToOwned::to_owned))
+            .unwrap_or_default();
 
-        let item = result.items
-            .as_ref()
-            .and_then(|items| items.first())
-            .ok_or_else(|| DynamoDbError::NotFound)?;
+        let sequence = item.get("Sequence")
+            .and_then(|v| v.as_n().ok())
+            .map(|n| n.parse::<u64>())
+            .transpose()
+            .map_err(|e| DynamoDbError::Deserialization(format!("Invalid Sequence: {}", e)))?
+            .unwrap_or_default();
 
         let event_type = item.get("EventType")
             .and_then(|v| v.as_s().ok())
@@ -168,6 +195,9 @@ impl TransactionEventManager {
             .transpose()
             .map_err(|e| DynamoDbError::Deserialization(format!("Invalid Leg: {}", e)))?;
 
+        let idempotency_key = item.get("IdempotencyKey")
+            .and_then(|v| v.as_s().ok().map(ToOwned::to_owned));
+
         let user_id = item.get("UserID")
             .and_then(|v| v.as_s().ok().map(ToOwned::to_owned))
             .unwrap_or_default();
@@ -182,11 +212,19 @@ impl TransactionEventManager {
             .and_then(|v| v.as_s().ok())
             .ok_or_else(|| DynamoDbError::Deserialization("Missing BundleSnapshot".into()))?;
 
-        let bundle_snapshot: TransactionBundle = serde_json::from_str(bundle_json)
-            .map_err(|e| DynamoDbError::Deserialization(e.to_string()))?;
+        let schema_version = item.get("SchemaVersion")
+            .and_then(|v| v.as_n().ok())
+            .map(|n| n.parse::<u32>())
+            .transpose()
+            .map_err(|e| DynamoDbError::Deserialization(format!("Invalid SchemaVersion: {}", e)))?
+            .unwrap_or(LEGACY_SCHEMA_VERSION);
+
+        let bundle_snapshot = snapshot_schema::decode(schema_version, bundle_json)?;
 
         Ok(TransactionEvent {
-            event_id: String::new(), // Not returned from DB, can be ignored here or fetched separately
+            event_id,
+            idempotency_key,
+            sequence,
             bundle_id: bundle_id.to_string(),
             user_id,
             event_type,
@@ -198,3 +236,182 @@ impl TransactionEventManager {
         })
     }
 }
+
+#[async_trait]
+impl EventStore for TransactionEventManager {
+    async fn persist(
+        &self,
+        event: &TransactionEvent,
+    ) -> Result<String, DynamoDbError> {
+        if event.event_id.is_empty() {
+            return Err(DynamoDbError::DynamoDbOperation(format!(
+                "Event for bundle {} has no event ID", event.bundle_id
+            )));
+        }
+
+        if event.sequence == 0 {
+            return Err(DynamoDbError::DynamoDbOperation(format!(
+                "Event for bundle {} has no sequence number", event.bundle_id
+            )));
+        }
+
+        let result = self.client
+            .transact_write_items()
+            .set_transact_items(Some(self.append_items(event)?))
+            // Makes the SDK's own retries of this call idempotent as well. DynamoDB compares
+            // the whole request, so the items must be built from the event alone.
+            .client_request_token(&event.event_id)
+            .send()
+            .await;
+
+        if let Err(e) = result {
+            let original = match Self::failed_condition(&e, MARKER_ITEM_INDEX) {
+                true => self.find_idempotent(&event.bundle_id, event.dedup_key()).await?,
+                false => None,
+            };
+            if let Some(original) = original {
+                tracing::info!(bundle_id = %event.bundle_id, %original, "♻️ Event already persisted, returning original ID");
+                return Ok(original);
+            }
+
+            let err = DynamoDbError::from(e);
+            if let DynamoDbError::ConcurrencyConflict(_) = err {
+                tracing::warn!(bundle_id = %event.bundle_id, sequence = event.sequence, "⚔️ Concurrent append rejected");
+            }
+            return Err(err);
+        }
+
+        // Project straight away so readers see the change promptly; if that fails the
+        // entry stays in the outbox and the watcher retries it.
+        match outbox::project_event(self.client.clone(), Arc::new(self.clone()), event).await {
+            Ok(()) => {
+                let outbox = ProjectionOutbox::new(self.client.clone(), self.table_name.clone());
+                if let Err(e) = outbox.complete(&OutboxEntry::for_event(event)).await {
+                    tracing::warn!(?e, bundle_id = %event.bundle_id, "Projected, but failed to clear outbox entry");
+                }
+            }
+            Err(e) => tracing::error!(?e, bundle_id = %event.bundle_id, "Failed to project views, left in outbox for retry"),
+        }
+
+        Ok(event.event_id.clone())
+    }
+
+    async fn find_idempotent(&self, bundle_id: &str, key: &str) -> Result<Option<String>, DynamoDbError> {
+        let result = self.client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("PK", AttributeValue::S(format!("Bundle#{}", bundle_id)))
+            .key("SK", AttributeValue::S(marker_sort_key(key)))
+            .consistent_read(true)
+            .send()
+            .await?;
+
+        Ok(result.item
+            .as_ref()
+            .and_then(|item| item.get("EventID"))
+            .and_then(|v| v.as_s().ok())
+            .cloned())
+    }
+
+    async fn get_latest_event(
+        &self,
+        bundle_id: &str,
+    ) -> Result<TransactionEvent, DynamoDbError> {
+        let pk_value = format!("Bundle#{}", bundle_id);
+        tracing::info!(%bundle_id, %pk_value, "🔎 Querying DynamoDB for latest event");
+        tracing::info!(table = %self.table_name, "📋 Using table");
+
+        let result = self.client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("PK = :pk and begins_with(SK, :sk)")
+            .expression_attribute_values(":pk", AttributeValue::S(format!("Bundle#{}", bundle_id)))
+            .expression_attribute_values(":sk", AttributeValue::S("Event#".to_string()))
+            .scan_index_forward(false)
+            .limit(1)
+            .send()
+            .await
+            .map_err(DynamoDbError::from)?;
+
+        let item_count = result.items.as_ref().map(|items| items.len()).unwrap_or(0);
+        tracing::info!(bundle_id = %bundle_id, item_count, "🔍 Query returned items");
+
+        let item = result.items
+            .as_ref()
+            .and_then(|items| items.first())
+            .ok_or(DynamoDbError::NotFound)?;
+
+        Self::from_dynamo_item(bundle_id, item)
+    }
+
+    async fn get_events(
+        &self,
+        bundle_id: &str,
+    ) -> Result<Vec<TransactionEvent>, DynamoDbError> {
+        let mut events = Vec::new();
+        let mut exclusive_start_key = None;
+
+        loop {
+            let result = self.client
+                .query()
+                .table_name(&self.table_name)
+                .key_condition_expression("PK = :pk and begins_with(SK, :sk)")
+                .expression_attribute_values(":pk", AttributeValue::S(format!("Bundle#{}", bundle_id)))
+                .expression_attribute_values(":sk", AttributeValue::S("Event#".to_string()))
+                .scan_index_forward(true)
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await
+                .map_err(DynamoDbError::from)?;
+
+            for item in result.items() {
+                events.push(Self::from_dynamo_item(bundle_id, item)?);
+            }
+
+            match result.last_evaluated_key {
+                Some(key) => exclusive_start_key = Some(key),
+                None => break,
+            }
+        }
+
+        if events.is_empty() {
+            return Err(DynamoDbError::NotFound);
+        }
+
+        tracing::info!(%bundle_id, count = events.len(), "📜 Loaded event history");
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_dynamodb::config::{BehaviorVersion, Region};
+    use crate::models::transactions::{BundleMetadata, TokenType, Transaction, TransactionBundle};
+
+    fn manager() -> Arc<TransactionEventManager> {
+        let config = aws_sdk_dynamodb::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("eu-north-1"))
+            .build();
+        TransactionEventManager::new(Arc::new(DynamoDbClient::from_conf(config)), "events".into())
+    }
+
+    #[test]
+    fn repersisting_an_event_sends_the_same_request() {
+        let fee_tx = Transaction::new("user".into(), "from".into(), "foxy".into(), 100, TokenType::ETH, 1, "GBP".into(), 1);
+        let main_tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 0);
+        let bundle = TransactionBundle::new("user".into(), fee_tx, main_tx, Some(BundleMetadata::default()));
+        let event = TransactionEvent::initiate(bundle).unwrap();
+        let tem = manager();
+
+        let first = tem.append_items(&event).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let retried = tem.append_items(&event).unwrap();
+
+        // DynamoDB rejects a reused request token unless the request is identical
+        assert_eq!(first, retried);
+        let stored = first[0].put().unwrap().item();
+        assert_eq!(stored.get("CreatedAt").and_then(|v| v.as_s().ok()), Some(&event.created_at.to_rfc3339()));
+    }
+}
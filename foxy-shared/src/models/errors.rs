@@ -9,6 +9,7 @@ use aws_sdk_cognitoidentityprovider::error::SdkError;
 use aws_sdk_cognitoidentityprovider::operation::list_users::ListUsersError;
 use aws_sdk_sts::config::http::HttpResponse;
 use crate::database::errors::DynamoDbError;
+use crate::models::transactions::TransactionLeg;
 use aws_sdk_dynamodb::error::SdkError as DynamoError;
 use ethers_providers::ProviderError;
 use serde_json::Error as SerdeJsonError;
@@ -77,6 +78,12 @@ pub enum TransactionError {
     InvalidServiceFee,
     MissingFeeTransaction,
     MissingMainTransaction,
+    InvalidPaymentRequest(String),       // A scanned EIP-681 URI could not be used
+    InvalidQuote(String),                // Quote is missing, forged, expired, used or for another transfer
+    MalformedSignedTransaction(String),  // Signed payload is not a decodable EIP-1559 transaction
+    SignerMismatch { leg: TransactionLeg, expected: String, signer: String }, // Signed by a wallet other than the bundle sender
+    SignedFieldMismatch { leg: TransactionLeg, field: String, expected: String, signed: String }, // Signed tx differs from the unsigned one we issued
+    NotReplaceable(String),              // No pending leg, or it is already priced at the market
 
     // System & Processing Errors
     GasPriceUnavailable(String),                  // Could not fetch gas price
@@ -89,6 +96,8 @@ pub enum TransactionError {
     InvalidTransition(String),
     Projection(String),
     NotFound(String),
+    ConcurrencyConflict(String),         // Another writer appended to the bundle first
+    BundleExpired(String),               // Bundle was not signed within its TTL
 
     // External Dependencies
     RateLimitExceeded,                      // API rate limits from third-party services
@@ -118,6 +127,14 @@ impl fmt::Display for TransactionError {
             TransactionError::InvalidServiceFee => write!(f, "Invalid service fee."),
             TransactionError::MissingFeeTransaction => write!(f, "Missing fee transaction."),
             TransactionError::MissingMainTransaction => write!(f, "Missing main transaction."),
+            TransactionError::InvalidPaymentRequest(msg) => write!(f, "Invalid payment request: {}", msg),
+            TransactionError::InvalidQuote(msg) => write!(f, "Invalid quote: {}", msg),
+            TransactionError::MalformedSignedTransaction(msg) => write!(f, "Malformed signed transaction: {}", msg),
+            TransactionError::SignerMismatch { leg, expected, signer } =>
+                write!(f, "{} transaction signed by {} but the bundle sender is {}", leg, signer, expected),
+            TransactionError::SignedFieldMismatch { leg, field, expected, signed } =>
+                write!(f, "{} transaction signed with {} {} but {} was expected", leg, field, signed, expected),
+            TransactionError::NotReplaceable(msg) => write!(f, "Cannot speed up: {}", msg),
 
             // System & Processing Errors
             TransactionError::GasPriceUnavailable(msg) => write!(f, "Could not fetch gas price: {}", msg),
@@ -130,6 +147,8 @@ impl fmt::Display for TransactionError {
             TransactionError::InvalidTransition(msg) => write!(f, "Invalid Transition: {}", msg),
             TransactionError::Projection(msg) => write!(f, "Invalid Projection: {}", msg),
             TransactionError::NotFound(msg) => write!(f, "Transaction not found: {}", msg),
+            TransactionError::ConcurrencyConflict(msg) => write!(f, "Concurrent update conflict: {}", msg),
+            TransactionError::BundleExpired(msg) => write!(f, "Transaction expired: {}", msg),
 
             // External Dependencies
             TransactionError::RateLimitExceeded => write!(f, "Rate limit exceeded. Please try again later."),
@@ -163,7 +182,10 @@ impl From<FetchRateError> for TransactionError {
 
 impl From<DynamoDbError> for TransactionError {
     fn from(err: DynamoDbError) -> Self {
-        TransactionError::DatabaseError(format!("{:?}", err))
+        match err {
+            DynamoDbError::ConcurrencyConflict(msg) => TransactionError::ConcurrencyConflict(msg),
+            other => TransactionError::DatabaseError(format!("{:?}", other)),
+        }
     }
 }
 
@@ -408,6 +430,9 @@ pub enum GasEstimateError {
 
     #[error("Network error")]
     Network(String),
+
+    #[error("Unsupported token: {0}")]
+    UnsupportedToken(String),
 }
 
 impl From<GasEstimateError> for TransactionError {
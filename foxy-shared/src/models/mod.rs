@@ -1,4 +1,5 @@
 pub mod transactions;
+pub mod state_machine;
 pub mod auth;
 pub mod errors;
 pub mod phone;
@@ -0,0 +1,500 @@
+use std::fmt::Write;
+use crate::models::errors::TransactionError;
+use crate::models::transactions::{BundleStatus, EventType, TransactionEvent, TransactionLeg};
+
+/// One allowed step in the bundle lifecycle.
+#[derive(Debug)]
+pub struct Transition {
+    pub from: BundleStatus,
+    pub event: EventType,
+    pub leg: Option<LegKind>,
+    /// Event types the bundle's latest event may have for this step to apply.
+    pub after: &'static [EventType],
+    pub to: BundleStatus,
+}
+
+/// The legs a transition can be for. Split legs share their transitions, whatever the index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LegKind {
+    Split,
+    Main,
+    Fee,
+}
+
+impl LegKind {
+    pub fn of(leg: TransactionLeg) -> Self {
+        match leg {
+            TransactionLeg::Split(_) => LegKind::Split,
+            TransactionLeg::Main => LegKind::Main,
+            TransactionLeg::Fee => LegKind::Fee,
+        }
+    }
+}
+
+/// The bundle lifecycle. Anything not listed here is rejected.
+pub static TRANSITIONS: &[Transition] = &[
+    Transition { from: BundleStatus::Initiated, event: EventType::Sign, leg: None, after: &[EventType::Initiate], to: BundleStatus::Signed },
+    Transition { from: BundleStatus::Initiated, event: EventType::Cancel, leg: None, after: &[EventType::Initiate], to: BundleStatus::Cancelled },
+    Transition { from: BundleStatus::Initiated, event: EventType::Expire, leg: None, after: &[EventType::Initiate], to: BundleStatus::Expired },
+    Transition { from: BundleStatus::Initiated, event: EventType::Error, leg: Some(LegKind::Main), after: &[EventType::Initiate], to: BundleStatus::Errored },
+    Transition { from: BundleStatus::Initiated, event: EventType::Error, leg: Some(LegKind::Fee), after: &[EventType::Initiate], to: BundleStatus::Errored },
+    Transition { from: BundleStatus::Signed, event: EventType::Cancel, leg: None, after: &[EventType::Sign], to: BundleStatus::Cancelled },
+
+    // Split legs, one at a time ahead of the main leg
+    Transition { from: BundleStatus::Signed, event: EventType::Broadcast, leg: Some(LegKind::Split), after: SENDABLE, to: BundleStatus::Signed },
+    Transition { from: BundleStatus::Signed, event: EventType::Replace, leg: Some(LegKind::Split), after: UNMINED, to: BundleStatus::Signed },
+    Transition { from: BundleStatus::Signed, event: EventType::Include, leg: Some(LegKind::Split), after: IN_FLIGHT, to: BundleStatus::Signed },
+    Transition { from: BundleStatus::Signed, event: EventType::Reorg, leg: Some(LegKind::Split), after: &[EventType::Include], to: BundleStatus::Signed },
+    Transition { from: BundleStatus::Signed, event: EventType::Rebroadcast, leg: Some(LegKind::Split), after: UNMINED, to: BundleStatus::Signed },
+    Transition { from: BundleStatus::Signed, event: EventType::Confirm, leg: Some(LegKind::Split), after: IN_FLIGHT, to: BundleStatus::Signed },
+    Transition { from: BundleStatus::Signed, event: EventType::Fail, leg: Some(LegKind::Split), after: SIGNED_AND_UNSETTLED, to: BundleStatus::Failed },
+    Transition { from: BundleStatus::Signed, event: EventType::Error, leg: Some(LegKind::Split), after: SIGNED_AND_UNSETTLED, to: BundleStatus::Errored },
+
+    // Main leg, once any split legs are confirmed
+    Transition { from: BundleStatus::Signed, event: EventType::Broadcast, leg: Some(LegKind::Main), after: SENDABLE, to: BundleStatus::Signed },
+    Transition { from: BundleStatus::Signed, event: EventType::Replace, leg: Some(LegKind::Main), after: UNMINED, to: BundleStatus::Signed },
+    Transition { from: BundleStatus::Signed, event: EventType::Include, leg: Some(LegKind::Main), after: IN_FLIGHT, to: BundleStatus::Signed },
+    Transition { from: BundleStatus::Signed, event: EventType::Reorg, leg: Some(LegKind::Main), after: &[EventType::Include], to: BundleStatus::Signed },
+    Transition { from: BundleStatus::Signed, event: EventType::Rebroadcast, leg: Some(LegKind::Main), after: UNMINED, to: BundleStatus::Signed },
+    Transition { from: BundleStatus::Signed, event: EventType::Confirm, leg: Some(LegKind::Main), after: IN_FLIGHT, to: BundleStatus::MainConfirmed },
+    Transition { from: BundleStatus::Signed, event: EventType::Fail, leg: Some(LegKind::Main), after: SIGNED_AND_UNSETTLED, to: BundleStatus::Failed },
+    Transition { from: BundleStatus::Signed, event: EventType::Error, leg: Some(LegKind::Main), after: SIGNED_AND_UNSETTLED, to: BundleStatus::Errored },
+
+    // Fee leg, only once the main leg is confirmed
+    Transition { from: BundleStatus::MainConfirmed, event: EventType::Broadcast, leg: Some(LegKind::Fee), after: &[EventType::Confirm], to: BundleStatus::MainConfirmed },
+    Transition { from: BundleStatus::MainConfirmed, event: EventType::Skip, leg: Some(LegKind::Fee), after: &[EventType::Confirm], to: BundleStatus::Completed },
+    Transition { from: BundleStatus::MainConfirmed, event: EventType::Replace, leg: Some(LegKind::Fee), after: UNMINED, to: BundleStatus::MainConfirmed },
+    Transition { from: BundleStatus::MainConfirmed, event: EventType::Include, leg: Some(LegKind::Fee), after: IN_FLIGHT, to: BundleStatus::MainConfirmed },
+    Transition { from: BundleStatus::MainConfirmed, event: EventType::Reorg, leg: Some(LegKind::Fee), after: &[EventType::Include], to: BundleStatus::MainConfirmed },
+    Transition { from: BundleStatus::MainConfirmed, event: EventType::Rebroadcast, leg: Some(LegKind::Fee), after: UNMINED, to: BundleStatus::MainConfirmed },
+    Transition { from: BundleStatus::MainConfirmed, event: EventType::Confirm, leg: Some(LegKind::Fee), after: IN_FLIGHT, to: BundleStatus::Completed },
+    Transition { from: BundleStatus::MainConfirmed, event: EventType::Fail, leg: Some(LegKind::Fee), after: FEE_UNSETTLED, to: BundleStatus::Failed },
+    Transition { from: BundleStatus::MainConfirmed, event: EventType::Error, leg: Some(LegKind::Fee), after: FEE_UNSETTLED, to: BundleStatus::Errored },
+];
+
+/// Events after which the next leg may be sent: the bundle was just signed, or its last leg
+/// sent is confirmed.
+const SENDABLE: &[EventType] = &[EventType::Sign, EventType::Confirm];
+
+/// Events after which the bundle has a leg sent and not yet mined, so it can still be
+/// replaced or rebroadcast.
+const UNMINED: &[EventType] = &[
+    EventType::Broadcast,
+    EventType::Replace,
+    EventType::Reorg,
+    EventType::Rebroadcast,
+];
+
+/// Events after which the bundle has a leg sent and not yet settled. Whatever follows one of
+/// them is about that leg.
+const IN_FLIGHT: &[EventType] = &[
+    EventType::Broadcast,
+    EventType::Replace,
+    EventType::Include,
+    EventType::Reorg,
+    EventType::Rebroadcast,
+];
+
+/// Every event a signed bundle can be left at before its main leg is confirmed.
+const SIGNED_AND_UNSETTLED: &[EventType] = &[
+    EventType::Sign,
+    EventType::Confirm,
+    EventType::Broadcast,
+    EventType::Replace,
+    EventType::Include,
+    EventType::Reorg,
+    EventType::Rebroadcast,
+];
+
+/// Every event a bundle can be left at between its main leg's confirmation and its fee leg's.
+const FEE_UNSETTLED: &[EventType] = &[
+    EventType::Confirm,
+    EventType::Broadcast,
+    EventType::Replace,
+    EventType::Include,
+    EventType::Reorg,
+    EventType::Rebroadcast,
+];
+
+impl Transition {
+    fn applies_to(&self, last_event: &TransactionEvent, event: &EventType) -> bool {
+        self.from == last_event.bundle_snapshot.status
+            && self.event == *event
+            && self.after.contains(&last_event.event_type)
+    }
+
+    fn names(&self, leg: Option<TransactionLeg>) -> bool {
+        self.leg == leg.map(LegKind::of)
+    }
+}
+
+/// Finds the transition for applying `event` on `leg` after `last_event`. A split leg the
+/// bundle does not have is rejected, and so is any leg but the one in flight while a leg is.
+pub fn lookup(
+    last_event: &TransactionEvent,
+    event: EventType,
+    leg: Option<TransactionLeg>,
+) -> Result<&'static Transition, TransactionError> {
+    let in_bundle = leg.is_none_or(|leg| last_event.bundle_snapshot.get_leg(leg).is_some());
+    let other_leg = IN_FLIGHT.contains(&last_event.event_type)
+        && last_event.leg.is_some_and(|in_flight| leg != Some(in_flight));
+
+    TRANSITIONS
+        .iter()
+        .find(|t| in_bundle && !other_leg && t.applies_to(last_event, &event) && t.names(leg))
+        .ok_or_else(|| rejected(last_event, &event, leg))
+}
+
+/// Finds the transition for `event` when the leg is implied by the bundle's state: the
+/// first leg, in nonce order, that has not been sent and that `event` applies to.
+pub fn next_leg(
+    last_event: &TransactionEvent,
+    event: EventType,
+) -> Result<(&'static Transition, TransactionLeg), TransactionError> {
+    let bundle = &last_event.bundle_snapshot;
+
+    bundle.legs()
+        .filter(|leg| bundle.leg(*leg).transaction_hash.is_none())
+        .find_map(|leg| lookup(last_event, event.clone(), Some(leg)).ok().map(|t| (t, leg)))
+        .ok_or_else(|| rejected(last_event, &event, None))
+}
+
+fn rejected(last_event: &TransactionEvent, event: &EventType, leg: Option<TransactionLeg>) -> TransactionError {
+    let event = match leg {
+        Some(leg) => format!("{}({}) after {}", event, leg, last_event.event_type),
+        None => format!("{} after {}", event, last_event.event_type),
+    };
+
+    TransactionError::InvalidStateTransition {
+        event,
+        status: last_event.bundle_snapshot.status.to_string(),
+    }
+}
+
+/// Whether a bundle in `status` is finished: no event can follow.
+pub fn is_terminal(status: &BundleStatus) -> bool {
+    !TRANSITIONS.iter().any(|t| t.from == *status)
+}
+
+fn label(t: &Transition) -> String {
+    match t.leg {
+        Some(kind) => format!("{} ({:?})", t.event, kind),
+        None => t.event.to_string(),
+    }
+}
+
+fn terminal_states() -> Vec<&'static BundleStatus> {
+    let mut terminals: Vec<&BundleStatus> = Vec::new();
+    for t in TRANSITIONS {
+        if is_terminal(&t.to) && !terminals.contains(&&t.to) {
+            terminals.push(&t.to);
+        }
+    }
+    terminals
+}
+
+/// Renders the lifecycle as a Mermaid state diagram.
+pub fn to_mermaid() -> String {
+    let mut out = String::from("stateDiagram-v2\n");
+    let _ = writeln!(out, "    [*] --> {}: {}", BundleStatus::Initiated, EventType::Initiate);
+    for t in TRANSITIONS {
+        let _ = writeln!(out, "    {} --> {}: {}", t.from, t.to, label(t));
+    }
+    for status in terminal_states() {
+        let _ = writeln!(out, "    {} --> [*]", status);
+    }
+    out
+}
+
+/// Renders the lifecycle as a Graphviz DOT digraph.
+pub fn to_dot() -> String {
+    let mut out = String::from("digraph BundleLifecycle {\n    rankdir=LR;\n    start [shape=point];\n");
+    for status in terminal_states() {
+        let _ = writeln!(out, "    {} [shape=doublecircle];", status);
+    }
+    let _ = writeln!(out, "    start -> {} [label=\"{}\"];", BundleStatus::Initiated, EventType::Initiate);
+    for t in TRANSITIONS {
+        let _ = writeln!(out, "    {} -> {} [label=\"{}\"];", t.from, t.to, label(t));
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use ethers_core::types::H256;
+    use crate::models::transactions::{BundleMetadata, FinalityStage, TokenType, Transaction, TransactionBundle, TransactionStatus};
+
+    const STATUSES: [BundleStatus; 8] = [
+        BundleStatus::Initiated,
+        BundleStatus::Signed,
+        BundleStatus::MainConfirmed,
+        BundleStatus::Completed,
+        BundleStatus::Failed,
+        BundleStatus::Cancelled,
+        BundleStatus::Errored,
+        BundleStatus::Expired,
+    ];
+
+    const EVENTS: [EventType; 13] = [
+        EventType::Initiate,
+        EventType::Sign,
+        EventType::Broadcast,
+        EventType::Confirm,
+        EventType::Fail,
+        EventType::Cancel,
+        EventType::Error,
+        EventType::Skip,
+        EventType::Expire,
+        EventType::Replace,
+        EventType::Include,
+        EventType::Reorg,
+        EventType::Rebroadcast,
+    ];
+
+    const LEGS: [Option<TransactionLeg>; 5] = [
+        None,
+        Some(TransactionLeg::Fee),
+        Some(TransactionLeg::Main),
+        Some(TransactionLeg::Split(0)),
+        Some(TransactionLeg::Split(2)),
+    ];
+
+    fn event_in(status: BundleStatus, last: EventType) -> TransactionEvent {
+        let fee_tx = Transaction::new("user".into(), "from".into(), "foxy".into(), 0, TokenType::ETH, 0, "GBP".into(), 1);
+        let main_tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 0);
+        let mut bundle = TransactionBundle::new("user".into(), fee_tx, main_tx, Some(BundleMetadata::default()));
+        bundle.status = status.clone();
+
+        TransactionEvent {
+            event_id: "event".into(),
+            idempotency_key: None,
+            sequence: 1,
+            bundle_id: bundle.bundle_id.clone(),
+            user_id: "user".into(),
+            event_type: last,
+            leg: None,
+            created_at: Utc::now(),
+            bundle_status: Some(status),
+            transaction_status: None,
+            bundle_snapshot: bundle,
+        }
+    }
+
+    #[test]
+    fn keys_are_unique() {
+        for (i, a) in TRANSITIONS.iter().enumerate() {
+            for b in &TRANSITIONS[i + 1..] {
+                let overlaps = a.from == b.from
+                    && a.event == b.event
+                    && a.leg == b.leg
+                    && a.after.iter().any(|e| b.after.contains(e));
+                assert!(!overlaps, "duplicate transition: {:?} / {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn lookup_accepts_exactly_the_table() {
+        for status in STATUSES {
+            for last in EVENTS {
+                for last_leg in LEGS {
+                    // One split leg, so Split(0) is in the bundle and Split(2) is not
+                    let mut last_event = event_in(status.clone(), last.clone());
+                    let split_tx = last_event.bundle_snapshot.main_tx.clone();
+                    last_event.bundle_snapshot.split_txs.push(split_tx);
+                    last_event.leg = last_leg;
+
+                    for event in EVENTS {
+                        for leg in LEGS {
+                            let other_leg = IN_FLIGHT.contains(&last) && last_leg.is_some() && leg != last_leg;
+                            let expected = TRANSITIONS.iter().find(|t| {
+                                t.from == status && t.event == event && t.names(leg) && t.after.contains(&last)
+                            }).filter(|_| leg != Some(TransactionLeg::Split(2)) && !other_leg);
+
+                            match (lookup(&last_event, event.clone(), leg), expected) {
+                                (Ok(found), Some(expected)) => assert!(std::ptr::eq(found, expected)),
+                                (Err(TransactionError::InvalidStateTransition { .. }), None) => {}
+                                (result, expected) => panic!(
+                                    "{:?} after {:?}({:?}) in {:?} on {:?}: got {:?}, expected {:?}",
+                                    event, last, last_leg, status, leg, result, expected
+                                ),
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Whatever follows a leg in flight is about that leg, even another split leg
+        let mut split_sent = event_in(BundleStatus::Signed, EventType::Broadcast);
+        let split_tx = split_sent.bundle_snapshot.main_tx.clone();
+        split_sent.bundle_snapshot.split_txs = vec![split_tx.clone(), split_tx];
+        split_sent.leg = Some(TransactionLeg::Split(0));
+        for event in [EventType::Confirm, EventType::Include, EventType::Replace, EventType::Rebroadcast, EventType::Fail, EventType::Error] {
+            assert!(lookup(&split_sent, event.clone(), Some(TransactionLeg::Split(0))).is_ok(), "{:?}", event);
+            assert!(lookup(&split_sent, event.clone(), Some(TransactionLeg::Split(1))).is_err(), "{:?}", event);
+            assert!(lookup(&split_sent, event.clone(), Some(TransactionLeg::Main)).is_err(), "{:?}", event);
+        }
+    }
+
+    #[test]
+    fn terminal_states_have_no_outgoing_transitions() {
+        for status in [BundleStatus::Completed, BundleStatus::Failed, BundleStatus::Cancelled, BundleStatus::Errored, BundleStatus::Expired] {
+            assert!(is_terminal(&status), "{} should be terminal", status);
+        }
+    }
+
+    #[test]
+    fn every_non_terminal_state_is_reachable() {
+        let mut reached = vec![BundleStatus::Initiated];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for t in TRANSITIONS {
+                if reached.contains(&t.from) && !reached.contains(&t.to) {
+                    reached.push(t.to.clone());
+                    changed = true;
+                }
+            }
+        }
+
+        for t in TRANSITIONS {
+            assert!(reached.contains(&t.from), "{} is unreachable", t.from);
+        }
+    }
+
+    #[test]
+    fn next_leg_picks_the_broadcastable_leg() {
+        let signed = event_in(BundleStatus::Signed, EventType::Sign);
+        assert_eq!(next_leg(&signed, EventType::Broadcast).unwrap().1, TransactionLeg::Main);
+
+        let main_confirmed = event_in(BundleStatus::MainConfirmed, EventType::Confirm);
+        assert_eq!(next_leg(&main_confirmed, EventType::Broadcast).unwrap().1, TransactionLeg::Fee);
+
+        let broadcast = event_in(BundleStatus::Signed, EventType::Broadcast);
+        assert!(next_leg(&broadcast, EventType::Broadcast).is_err());
+    }
+
+    #[test]
+    fn builders_follow_the_table() {
+        let initiated = event_in(BundleStatus::Initiated, EventType::Initiate);
+        let signed = TransactionEvent::sign(&initiated, "0x01", "0x02", &[]).unwrap();
+        assert_eq!(signed.bundle_snapshot.status, lookup(&initiated, EventType::Sign, None).unwrap().to);
+
+        let main_sent = TransactionEvent::broadcast(&signed, H256::repeat_byte(1)).unwrap();
+        assert_eq!(main_sent.leg, Some(TransactionLeg::Main));
+
+        let main_tx = main_sent.bundle_snapshot.main_tx.clone().with_status(TransactionStatus::Confirmed);
+        let main_confirmed = TransactionEvent::confirm(&main_sent, &main_tx).unwrap();
+        assert_eq!(main_confirmed.bundle_snapshot.status, BundleStatus::MainConfirmed);
+
+        // Zero-value fee goes straight from MainConfirmed to Completed without a broadcast
+        let fee_tx = main_confirmed.bundle_snapshot.fee_tx.clone();
+        let skipped = TransactionEvent::skip(&main_confirmed, &fee_tx).unwrap();
+        assert_eq!(skipped.bundle_snapshot.status, BundleStatus::Completed);
+        assert_eq!(skipped.bundle_snapshot.fee_tx.status, TransactionStatus::Skipped);
+
+        assert!(TransactionEvent::fail(&skipped, TransactionLeg::Fee).is_err());
+        assert!(TransactionEvent::error(&skipped, TransactionLeg::Fee).is_err());
+    }
+
+    #[test]
+    fn split_legs_go_out_one_at_a_time_before_the_main_leg() {
+        let mut initiated = event_in(BundleStatus::Initiated, EventType::Initiate);
+        let split_tx = |to: &str| Transaction::new("user".into(), "from".into(), to.into(), 500, TokenType::ETH, 50, "GBP".into(), 0);
+        initiated.bundle_snapshot.split_txs = vec![split_tx("carol"), split_tx("dave")];
+
+        assert!(TransactionEvent::sign(&initiated, "0x01", "0x02", &["0x03".into()]).is_err());
+        let mut last = TransactionEvent::sign(&initiated, "0x01", "0x02", &["0x03".into(), "0x04".into()]).unwrap();
+        // An index the bundle does not have is rejected, not a panic
+        assert!(TransactionEvent::fail(&last, TransactionLeg::Split(7)).is_err());
+
+        for (i, leg) in [TransactionLeg::Split(0), TransactionLeg::Split(1), TransactionLeg::Main].into_iter().enumerate() {
+            assert_eq!(last.bundle_snapshot.status, BundleStatus::Signed);
+            assert_eq!(next_leg(&last, EventType::Broadcast).unwrap().1, leg);
+
+            let sent = TransactionEvent::broadcast(&last, H256::repeat_byte(i as u8 + 1)).unwrap();
+            assert_eq!(sent.leg, Some(leg));
+            // Nothing else goes out until this leg is mined
+            assert!(next_leg(&sent, EventType::Broadcast).is_err());
+
+            let mined = sent.bundle_snapshot.leg(leg).clone();
+            last = TransactionEvent::confirm(&sent, &mined).unwrap();
+            assert_eq!(last.bundle_snapshot.leg(leg).status, TransactionStatus::Confirmed);
+        }
+        assert_eq!(last.bundle_snapshot.status, BundleStatus::MainConfirmed);
+
+        let fee_sent = TransactionEvent::broadcast(&last, H256::repeat_byte(9)).unwrap();
+        let fee_tx = fee_sent.bundle_snapshot.fee_tx.clone();
+        let completed = TransactionEvent::confirm(&fee_sent, &fee_tx).unwrap();
+        assert_eq!(completed.bundle_snapshot.status, BundleStatus::Completed);
+        assert!(completed.bundle_snapshot.legs().all(|leg| completed.bundle_snapshot.leg(leg).status == TransactionStatus::Confirmed));
+    }
+
+    #[test]
+    fn included_legs_stay_pending_until_confirmed_and_survive_reorgs() {
+        let signed = event_in(BundleStatus::Signed, EventType::Sign);
+        let sent = TransactionEvent::broadcast(&signed, H256::repeat_byte(1)).unwrap();
+        // Only a leg recorded in a block can be reorged out of it
+        assert!(TransactionEvent::reorg(&sent, TransactionLeg::Main).is_err());
+
+        let mined = sent.bundle_snapshot.main_tx.clone()
+            .with_block_number(Some(10))
+            .with_block_hash(Some("0xaa".into()))
+            .with_finality(Some(FinalityStage::Included));
+        let included = TransactionEvent::include(&sent, &mined).unwrap();
+        assert_eq!(included.bundle_snapshot.status, BundleStatus::Signed);
+        assert_eq!(included.bundle_snapshot.main_tx.status, TransactionStatus::Pending);
+        // A mined leg cannot be sped up, and the next leg waits for its confirmation
+        assert!(lookup(&included, EventType::Replace, Some(TransactionLeg::Main)).is_err());
+        assert!(next_leg(&included, EventType::Broadcast).is_err());
+
+        let reorged = TransactionEvent::reorg(&included, TransactionLeg::Main).unwrap();
+        let main_tx = &reorged.bundle_snapshot.main_tx;
+        assert_eq!(main_tx.status, TransactionStatus::Pending);
+        assert_eq!((main_tx.block_number, main_tx.block_hash.as_deref(), main_tx.finality), (None, None, None));
+        assert_eq!(main_tx.transaction_hash, sent.bundle_snapshot.main_tx.transaction_hash);
+
+        let remined = mined.with_block_hash(Some("0xbb".into())).with_finality(Some(FinalityStage::Safe));
+        let confirmed = TransactionEvent::confirm(&reorged, &remined).unwrap();
+        assert_eq!(confirmed.bundle_snapshot.status, BundleStatus::MainConfirmed);
+        assert_eq!(confirmed.bundle_snapshot.main_tx.finality, Some(FinalityStage::Safe));
+    }
+
+    #[test]
+    fn dropped_legs_are_rebroadcast_until_mined() {
+        let signed = event_in(BundleStatus::Signed, EventType::Sign);
+        assert!(lookup(&signed, EventType::Rebroadcast, Some(TransactionLeg::Main)).is_err());
+
+        let sent = TransactionEvent::broadcast(&signed, H256::repeat_byte(1)).unwrap();
+        let resent = TransactionEvent::rebroadcast(&sent, TransactionLeg::Main).unwrap();
+        assert_eq!(resent.bundle_snapshot.status, BundleStatus::Signed);
+        assert_eq!(resent.bundle_snapshot.rebroadcast_attempts, 1);
+        // Nothing else goes out while the leg is pending again
+        assert!(next_leg(&resent, EventType::Broadcast).is_err());
+
+        let mined = resent.bundle_snapshot.main_tx.clone()
+            .with_block_number(Some(10))
+            .with_block_hash(Some("0xaa".into()));
+        let included = TransactionEvent::include(&resent, &mined).unwrap();
+        // A mined leg was not dropped
+        assert!(TransactionEvent::rebroadcast(&included, TransactionLeg::Main).is_err());
+    }
+
+    #[test]
+    fn diagrams_include_every_transition() {
+        let mermaid = to_mermaid();
+        let dot = to_dot();
+
+        assert!(mermaid.starts_with("stateDiagram-v2"));
+        assert!(dot.starts_with("digraph"));
+        for t in TRANSITIONS {
+            assert!(mermaid.contains(&format!("{} --> {}: {}", t.from, t.to, label(t))));
+            assert!(dot.contains(&format!("{} -> {} [label=\"{}\"]", t.from, t.to, label(t))));
+        }
+        assert!(mermaid.contains("Completed --> [*]"));
+    }
+}
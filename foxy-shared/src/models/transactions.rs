@@ -1,4 +1,5 @@
 use crate::models::user_device::UserDevice;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use lambda_http::tracing::info;
 use crate::utilities::parsers::u128_from_str;
@@ -7,21 +8,26 @@ use std::fmt;
 use std::fmt::Formatter;
 use std::str::FromStr;
 use std::sync::Arc;
-use serde::{Serialize, Deserialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use chrono::{DateTime, Utc};
 use crate::models::errors::TransactionError;
 use crate::models::estimate_flags::EstimateFlags;
+use crate::models::state_machine;
 use crate::services::cognito_services::get_party_details_from_wallet;
-use crate::utilities::config::{get_chain_id, get_foxy_wallet, get_network};
+use crate::utilities::config::{get_chain_id, get_chain_id_for, get_foxy_wallet, get_network, get_quote_signing_key, get_transaction_event_table};
+use crate::utilities::token_registry::{token_registry, token_registry_for};
+use crate::utilities::erc20;
 use aws_sdk_dynamodb::Client as DynamoDbClient;
 use aws_sdk_cognitoidentityprovider::Client as CognitoClient;
 use aws_sdk_dynamodb::types::AttributeValue;
-use ethers_core::types::H256;
+use ethers_core::types::{Log, H256};
 use ethers_core::utils::keccak256;
 use log::warn;
 use uuid::Uuid;
-use crate::database::transaction_event::TransactionEventManager;
-use crate::utilities::nonce_manager::NonceManager;
+use crate::database::event_store::EventStore;
+use crate::utilities::quote::Quote;
+use crate::database::quote_ledger::QuoteLedger;
+use crate::database::nonce_leases::NonceLeases;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionBundle {
@@ -30,6 +36,10 @@ pub struct TransactionBundle {
     pub status: BundleStatus,
     pub fee_tx: Transaction,
     pub main_tx: Transaction,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub split_txs: Vec<Transaction>, // Further recipients of a split payment, sent ahead of main_tx
+    #[serde(default)]
+    pub rebroadcast_attempts: u32, // times the watcher resent a leg the node had dropped
     pub metadata: Option<BundleMetadata>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -48,18 +58,80 @@ impl TransactionBundle {
             status: BundleStatus::Initiated,
             fee_tx,
             main_tx,
+            split_txs: Vec::new(),
+            rebroadcast_attempts: 0,
             metadata,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
     }
 
+    /// Adds recipient legs for a split payment. Their nonces must come before the main leg's.
+    pub fn with_split_txs(mut self, split_txs: Vec<Transaction>) -> Self {
+        self.split_txs = split_txs;
+        self
+    }
+
+    /// Panics on a split index the bundle does not have; legs come from the bundle itself
+    /// or from its events. See `get_leg` for legs from elsewhere.
+    pub fn leg(&self, leg: TransactionLeg) -> &Transaction {
+        self.get_leg(leg).expect("split leg belongs to the bundle")
+    }
+
+    pub fn get_leg(&self, leg: TransactionLeg) -> Option<&Transaction> {
+        match leg {
+            TransactionLeg::Fee => Some(&self.fee_tx),
+            TransactionLeg::Main => Some(&self.main_tx),
+            TransactionLeg::Split(i) => self.split_txs.get(i as usize),
+        }
+    }
+
+    pub fn leg_mut(&mut self, leg: TransactionLeg) -> &mut Transaction {
+        match leg {
+            TransactionLeg::Fee => &mut self.fee_tx,
+            TransactionLeg::Main => &mut self.main_tx,
+            TransactionLeg::Split(i) => &mut self.split_txs[i as usize],
+        }
+    }
+
+    /// The legs paying a recipient, in nonce order: the split legs, then the main leg.
+    pub fn recipient_legs(&self) -> impl Iterator<Item = TransactionLeg> {
+        (0..self.split_txs.len() as u8)
+            .map(TransactionLeg::Split)
+            .chain(std::iter::once(TransactionLeg::Main))
+    }
+
+    /// Every leg in nonce order, the fee leg last.
+    pub fn legs(&self) -> impl Iterator<Item = TransactionLeg> {
+        self.recipient_legs().chain(std::iter::once(TransactionLeg::Fee))
+    }
+
+    pub fn legs_mut(&mut self) -> impl Iterator<Item = &mut Transaction> {
+        self.split_txs.iter_mut().chain([&mut self.main_tx, &mut self.fee_tx])
+    }
+
+    pub fn leg_of(&self, transaction_id: &str) -> Option<TransactionLeg> {
+        self.legs().find(|leg| self.leg(*leg).transaction_id == transaction_id)
+    }
+
+    /// True once an unsigned bundle has outlived `ttl`; its quote, nonce and gas pricing
+    /// can no longer be trusted.
+    pub fn is_expired(&self, now: DateTime<Utc>, ttl: chrono::Duration) -> bool {
+        self.status == BundleStatus::Initiated && now - self.created_at > ttl
+    }
+
     pub async fn from_request(
         user_id: String,
         request: TransactionRequest,
         cognito_client: &CognitoClient,
         dynamo_db_client: &DynamoDbClient,
     ) -> Result<Self, TransactionError> {
+        // Only a quote we signed can price the bundle, whatever the client sent alongside it
+        let token = request.quote.as_deref()
+            .ok_or_else(|| TransactionError::InvalidQuote("No quote provided".to_string()))?;
+        let quote = Quote::verify(token, &get_quote_signing_key(), Utc::now())?;
+        let request = quote.bind(&user_id, request)?;
+
         let sender_details = get_party_details_from_wallet(
             cognito_client,
             dynamo_db_client,
@@ -74,14 +146,54 @@ impl TransactionBundle {
         )
             .await?;
 
+        let mut split_recipients = Vec::with_capacity(quote.splits.len());
+        for share in &quote.splits {
+            split_recipients.push(get_party_details_from_wallet(cognito_client, dynamo_db_client, &share.recipient_address).await?);
+        }
+
         let gas_pricing = request
             .gas_pricing
             .as_ref()
             .ok_or_else(|| TransactionError::MissingGasEstimate)?;
         let fee_tx_value = request.service_fee;
 
-        let nonces = NonceManager::new()?;
-        let nonce = nonces.get_nonce(&request.sender_address).await?;
+        let bundle_id = Uuid::new_v4().to_string();
+        let events = Arc::new(dynamo_db_client.clone());
+        let quotes = QuoteLedger::new(events.clone(), get_transaction_event_table());
+        quotes.claim(&quote, &bundle_id).await?;
+
+        // Leased rather than read from the chain, so a second bundle sent before this one is
+        // broadcast gets the next run. Split legs take the first nonces, then main, then fee.
+        let splits = quote.splits.len() as u64;
+        let reserved = NonceLeases::new(events, get_transaction_event_table())
+            .reserve(&request.network, &request.sender_address, &bundle_id, splits + 2)
+            .await;
+
+        // No bundle was started, so the quote is still good for a retry
+        let nonce = match reserved {
+            Ok(nonce) => nonce,
+            Err(e) => {
+                if let Err(release) = quotes.release(&quote, &bundle_id).await {
+                    warn!("Failed to release quote {} after nonce reservation failed: {:?}", quote.quote_id, release);
+                }
+                return Err(e);
+            }
+        };
+
+        let split_txs = quote.splits.iter().zip(0..).map(|(share, i)| {
+            Transaction::new(
+                user_id.clone(),
+                sender_details.wallet.clone(),
+                share.recipient_address.clone(),
+                share.transaction_value,
+                request.token_type.clone(),
+                share.fiat_value,
+                request.fiat_currency_code.clone(),
+                nonce + i,
+            ).with_network(&request.network)
+                .with_priority_level(&request.priority_level)
+                .with_gas_pricing(gas_pricing)
+        }).collect();
 
         let fee_tx = Transaction::new(
             user_id.clone(),
@@ -91,8 +203,10 @@ impl TransactionBundle {
             request.token_type.clone(),
             request.fiat_value,
             request.fiat_currency_code.clone(),
-            nonce + 1, //perform the main transaction first
-        ).with_gas_pricing(gas_pricing);
+            nonce + splits + 1, //perform the main transaction first
+        ).with_network(&request.network)
+            .with_priority_level(&request.priority_level)
+            .with_gas_pricing(gas_pricing);
 
         let main_tx = Transaction::new(
             user_id.clone(),
@@ -102,8 +216,10 @@ impl TransactionBundle {
             request.token_type.clone(),
             request.fiat_value,
             request.fiat_currency_code.clone(),
-            nonce,
-        ).with_gas_pricing(gas_pricing);
+            nonce + splits,
+        ).with_network(&request.network)
+            .with_priority_level(&request.priority_level)
+            .with_gas_pricing(gas_pricing);
 
         let metadata = BundleMetadata {
             display_currency: request.fiat_currency_code,
@@ -118,14 +234,17 @@ impl TransactionBundle {
             gas_pricing: gas_pricing.clone(),
             service_fee_minor: Some(request.service_fee_minor),
             user_device: request.user_device.clone(),
+            split_recipients,
         };
 
         Ok(TransactionBundle {
-            bundle_id: Uuid::new_v4().to_string(),
+            bundle_id,
             user_id,
             status: BundleStatus::Initiated,
             fee_tx,
             main_tx,
+            split_txs,
+            rebroadcast_attempts: 0,
             metadata: Some(metadata),
             created_at: Utc::now(),
             updated_at: Utc::now(),
@@ -148,6 +267,8 @@ pub struct BundleMetadata {
     pub gas_pricing: GasPricing,
     pub service_fee_minor: Option<u64>,
     pub user_device: UserDevice,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub split_recipients: Vec<PartyDetails>, // One per split leg, in the same order
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -158,7 +279,8 @@ pub enum BundleStatus {
     Completed,
     Failed,
     Cancelled,
-    Errored
+    Errored,
+    Expired,
 }
 
 impl fmt::Display for BundleStatus {
@@ -171,6 +293,7 @@ impl fmt::Display for BundleStatus {
             BundleStatus::Failed => "Failed",
             BundleStatus::Cancelled => "Cancelled",
             BundleStatus::Errored => "Errored",
+            BundleStatus::Expired => "Expired",
         };
         write!(f, "{}", s)
     }
@@ -189,6 +312,7 @@ impl FromStr for BundleStatus {
             "failed" => Ok(BundleStatus::Failed),
             "cancelled" => Ok(BundleStatus::Cancelled),
             "errored" => Ok(BundleStatus::Errored),
+            "expired" => Ok(BundleStatus::Expired),
             _ => Err(format!("Invalid bundle status: {}", s)),
         }
     }
@@ -205,21 +329,23 @@ pub enum TransactionStatus {
     Cancelled,   // User/system abort
     Error,       // Infra/systemic issue
     Skipped,     // Zero value transactions are skipped (typically fees in low-value tx)
+    Expired,     // Never signed before the bundle's quote went stale
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TransactionLeg {
     Fee,
     Main,
+    Split(u8), // A further recipient of a split payment, indexing the bundle's split_txs
 }
 
 impl fmt::Display for TransactionLeg {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        let s = match self {
-            TransactionLeg::Fee => "Fee",
-            TransactionLeg::Main => "Main",
-        };
-        write!(f, "{}", s)
+        match self {
+            TransactionLeg::Fee => write!(f, "Fee"),
+            TransactionLeg::Main => write!(f, "Main"),
+            TransactionLeg::Split(i) => write!(f, "Split#{}", i),
+        }
     }
 }
 
@@ -230,7 +356,11 @@ impl FromStr for TransactionLeg {
         match s.to_lowercase().as_str() {
             "fee" => Ok(TransactionLeg::Fee),
             "main" => Ok(TransactionLeg::Main),
-            _ => Err(format!("Invalid transaction leg: {}", s)),
+            other => other
+                .strip_prefix("split#")
+                .and_then(|i| i.parse().ok())
+                .map(TransactionLeg::Split)
+                .ok_or_else(|| format!("Invalid transaction leg: {}", s)),
         }
     }
 }
@@ -246,6 +376,7 @@ impl fmt::Display for TransactionStatus {
             TransactionStatus::Cancelled => write!(f, "Cancelled"),
             TransactionStatus::Error => write!(f, "Error"),
             TransactionStatus::Skipped => write!(f, "Skipped"),
+            TransactionStatus::Expired => write!(f, "Expired"),
         }
     }
 }
@@ -262,11 +393,44 @@ impl FromStr for TransactionStatus {
             "failed" => Ok(TransactionStatus::Failed),
             "cancelled" => Ok(TransactionStatus::Cancelled),
             "error" => Ok(TransactionStatus::Error),
+            "expired" => Ok(TransactionStatus::Expired),
             other => Err(format!("Unknown transaction status: {}", other)),
         }
     }
 }
 
+/// How settled the block holding a mined leg is. Included blocks can still be reorged out;
+/// on Optimism, safe blocks are posted to L1 and finalized blocks are final on L1 too.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FinalityStage {
+    Included,
+    Safe,
+    Finalized,
+}
+
+impl fmt::Display for FinalityStage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FinalityStage::Included => write!(f, "Included"),
+            FinalityStage::Safe => write!(f, "Safe"),
+            FinalityStage::Finalized => write!(f, "Finalized"),
+        }
+    }
+}
+
+impl FromStr for FinalityStage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "included" => Ok(FinalityStage::Included),
+            "safe" => Ok(FinalityStage::Safe),
+            "finalized" => Ok(FinalityStage::Finalized),
+            _ => Err(format!("Invalid finality stage: {}", s)),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum EventType {
     Initiate,
@@ -277,6 +441,11 @@ pub enum EventType {
     Cancel,
     Error,
     Skip,
+    Expire,
+    Replace,
+    Include,
+    Reorg,
+    Rebroadcast,
 }
 
 impl FromStr for EventType {
@@ -292,6 +461,11 @@ impl FromStr for EventType {
             "cancel" => Ok(EventType::Cancel),
             "error" => Ok(EventType::Error),
             "skip" => Ok(EventType::Skip),
+            "expire" => Ok(EventType::Expire),
+            "replace" => Ok(EventType::Replace),
+            "include" => Ok(EventType::Include),
+            "reorg" => Ok(EventType::Reorg),
+            "rebroadcast" => Ok(EventType::Rebroadcast),
             _ => Err(format!("Invalid event type: {}", s)),
         }
     }
@@ -308,33 +482,39 @@ impl fmt::Display for EventType {
             EventType::Cancel => write!(f, "Cancel"),
             EventType::Error => write!(f, "Error"),
             EventType::Skip => write!(f, "Skip"),
+            EventType::Expire => write!(f, "Expire"),
+            EventType::Replace => write!(f, "Replace"),
+            EventType::Include => write!(f, "Include"),
+            EventType::Reorg => write!(f, "Reorg"),
+            EventType::Rebroadcast => write!(f, "Rebroadcast"),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
-#[serde(rename_all = "UPPERCASE")]
-pub enum TokenType {
-    #[default]
-    ETH,
-    USDC,
-}
+/// A token symbol such as "ETH" or "USDC". Which symbols can be used, and their decimals
+/// and contracts, is up to the network's `TokenRegistry`.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, Hash)]
+#[serde(transparent)]
+pub struct TokenType(Cow<'static, str>);
 
 impl TokenType {
-    pub fn decimals(&self) -> u8 {
-        match self {
-            TokenType::ETH => 18,
-            TokenType::USDC => 6,
-        }
+    pub const ETH: TokenType = TokenType(Cow::Borrowed("ETH"));
+    pub const USDC: TokenType = TokenType(Cow::Borrowed("USDC"));
+
+    pub fn symbol(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for TokenType {
+    fn default() -> Self {
+        TokenType::ETH
     }
 }
 
 impl fmt::Display for TokenType {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match self {
-            TokenType::ETH => write!(f, "ETH"),
-            TokenType::USDC => write!(f, "USDC"),
-        }
+        write!(f, "{}", self.0)
     }
 }
 
@@ -342,11 +522,18 @@ impl FromStr for TokenType {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_uppercase().as_str() {
-            "ETH" => Ok(TokenType::ETH),
-            "USDC" => Ok(TokenType::USDC),
-            _ => Err(format!("Invalid token type: {}", s)),
+        let symbol = s.trim().to_uppercase();
+        if symbol.is_empty() || !symbol.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(format!("Invalid token type: {}", s));
         }
+        Ok(TokenType(Cow::Owned(symbol)))
+    }
+}
+
+impl<'de> Deserialize<'de> for TokenType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let symbol = String::deserialize(deserializer)?;
+        TokenType::from_str(&symbol).map_err(de::Error::custom)
     }
 }
 
@@ -367,11 +554,17 @@ pub struct Transaction {
     pub chain_id: u64, // Supports multi-chain transactions
     pub signed_tx: Option<String>, //the signed data
     pub transaction_hash: Option<String>, // Assigned after broadcast
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replaced_tx_hashes: Vec<String>, // Earlier transactions with this nonce, any of which may still be mined
     pub event_log: Option<String>, // Stores blockchain receipt events
     pub priority_level: PriorityLevel, // Transaction priority (e.g., 1-5 scale)
     pub network: Network, // Network name (Optimism, Ethereum, etc.)
     pub gas_price: Option<u64>, // Gas price used for the transaction
     pub gas_used: Option<u64>, // Gas consumed by the transaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_gas_price: Option<u64>, // Price per gas actually paid, from the receipt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l1_fee: Option<u64>, // Optimism L1 data fee, paid on top of the gas
     pub gas_limit: Option<u64>, // Gas limit set for the transaction
     pub nonce: Option<u64>, // Nonce used for ordering transactions
     pub max_fee_per_gas: Option<u64>, // EIP-1559: Max fee willing to pay per gas unit
@@ -379,7 +572,13 @@ pub struct Transaction {
     pub total_fee_paid: Option<u64>, // total fees for simple view
     pub exchange_rate: Option<f64>, // rate at time of tx
     pub block_number: Option<u64>, // Block number the transaction was included in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_hash: Option<String>, // Hash of that block, to notice it being reorged out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finality: Option<FinalityStage>, // How final that block was when last checked
     pub receipt_status: Option<u8>, // Status from the transaction receipt (1 = success, 0 = fail)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_reason: Option<String>, // Why a mined leg failed, e.g. its decoded revert reason
     pub contract_address: Option<String>, // Required for ERC-20 transactions (e.g., USDC contract)
     pub approval_tx_hash: Option<String>, // Transaction hash for ERC-20 approval (if needed)
     pub recipient_tx_hash: Option<String>,
@@ -398,6 +597,8 @@ impl Transaction {
         fiat_currency: String,
         nonce: u64,
     ) -> Self {
+        let contract_address = token_registry().get(&token_type).and_then(|t| t.contract_address.clone());
+
         Self {
             transaction_id: Uuid::new_v4().to_string(),
             user_id,
@@ -416,6 +617,7 @@ impl Transaction {
             chain_id: get_chain_id(),
             signed_tx: None,
             transaction_hash: None,
+            replaced_tx_hashes: Vec::new(),
             event_log: None,
 
             priority_level: PriorityLevel::Standard,
@@ -423,6 +625,8 @@ impl Transaction {
 
             gas_price: None,
             gas_used: None,
+            effective_gas_price: None,
+            l1_fee: None,
             gas_limit: None,
             nonce: Some(nonce),
             max_fee_per_gas: None,
@@ -430,8 +634,11 @@ impl Transaction {
             total_fee_paid: None,
             exchange_rate: None,
             block_number: None,
+            block_hash: None,
+            finality: None,
             receipt_status: None,
-            contract_address: None,
+            failure_reason: None,
+            contract_address,
             approval_tx_hash: None,
             recipient_tx_hash: None,
             fee_tx_hash: None,
@@ -448,6 +655,21 @@ impl Transaction {
         self
     }
 
+    pub fn with_priority_level(mut self, priority_level: &PriorityLevel) -> Self {
+        self.priority_level = priority_level.clone();
+        self
+    }
+
+    /// Targets `network`: its chain ID, and the token's contract deployed there.
+    pub fn with_network(mut self, network: &Network) -> Self {
+        self.chain_id = get_chain_id_for(network);
+        self.contract_address = token_registry_for(network)
+            .get(&self.token_type)
+            .and_then(|t| t.contract_address.clone());
+        self.network = network.clone();
+        self
+    }
+
     pub fn with_nonce(mut self, nonce: u64) -> Self {
         self.nonce = Some(nonce);
         self
@@ -480,6 +702,16 @@ impl Transaction {
         self
     }
 
+    pub fn with_block_hash(mut self, block_hash: Option<String>) -> Self {
+        self.block_hash = block_hash;
+        self
+    }
+
+    pub fn with_finality(mut self, finality: Option<FinalityStage>) -> Self {
+        self.finality = finality;
+        self
+    }
+
     pub fn with_receipt_status(mut self, status: Option<u64>) -> Self {
         self.receipt_status = status.map(|s| s as u8);
         self
@@ -490,10 +722,78 @@ impl Transaction {
         self
     }
 
+    /// Records what the mined leg cost: the gas it used at the effective price per gas, plus
+    /// any L1 data fee. The quoted `gas_price` is kept to compare against.
+    pub fn with_gas_paid(mut self, gas_used: Option<u64>, effective_gas_price: Option<u64>, l1_fee: Option<u64>) -> Self {
+        self.gas_used = gas_used;
+        self.effective_gas_price = effective_gas_price;
+        self.l1_fee = l1_fee;
+        self.total_fee_paid = gas_used.zip(effective_gas_price)
+            .and_then(|(used, price)| used.checked_mul(price))
+            .and_then(|l2_fee| l2_fee.checked_add(l1_fee.unwrap_or(0)));
+        self
+    }
+
+    /// The most the leg was priced to cost in gas: its gas limit at its max fee per gas.
+    pub fn estimated_network_fee(&self) -> Option<u128> {
+        self.gas_limit.zip(self.max_fee_per_gas).map(|(limit, fee)| limit as u128 * fee as u128)
+    }
+
+    /// What the mined leg paid, as a percentage of `estimated_network_fee`.
+    pub fn fee_paid_percent_of_estimate(&self) -> Option<f64> {
+        let estimate = self.estimated_network_fee().filter(|fee| *fee > 0)?;
+        Some(self.total_fee_paid? as f64 * 100.0 / estimate as f64)
+    }
+
+    pub fn with_failure_reason(mut self, reason: Option<String>) -> Self {
+        self.failure_reason = reason;
+        self
+    }
+
+    /// The address the signed transaction is sent to: the token contract for ERC-20 legs,
+    /// otherwise the recipient.
+    pub fn to_address(&self) -> &str {
+        self.contract_address.as_deref().unwrap_or(&self.recipient_address)
+    }
+
+    /// The native value carried by the transaction; ERC-20 amounts travel in the calldata.
+    pub fn native_value(&self) -> u128 {
+        match self.contract_address {
+            None => self.transaction_value,
+            Some(_) => 0,
+        }
+    }
+
+    /// Calldata for the transaction: empty for ETH, `transfer(recipient, value)` for ERC-20.
+    pub fn call_data(&self) -> Result<String, TransactionError> {
+        match self.contract_address {
+            None => Ok("0x".to_string()),
+            Some(_) => erc20::transfer_calldata(&self.recipient_address, self.transaction_value)
+                .map_err(|e| {
+                    warn!("Cannot encode transfer for {}: {}", self.transaction_id, e);
+                    TransactionError::InvalidAddress
+                }),
+        }
+    }
+
+    /// Whether a mined receipt's logs show this leg's transfer. A token contract can succeed
+    /// without moving anything, so ERC-20 legs need a matching `Transfer` event; native
+    /// transfers emit no logs.
+    pub fn transfer_logged(&self, logs: &[Log]) -> bool {
+        match &self.contract_address {
+            None => true,
+            Some(contract) => erc20::has_transfer(logs, contract, &self.recipient_address, self.transaction_value),
+        }
+    }
+
     pub fn tx_hash(&self) -> Option<H256> {
+        Some(H256::from(keccak256(self.signed_tx_bytes()?)))
+    }
+
+    /// The signed transaction as it goes over the wire.
+    pub fn signed_tx_bytes(&self) -> Option<Vec<u8>> {
         let signed_tx = self.signed_tx.as_ref()?;
-        let raw = hex::decode(signed_tx.trim_start_matches("0x")).ok()?;
-        Some(H256::from(keccak256(&raw)))
+        hex::decode(signed_tx.trim_start_matches("0x")).ok()
     }
 }
 
@@ -501,6 +801,10 @@ impl Transaction {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TransactionEvent {
     pub event_id: String,
+    #[serde(default)]
+    pub sequence: u64, // position in the bundle's event stream, starting at 1; 0 for legacy events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>, // re-persisting an event with a key already used is a no-op
     pub bundle_id: String,
     pub user_id: String,
     pub event_type: EventType,
@@ -523,7 +827,9 @@ impl TransactionEvent {
         bundle_snapshot: TransactionBundle,
     ) -> Self {
         Self {
-            event_id: String::default(),
+            event_id: Uuid::new_v4().to_string(),
+            idempotency_key: None,
+            sequence: 0,
             bundle_id,
             user_id,
             event_type,
@@ -534,45 +840,99 @@ impl TransactionEvent {
             bundle_snapshot,
         }
     }
+
+    /// What persisting deduplicates on: the caller's idempotency key, or else the event ID.
+    pub fn dedup_key(&self) -> &str {
+        self.idempotency_key.as_deref().unwrap_or(&self.event_id)
+    }
+
+    /// Tags the event with a caller-chosen key, e.g. a client request ID, so that persisting
+    /// the same logical event again returns the original event ID instead of appending.
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+
     pub fn initiate(bundle: TransactionBundle) -> Result<Self, TransactionError> {
         if bundle.status != BundleStatus::Initiated {
             return Err(TransactionError::InvalidTransition(
                 format!("Cannot sign from status {:?}", bundle.status), ));
             }
 
-        Ok(TransactionEvent{
-            event_id: String::new(),
+        let event = Self::new(
+            bundle.bundle_id.clone(),
+            bundle.user_id.clone(),
+            EventType::Initiate,
+            None,
+            Some(BundleStatus::Initiated),
+            None,
+            Utc::now(),
+            bundle,
+        );
+        Ok(TransactionEvent { sequence: 1, ..event })
+    }
+
+    /// The event after `last_event` that leaves the bundle as `bundle`. Builders only change
+    /// the snapshot and pick its status; the event's identity and position come from here.
+    fn next_event(
+        last_event: &TransactionEvent,
+        event_type: EventType,
+        leg: Option<TransactionLeg>,
+        mut bundle: TransactionBundle,
+    ) -> TransactionEvent {
+        let now = Utc::now();
+        bundle.updated_at = now;
+
+        TransactionEvent {
+            event_id: Uuid::new_v4().to_string(),
+            idempotency_key: None,
+            sequence: last_event.sequence + 1,
             bundle_id: bundle.bundle_id.clone(),
-            user_id: bundle.user_id.clone(),
-            event_type: EventType::Initiate,
-            leg: None,
-            bundle_status: Some(BundleStatus::Initiated),
+            user_id: last_event.user_id.clone(),
+            event_type,
+            leg,
+            bundle_status: Some(bundle.status.clone()),
             transaction_status: None,
-            created_at: Utc::now(),
-            bundle_snapshot: bundle.clone(),
-        })
+            created_at: now,
+            bundle_snapshot: bundle,
+        }
+    }
+
+    /// Appends `event` and returns it under the ID the store assigned.
+    async fn persisted(
+        mut event: TransactionEvent,
+        event_store: Arc<dyn EventStore>,
+    ) -> Result<TransactionEvent, TransactionError> {
+        event.event_id = event_store.persist(&event).await?;
+        Ok(event)
     }
 
     pub async fn on_signed(
         last_event: &TransactionEvent,
         fee_signed: &str,
         main_signed: &str,
-        event_store: Arc<TransactionEventManager>,
+        split_signed: &[String],
+        event_store: Arc<dyn EventStore>,
     ) -> Result<TransactionEvent, TransactionError> {
-        if last_event.event_type != EventType::Initiate {
-            return Err(TransactionError::InvalidTransition(
-                "Signing is only valid after Initiate".into(),
-            ));
-        }
-
-        if last_event.bundle_status != Some(BundleStatus::Initiated) {
-            return Err(TransactionError::InvalidTransition(
-                format!("Cannot sign from status {:?}", last_event.bundle_status),
-            ));
-        }
+        Self::persisted(Self::sign(last_event, fee_signed, main_signed, split_signed)?, event_store).await
+    }
 
+    /// Signs every leg at once; `split_signed` follows the bundle's split legs in order.
+    pub fn sign(
+        last_event: &TransactionEvent,
+        fee_signed: &str,
+        main_signed: &str,
+        split_signed: &[String],
+    ) -> Result<TransactionEvent, TransactionError> {
+        let transition = state_machine::lookup(last_event, EventType::Sign, None)?;
         let mut bundle = last_event.bundle_snapshot.clone();
 
+        if split_signed.len() != bundle.split_txs.len() {
+            return Err(TransactionError::MissingSignatureData(format!(
+                "bundle has {} split legs but {} were signed", bundle.split_txs.len(), split_signed.len()
+            )));
+        }
+
         let fee_tx = bundle.fee_tx
             .clone()
             .with_signed_tx(fee_signed)
@@ -585,173 +945,204 @@ impl TransactionEvent {
 
         bundle.fee_tx = fee_tx;
         bundle.main_tx = main_tx;
-        bundle.status = BundleStatus::Signed;
-        bundle.updated_at = Utc::now();
+        for (tx, signed) in bundle.split_txs.iter_mut().zip(split_signed) {
+            *tx = tx.clone().with_signed_tx(signed).with_status(TransactionStatus::Signed);
+        }
+        bundle.status = transition.to.clone();
 
-        let mut event = TransactionEvent {
-            event_id: String::new(),
-            bundle_id: bundle.bundle_id.clone(),
-            user_id: last_event.user_id.clone(),
-            event_type: EventType::Sign,
-            leg: None,
-            bundle_status: Some(BundleStatus::Signed),
-            transaction_status: None,
-            created_at: Utc::now(),
-            bundle_snapshot: bundle,
-        };
+        Ok(Self::next_event(last_event, EventType::Sign, None, bundle))
+    }
 
-        let assigned_event_id = event_store.persist(&event).await?;
-        event.event_id = assigned_event_id;
+    pub async fn on_broadcast(
+        last_event: &TransactionEvent,
+        tx_hash: H256,
+        event_store: Arc<dyn EventStore>,
+    ) -> Result<TransactionEvent, TransactionError> {
+        Self::persisted(Self::broadcast(last_event, tx_hash)?, event_store).await
+    }
 
-        Ok(event)
+    pub fn broadcast(
+        last_event: &TransactionEvent,
+        tx_hash: H256,
+    ) -> Result<TransactionEvent, TransactionError> {
+        let (_, leg) = state_machine::next_leg(last_event, EventType::Broadcast).inspect_err(|_| {
+            warn!("🚫 Not a broadcastable state: event_type={:?}, bundle_status={:?}",
+                &last_event.event_type, &last_event.bundle_snapshot.status);
+        })?;
+
+        Self::broadcast_leg(last_event, leg, tx_hash)
     }
 
-    pub async fn on_broadcast(
+    /// Records `leg` as sent with `tx_hash`, for a sender that already picked the leg and may
+    /// be recording it against a later event than the one it picked it from.
+    pub async fn on_broadcast_leg(
         last_event: &TransactionEvent,
+        leg: TransactionLeg,
         tx_hash: H256,
-        event_store: Arc<TransactionEventManager>,
+        event_store: Arc<dyn EventStore>,
     ) -> Result<TransactionEvent, TransactionError> {
-        if last_event.event_type != EventType::Confirm && last_event.event_type != EventType::Sign {
-            return Err(TransactionError::InvalidTransition(
-                "Broadcasting is only valid after signing or confirm".into(),
-            ));
-        }
+        Self::persisted(Self::broadcast_leg(last_event, leg, tx_hash)?, event_store).await
+    }
 
-        if last_event.bundle_status != Some(BundleStatus::Signed) && last_event.bundle_status != Some(BundleStatus::MainConfirmed) {
-            return Err(TransactionError::InvalidTransition(
-                format!("Cannot broadcast from status {:?}", last_event.bundle_status),
-            ));
-        }
+    pub fn broadcast_leg(
+        last_event: &TransactionEvent,
+        leg: TransactionLeg,
+        tx_hash: H256,
+    ) -> Result<TransactionEvent, TransactionError> {
+        // Legs go out in nonce order, so only the first unsent one can be recorded as sent
+        let transition = match state_machine::next_leg(last_event, EventType::Broadcast) {
+            Ok((transition, next)) if next == leg => transition,
+            _ => return Err(TransactionError::InvalidStateTransition {
+                event: format!("{}({}) after {}", EventType::Broadcast, leg, last_event.event_type),
+                status: last_event.bundle_snapshot.status.to_string(),
+            }),
+        };
 
         let mut bundle = last_event.bundle_snapshot.clone();
         let hash_str = &format!("{:#x}", tx_hash);
         info!("Broadcasting hash_str: {} - is it concatenated?", hash_str);
 
-        let (leg, tx) = match (&last_event.event_type, &bundle.status) {
-            (EventType::Sign, BundleStatus::Signed) => {
-                (TransactionLeg::Main, bundle.main_tx
-                                             .clone()
-                                             .with_transaction_hash(hash_str)
-                                             .with_status(TransactionStatus::Pending))
-            }
-            (EventType::Confirm, BundleStatus::MainConfirmed) => {
-                (TransactionLeg::Fee, bundle.fee_tx
-                                            .clone()
-                                            .with_transaction_hash(hash_str)
-                                            .with_status(TransactionStatus::Pending))
-            }
-            _ => {
-                warn!("🚫 Not a broadcastable state: event_type={:?}, bundle_status={:?}",
-                    &last_event.event_type, &bundle.status);
-                return Err(TransactionError::InvalidTransition(format!("Not a broadcastable state: event_type={:?}, bundle_status={:?}",
-                                                                       &last_event.event_type, &bundle.status)));
-            }
-        };
-
-        match leg {
-            TransactionLeg::Main => bundle.main_tx = tx,
-            TransactionLeg::Fee => bundle.fee_tx = tx,
-        }
-
-        bundle.updated_at = Utc::now();
-
-        let mut event = TransactionEvent {
-            event_id: String::new(),
-            bundle_id: bundle.bundle_id.clone(),
-            user_id: last_event.user_id.clone(),
-            event_type: EventType::Broadcast,
-            leg: Some(leg),
-            bundle_status: Some(bundle.status.clone()), // no status update on the bundle
-            transaction_status: None,
-            created_at: Utc::now(),
-            bundle_snapshot: bundle,
-        };
+        let tx = bundle.leg(leg)
+            .clone()
+            .with_transaction_hash(hash_str)
+            .with_status(TransactionStatus::Pending);
+        *bundle.leg_mut(leg) = tx;
 
-        let assigned_event_id = event_store.persist(&event).await?;
-        event.event_id = assigned_event_id;
+        bundle.status = transition.to.clone();
 
-        Ok(event)
+        Ok(Self::next_event(last_event, EventType::Broadcast, Some(leg), bundle))
     }
 
     pub async fn on_confirmed(
         last_event: &TransactionEvent,
         updated_tx: &Transaction,
-        event_store: Arc<TransactionEventManager>,
+        event_store: Arc<dyn EventStore>,
     ) -> Result<TransactionEvent, TransactionError> {
-        if last_event.event_type != EventType::Broadcast {
-            return Err(TransactionError::InvalidTransition("Can only confirm after Broadcast".into()));
-        }
-
-        if last_event.bundle_status != Some(BundleStatus::Signed)
-            && last_event.bundle_status != Some(BundleStatus::MainConfirmed)
-        {
-            return Err(TransactionError::InvalidTransition(
-                format!("Cannot confirm from bundle status {:?}", last_event.bundle_status),
-            ));
-        }
+        Self::persisted(Self::confirm(last_event, updated_tx)?, event_store).await
+    }
 
+    pub fn confirm(
+        last_event: &TransactionEvent,
+        updated_tx: &Transaction,
+    ) -> Result<TransactionEvent, TransactionError> {
         let mut bundle = last_event.bundle_snapshot.clone();
-        let (leg, tx) = match (&last_event.bundle_status, &updated_tx.transaction_id) {
-            (Some(BundleStatus::Signed), id) if *id == bundle.main_tx.transaction_id => {
-                (TransactionLeg::Main, updated_tx.clone())
-            }
-            (Some(BundleStatus::MainConfirmed), id) if *id == bundle.fee_tx.transaction_id => {
-                (TransactionLeg::Fee, updated_tx.clone())
-            }
-            _ => {
-                return Err(TransactionError::InvalidTransition(
-                    "Confirmation does not match expected leg".into(),
-                ));
-            }
-        };
+        let leg = bundle.leg_of(&updated_tx.transaction_id).ok_or_else(|| {
+            TransactionError::InvalidTransition("Confirmation does not match expected leg".into())
+        })?;
 
-        // Apply updated transaction
-        match leg {
-            TransactionLeg::Main => bundle.main_tx = tx,
-            TransactionLeg::Fee => bundle.fee_tx = tx,
-        }
+        let transition = state_machine::lookup(last_event, EventType::Confirm, Some(leg))?;
 
-        // Update bundle status
-        bundle.status = match (&bundle.main_tx.status, &bundle.fee_tx.status) {
-            (TransactionStatus::Confirmed, TransactionStatus::Confirmed) => BundleStatus::Completed,
-            (TransactionStatus::Confirmed, _) => BundleStatus::MainConfirmed,
-            _ => bundle.status.clone(), // No change if confirmation did not progress lifecycle
-        };
+        // Apply updated transaction
+        *bundle.leg_mut(leg) = updated_tx.clone().with_status(TransactionStatus::Confirmed);
 
-        bundle.updated_at = Utc::now();
+        bundle.status = transition.to.clone();
 
-        // Create and persist event
-        let mut event = TransactionEvent {
-            event_id: String::new(),
-            bundle_id: bundle.bundle_id.clone(),
-            user_id: last_event.user_id.clone(),
-            event_type: EventType::Confirm,
-            leg: Some(leg),
-            bundle_status: Some(bundle.status.clone()),
+        Ok(TransactionEvent {
             transaction_status: Some(TransactionStatus::Confirmed),
-            created_at: Utc::now(),
-            bundle_snapshot: bundle,
-        };
+            ..Self::next_event(last_event, EventType::Confirm, Some(leg), bundle)
+        })
+    }
 
-        let new_event_id = event_store.persist(&event).await?;
-        event.event_id = new_event_id;
+    pub async fn on_included(
+        last_event: &TransactionEvent,
+        included_tx: &Transaction,
+        event_store: Arc<dyn EventStore>,
+    ) -> Result<TransactionEvent, TransactionError> {
+        Self::persisted(Self::include(last_event, included_tx)?, event_store).await
+    }
 
-        Ok(event)
+    /// Records a leg mined in a block that is not yet final enough to confirm it. The leg
+    /// stays pending, and is recorded again whenever its block or finality stage changes.
+    pub fn include(
+        last_event: &TransactionEvent,
+        included_tx: &Transaction,
+    ) -> Result<TransactionEvent, TransactionError> {
+        let mut bundle = last_event.bundle_snapshot.clone();
+        let leg = bundle.leg_of(&included_tx.transaction_id).ok_or_else(|| {
+            TransactionError::InvalidTransition("Inclusion does not match expected leg".into())
+        })?;
+
+        let transition = state_machine::lookup(last_event, EventType::Include, Some(leg))?;
+
+        *bundle.leg_mut(leg) = included_tx.clone().with_status(TransactionStatus::Pending);
+
+        bundle.status = transition.to.clone();
+
+        Ok(TransactionEvent {
+            transaction_status: Some(TransactionStatus::Pending),
+            ..Self::next_event(last_event, EventType::Include, Some(leg), bundle)
+        })
+    }
+
+    pub async fn on_reorged(
+        last_event: &TransactionEvent,
+        leg: TransactionLeg,
+        event_store: Arc<dyn EventStore>,
+    ) -> Result<TransactionEvent, TransactionError> {
+        Self::persisted(Self::reorg(last_event, leg)?, event_store).await
+    }
+
+    /// Forgets the block of an included leg that was reorged out. The leg is pending again
+    /// under the same hashes, to be mined again or rebroadcast.
+    pub fn reorg(last_event: &TransactionEvent, leg: TransactionLeg) -> Result<TransactionEvent, TransactionError> {
+        let transition = state_machine::lookup(last_event, EventType::Reorg, Some(leg))?;
+        let mut bundle = last_event.bundle_snapshot.clone();
+
+        let tx = bundle.leg_mut(leg);
+        tx.block_number = None;
+        tx.block_hash = None;
+        tx.finality = None;
+        tx.receipt_status = None;
+        tx.gas_used = None;
+        tx.effective_gas_price = None;
+        tx.l1_fee = None;
+        tx.total_fee_paid = None;
+        tx.status = TransactionStatus::Pending;
+
+        bundle.status = transition.to.clone();
+
+        Ok(TransactionEvent {
+            transaction_status: Some(TransactionStatus::Pending),
+            ..Self::next_event(last_event, EventType::Reorg, Some(leg), bundle)
+        })
+    }
+
+    pub async fn on_rebroadcast(
+        last_event: &TransactionEvent,
+        leg: TransactionLeg,
+        event_store: Arc<dyn EventStore>,
+    ) -> Result<TransactionEvent, TransactionError> {
+        Self::persisted(Self::rebroadcast(last_event, leg)?, event_store).await
+    }
+
+    /// Records that a pending leg the node had dropped was sent again, as it was signed.
+    /// The bundle counts every attempt.
+    pub fn rebroadcast(last_event: &TransactionEvent, leg: TransactionLeg) -> Result<TransactionEvent, TransactionError> {
+        let transition = state_machine::lookup(last_event, EventType::Rebroadcast, Some(leg))?;
+        let mut bundle = last_event.bundle_snapshot.clone();
+
+        bundle.rebroadcast_attempts += 1;
+        bundle.status = transition.to.clone();
+
+        Ok(TransactionEvent {
+            transaction_status: Some(TransactionStatus::Pending),
+            ..Self::next_event(last_event, EventType::Rebroadcast, Some(leg), bundle)
+        })
     }
 
     pub async fn on_skip(
         last_event: &TransactionEvent,
         updated_tx: &Transaction,
-        event_store: Arc<TransactionEventManager>,
+        event_store: Arc<dyn EventStore>,
     ) -> Result<TransactionEvent, TransactionError> {
-        if last_event.event_type != EventType::Broadcast {
-            return Err(TransactionError::InvalidTransition("Can only skip after Broadcast".into()));
-        }
-
-        if last_event.bundle_status != Some(BundleStatus::MainConfirmed) {
-            return Err(TransactionError::InvalidTransition("Skip only allowed after MainConfirmed".into()));
-        }
+        Self::persisted(Self::skip(last_event, updated_tx)?, event_store).await
+    }
 
+    pub fn skip(
+        last_event: &TransactionEvent,
+        updated_tx: &Transaction,
+    ) -> Result<TransactionEvent, TransactionError> {
+        let transition = state_machine::lookup(last_event, EventType::Skip, Some(TransactionLeg::Fee))?;
         let mut bundle = last_event.bundle_snapshot.clone();
 
         if updated_tx.transaction_value > 0 {
@@ -767,62 +1158,104 @@ impl TransactionEvent {
         skipped_tx.status = TransactionStatus::Skipped;
         bundle.fee_tx = skipped_tx;
 
-        // Update bundle status
-        bundle.status = BundleStatus::Completed;
-        bundle.updated_at = Utc::now();
+        bundle.status = transition.to.clone();
 
-        // Create and persist event
-        let mut event = TransactionEvent {
-            event_id: String::new(),
-            bundle_id: bundle.bundle_id.clone(),
-            user_id: last_event.user_id.clone(),
-            event_type: EventType::Skip,
-            leg: Some(TransactionLeg::Fee),
-            bundle_status: Some(BundleStatus::Completed),
+        Ok(TransactionEvent {
             transaction_status: Some(TransactionStatus::Skipped),
-            created_at: Utc::now(),
-            bundle_snapshot: bundle,
-        };
+            ..Self::next_event(last_event, EventType::Skip, Some(TransactionLeg::Fee), bundle)
+        })
+    }
 
-        let new_event_id = event_store.persist(&event).await?;
-        event.event_id = new_event_id;
+    pub async fn on_cancel(
+        last_event: &TransactionEvent,
+        user_id: &str,
+        event_store: Arc<dyn EventStore>,
+    ) -> Result<TransactionEvent, TransactionError> {
+        Self::persisted(Self::cancel(last_event, user_id)?, event_store).await
+    }
 
-        Ok(event)
+    /// Cancels a bundle on behalf of its owner. Only possible before any leg is broadcast.
+    pub fn cancel(last_event: &TransactionEvent, user_id: &str) -> Result<TransactionEvent, TransactionError> {
+        if last_event.bundle_snapshot.user_id != user_id {
+            return Err(TransactionError::Unauthorized);
+        }
+
+        let transition = state_machine::lookup(last_event, EventType::Cancel, None)?;
+        let mut bundle = last_event.bundle_snapshot.clone();
+
+        for tx in bundle.legs_mut() {
+            tx.status = TransactionStatus::Cancelled;
+        }
+        bundle.status = transition.to.clone();
+
+        Ok(TransactionEvent {
+            transaction_status: Some(TransactionStatus::Cancelled),
+            ..Self::next_event(last_event, EventType::Cancel, None, bundle)
+        })
+    }
+
+    pub async fn on_expire(
+        last_event: &TransactionEvent,
+        event_store: Arc<dyn EventStore>,
+    ) -> Result<TransactionEvent, TransactionError> {
+        Self::persisted(Self::expire(last_event)?, event_store).await
+    }
+
+    /// Expires a bundle that was never signed. Whether it is old enough is the caller's call,
+    /// see `TransactionBundle::is_expired`.
+    pub fn expire(last_event: &TransactionEvent) -> Result<TransactionEvent, TransactionError> {
+        let transition = state_machine::lookup(last_event, EventType::Expire, None)?;
+        let mut bundle = last_event.bundle_snapshot.clone();
+
+        for tx in bundle.legs_mut() {
+            tx.status = TransactionStatus::Expired;
+        }
+        bundle.status = transition.to.clone();
+
+        Ok(TransactionEvent {
+            transaction_status: Some(TransactionStatus::Expired),
+            ..Self::next_event(last_event, EventType::Expire, None, bundle)
+        })
     }
 
     pub async fn on_fail(
         last_event: &TransactionEvent,
         leg: TransactionLeg,
-        event_store: Arc<TransactionEventManager>,
+        event_store: Arc<dyn EventStore>,
     ) -> Result<TransactionEvent, TransactionError> {
+        Self::persisted(Self::fail(last_event, leg)?, event_store).await
+    }
+
+    pub fn fail(last_event: &TransactionEvent, leg: TransactionLeg) -> Result<TransactionEvent, TransactionError> {
+        let transition = state_machine::lookup(last_event, EventType::Fail, Some(leg))?;
         let mut bundle = last_event.bundle_snapshot.clone();
 
-        match leg {
-            TransactionLeg::Fee => {
-                bundle.fee_tx = bundle.fee_tx.clone().with_status(TransactionStatus::Failed);
-            }
-            TransactionLeg::Main => {
-                bundle.main_tx = bundle.main_tx.clone().with_status(TransactionStatus::Failed);
-            }
-        }
+        bundle.leg_mut(leg).status = TransactionStatus::Failed;
 
-        bundle.status = BundleStatus::Failed;
-        bundle.updated_at = Utc::now();
+        bundle.status = transition.to.clone();
 
-        let mut event = TransactionEvent {
-            event_id: String::new(), // Will be assigned after persist
-            bundle_id: bundle.bundle_id.clone(),
-            user_id: last_event.user_id.clone(),
-            event_type: EventType::Fail,
-            leg: Some(leg),
-            bundle_status: Some(BundleStatus::Failed),
+        Ok(TransactionEvent {
             transaction_status: Some(TransactionStatus::Failed),
-            created_at: Utc::now(),
-            bundle_snapshot: bundle,
-        };
+            ..Self::next_event(last_event, EventType::Fail, Some(leg), bundle)
+        })
+    }
+
+    pub async fn on_reverted(
+        last_event: &TransactionEvent,
+        reverted_tx: &Transaction,
+        event_store: Arc<dyn EventStore>,
+    ) -> Result<TransactionEvent, TransactionError> {
+        Self::persisted(Self::revert(last_event, reverted_tx)?, event_store).await
+    }
 
-        let assigned_id = event_store.persist(&event).await?;
-        event.event_id = assigned_id;
+    /// Fails a leg that was mined but reverted, keeping what its receipt says about it.
+    pub fn revert(last_event: &TransactionEvent, reverted_tx: &Transaction) -> Result<TransactionEvent, TransactionError> {
+        let leg = last_event.bundle_snapshot.leg_of(&reverted_tx.transaction_id).ok_or_else(|| {
+            TransactionError::InvalidTransition("Reverted transaction does not match expected leg".into())
+        })?;
+
+        let mut event = Self::fail(last_event, leg)?;
+        *event.bundle_snapshot.leg_mut(leg) = reverted_tx.clone().with_status(TransactionStatus::Failed);
 
         Ok(event)
     }
@@ -830,38 +1263,229 @@ impl TransactionEvent {
     pub async fn on_error(
         last_event: &TransactionEvent,
         leg: TransactionLeg,
-        event_store: Arc<TransactionEventManager>,
+        event_store: Arc<dyn EventStore>,
     ) -> Result<TransactionEvent, TransactionError> {
+        Self::persisted(Self::error(last_event, leg)?, event_store).await
+    }
+
+    pub fn error(last_event: &TransactionEvent, leg: TransactionLeg) -> Result<TransactionEvent, TransactionError> {
+        let transition = state_machine::lookup(last_event, EventType::Error, Some(leg))?;
         let mut bundle = last_event.bundle_snapshot.clone();
 
-        match leg {
-            TransactionLeg::Fee => {
-                bundle.fee_tx = bundle.fee_tx.clone().with_status(TransactionStatus::Error);
-            }
-            TransactionLeg::Main => {
-                bundle.main_tx = bundle.main_tx.clone().with_status(TransactionStatus::Error);
-            }
-        }
+        bundle.leg_mut(leg).status = TransactionStatus::Error;
 
-        bundle.status = BundleStatus::Errored;
-        bundle.updated_at = Utc::now();
+        bundle.status = transition.to.clone();
 
-        let mut event = TransactionEvent {
-            event_id: String::new(),
-            bundle_id: bundle.bundle_id.clone(),
-            user_id: last_event.user_id.clone(),
-            event_type: EventType::Error,
-            leg: Some(leg),
-            bundle_status: Some(BundleStatus::Errored),
+        Ok(TransactionEvent {
             transaction_status: Some(TransactionStatus::Error),
-            created_at: Utc::now(),
-            bundle_snapshot: bundle,
+            ..Self::next_event(last_event, EventType::Error, Some(leg), bundle)
+        })
+    }
+
+    pub async fn on_replace(
+        last_event: &TransactionEvent,
+        leg: TransactionLeg,
+        signed_tx: &str,
+        max_fee_per_gas: u64,
+        max_priority_fee_per_gas: u64,
+        event_store: Arc<dyn EventStore>,
+    ) -> Result<TransactionEvent, TransactionError> {
+        Self::persisted(Self::replace(last_event, leg, signed_tx, max_fee_per_gas, max_priority_fee_per_gas)?, event_store).await
+    }
+
+    /// Swaps a pending leg for a re-signed transaction with the same nonce and higher fees.
+    /// The hash it replaces is kept on the leg, since either transaction may be the one mined.
+    pub fn replace(
+        last_event: &TransactionEvent,
+        leg: TransactionLeg,
+        signed_tx: &str,
+        max_fee_per_gas: u64,
+        max_priority_fee_per_gas: u64,
+    ) -> Result<TransactionEvent, TransactionError> {
+        let transition = state_machine::lookup(last_event, EventType::Replace, Some(leg))?;
+        let mut bundle = last_event.bundle_snapshot.clone();
+
+        let mut tx = bundle.leg(leg).clone().with_signed_tx(signed_tx);
+        let tx_hash = tx.tx_hash()
+            .ok_or_else(|| TransactionError::MalformedSignedTransaction(format!("{} replacement is not hex", leg)))?;
+        if let Some(replaced) = tx.transaction_hash.take() {
+            tx.replaced_tx_hashes.push(replaced);
+        }
+        tx.max_fee_per_gas = Some(max_fee_per_gas);
+        tx.max_priority_fee_per_gas = Some(max_priority_fee_per_gas);
+        let tx = tx
+            .with_transaction_hash(&format!("{:#x}", tx_hash))
+            .with_status(TransactionStatus::Pending);
+        *bundle.leg_mut(leg) = tx;
+
+        bundle.status = transition.to.clone();
+
+        Ok(TransactionEvent {
+            transaction_status: Some(TransactionStatus::Pending),
+            ..Self::next_event(last_event, EventType::Replace, Some(leg), bundle)
+        })
+    }
+
+    /// Folds a recorded event history (oldest first) back through the transitions above,
+    /// checking each recorded event against the one the state machine would have produced.
+    /// Returns the rebuilt bundle as of the last event.
+    pub fn replay(events: &[TransactionEvent]) -> Result<TransactionBundle, TransactionError> {
+        let (first, rest) = events
+            .split_first()
+            .ok_or_else(|| TransactionError::StateMachine("Cannot replay an empty event stream".into()))?;
+
+        if first.event_type != EventType::Initiate {
+            return Err(TransactionError::StateMachine(
+                format!("Event stream must start with Initiate, found {:?}", first.event_type),
+            ));
+        }
+
+        let mut initial_bundle = first.bundle_snapshot.clone();
+        initial_bundle.status = BundleStatus::Initiated;
+        let mut state = Self::initiate(initial_bundle)?;
+        Self::verify_replayed(&state, first)?;
+        state = Self::with_recorded_identity(state, first);
+
+        for recorded in rest {
+            if recorded.bundle_id != first.bundle_id {
+                return Err(TransactionError::StateMachine(
+                    format!("Event {} belongs to bundle {}, expected {}", recorded.event_id, recorded.bundle_id, first.bundle_id),
+                ));
+            }
+
+            let snapshot = &recorded.bundle_snapshot;
+            let next = match recorded.event_type {
+                EventType::Sign => {
+                    let fee_signed = snapshot.fee_tx.signed_tx.as_deref()
+                        .ok_or_else(|| Self::replay_error(recorded, "fee leg has no signed transaction"))?;
+                    let main_signed = snapshot.main_tx.signed_tx.as_deref()
+                        .ok_or_else(|| Self::replay_error(recorded, "main leg has no signed transaction"))?;
+                    let split_signed = snapshot.split_txs.iter()
+                        .map(|tx| tx.signed_tx.clone()
+                            .ok_or_else(|| Self::replay_error(recorded, "split leg has no signed transaction")))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Self::sign(&state, fee_signed, main_signed, &split_signed)?
+                }
+                EventType::Broadcast => {
+                    let leg = recorded.leg
+                        .ok_or_else(|| Self::replay_error(recorded, "broadcast has no leg"))?;
+                    let tx_hash = Self::recorded_leg(recorded, leg)?.transaction_hash.as_deref()
+                        .and_then(|h| H256::from_str(h).ok())
+                        .ok_or_else(|| Self::replay_error(recorded, "broadcast leg has no valid transaction hash"))?;
+                    Self::broadcast_leg(&state, leg, tx_hash)?
+                }
+                EventType::Confirm => {
+                    let leg = recorded.leg
+                        .ok_or_else(|| Self::replay_error(recorded, "confirmation has no leg"))?;
+                    Self::confirm(&state, Self::recorded_leg(recorded, leg)?)?
+                }
+                EventType::Include => {
+                    let leg = recorded.leg
+                        .ok_or_else(|| Self::replay_error(recorded, "inclusion has no leg"))?;
+                    Self::include(&state, Self::recorded_leg(recorded, leg)?)?
+                }
+                EventType::Reorg => {
+                    let leg = recorded.leg
+                        .ok_or_else(|| Self::replay_error(recorded, "reorg has no leg"))?;
+                    Self::reorg(&state, leg)?
+                }
+                EventType::Rebroadcast => {
+                    let leg = recorded.leg
+                        .ok_or_else(|| Self::replay_error(recorded, "rebroadcast has no leg"))?;
+                    Self::rebroadcast(&state, leg)?
+                }
+                EventType::Skip => Self::skip(&state, &snapshot.fee_tx)?,
+                EventType::Fail => {
+                    let leg = recorded.leg
+                        .ok_or_else(|| Self::replay_error(recorded, "failure has no leg"))?;
+                    let tx = Self::recorded_leg(recorded, leg)?;
+                    if tx.receipt_status == Some(0) {
+                        Self::revert(&state, tx)?
+                    } else {
+                        Self::fail(&state, leg)?
+                    }
+                }
+                EventType::Error => {
+                    let leg = recorded.leg
+                        .ok_or_else(|| Self::replay_error(recorded, "error has no leg"))?;
+                    Self::error(&state, leg)?
+                }
+                EventType::Cancel => Self::cancel(&state, &recorded.user_id)?,
+                EventType::Expire => Self::expire(&state)?,
+                EventType::Replace => {
+                    let leg = recorded.leg
+                        .ok_or_else(|| Self::replay_error(recorded, "replacement has no leg"))?;
+                    let tx = Self::recorded_leg(recorded, leg)?;
+                    let signed_tx = tx.signed_tx.as_deref()
+                        .ok_or_else(|| Self::replay_error(recorded, "replaced leg has no signed transaction"))?;
+                    let (max_fee, max_priority_fee) = tx.max_fee_per_gas.zip(tx.max_priority_fee_per_gas)
+                        .ok_or_else(|| Self::replay_error(recorded, "replaced leg has no fees"))?;
+                    Self::replace(&state, leg, signed_tx, max_fee, max_priority_fee)?
+                }
+                EventType::Initiate => {
+                    return Err(Self::replay_error(recorded, "event type cannot be replayed at this point"));
+                }
+            };
+
+            Self::verify_replayed(&next, recorded)?;
+            state = Self::with_recorded_identity(next, recorded);
+        }
+
+        Ok(state.bundle_snapshot)
+    }
+
+    fn verify_replayed(replayed: &TransactionEvent, recorded: &TransactionEvent) -> Result<(), TransactionError> {
+        let expected = &replayed.bundle_snapshot;
+        let actual = &recorded.bundle_snapshot;
+
+        let mismatch = if recorded.sequence != 0 && replayed.sequence != recorded.sequence {
+            Some(format!("sequence {} != {}", recorded.sequence, replayed.sequence))
+        } else if replayed.event_type != recorded.event_type {
+            Some(format!("event type {:?} != {:?}", recorded.event_type, replayed.event_type))
+        } else if replayed.leg != recorded.leg {
+            Some(format!("leg {:?} != {:?}", recorded.leg, replayed.leg))
+        } else if replayed.bundle_status != recorded.bundle_status || expected.status != actual.status {
+            Some(format!("bundle status {:?} != {:?}", actual.status, expected.status))
+        } else if expected.split_txs.len() != actual.split_txs.len() {
+            Some(format!("{} split legs != {}", actual.split_txs.len(), expected.split_txs.len()))
+        } else if expected.rebroadcast_attempts != actual.rebroadcast_attempts {
+            Some(format!("{} rebroadcasts != {}", actual.rebroadcast_attempts, expected.rebroadcast_attempts))
+        } else if let Some(leg) = expected.legs().find(|leg| expected.leg(*leg).status != actual.leg(*leg).status) {
+            Some(format!("{} leg status {:?} != {:?}", leg, actual.leg(leg).status, expected.leg(leg).status))
+        } else if expected.legs().any(|leg| {
+            let (expected, actual) = (expected.leg(leg), actual.leg(leg));
+            expected.transaction_hash != actual.transaction_hash || expected.replaced_tx_hashes != actual.replaced_tx_hashes
+        }) {
+            Some("transaction hashes diverge from the replayed bundle".to_string())
+        } else {
+            None
         };
 
-        let assigned_id = event_store.persist(&event).await?;
-        event.event_id = assigned_id;
+        match mismatch {
+            Some(reason) => Err(Self::replay_error(recorded, &reason)),
+            None => Ok(()),
+        }
+    }
+
+    fn recorded_leg(recorded: &TransactionEvent, leg: TransactionLeg) -> Result<&Transaction, TransactionError> {
+        recorded.bundle_snapshot.get_leg(leg)
+            .ok_or_else(|| Self::replay_error(recorded, &format!("{} leg is not in the bundle", leg)))
+    }
 
-        Ok(event)
+    fn with_recorded_identity(mut replayed: TransactionEvent, recorded: &TransactionEvent) -> TransactionEvent {
+        replayed.event_id = recorded.event_id.clone();
+        replayed.idempotency_key = recorded.idempotency_key.clone();
+        replayed.sequence = recorded.sequence;
+        replayed.created_at = recorded.created_at;
+        replayed.bundle_snapshot.updated_at = recorded.bundle_snapshot.updated_at;
+        replayed
+    }
+
+    fn replay_error(recorded: &TransactionEvent, reason: &str) -> TransactionError {
+        TransactionError::StateMachine(format!(
+            "Replay of bundle {} failed at {:?} event {}: {}",
+            recorded.bundle_id, recorded.event_type, recorded.event_id, reason,
+        ))
     }
 }
 
@@ -874,6 +1498,10 @@ pub struct TransactionRequest {
     #[serde(deserialize_with = "u128_from_str")]
     pub transaction_value: u128,
     pub token_type: TokenType,
+    #[serde(default = "get_network")]
+    pub network: Network,
+    #[serde(default)]
+    pub priority_level: PriorityLevel,
     pub message: Option<String>,
 
     // The user-visible quote data, stringified for mobile compatibility
@@ -887,10 +1515,13 @@ pub struct TransactionRequest {
     pub service_fee: u128,
     pub service_fee_minor: u64,
 
-    pub user_device: UserDevice
+    pub user_device: UserDevice,
+
+    // The signed quote from /transactions/estimate; its pricing overrides the fields above
+    pub quote: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub enum Network {
     EthereumMainnet,
     EthereumSepolia,
@@ -898,13 +1529,79 @@ pub enum Network {
     OptimismSepolia,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+impl Network {
+    pub fn block_time_secs(&self) -> u64 {
+        match self {
+            Network::EthereumMainnet | Network::EthereumSepolia => 12,
+            Network::OptimismMainnet | Network::OptimismSepolia => 2,
+        }
+    }
+
+    /// The L1 an L2 posts its data to, whose gas price sets the L2's data fee.
+    pub fn settlement_layer(&self) -> Option<Network> {
+        match self {
+            Network::OptimismMainnet => Some(Network::EthereumMainnet),
+            Network::OptimismSepolia => Some(Network::EthereumSepolia),
+            Network::EthereumMainnet | Network::EthereumSepolia => None,
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Network::EthereumMainnet => write!(f, "EthereumMainnet"),
+            Network::EthereumSepolia => write!(f, "EthereumSepolia"),
+            Network::OptimismMainnet => write!(f, "OptimismMainnet"),
+            Network::OptimismSepolia => write!(f, "OptimismSepolia"),
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ethereummainnet" => Ok(Network::EthereumMainnet),
+            "ethereumsepolia" => Ok(Network::EthereumSepolia),
+            "optimismmainnet" | "mainnet" => Ok(Network::OptimismMainnet),
+            "optimismsepolia" | "testnet" => Ok(Network::OptimismSepolia),
+            _ => Err(format!("Invalid network: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
 pub enum PriorityLevel {
+    #[default]
     Standard,  // default, safe gas fee
     Fast,      // more responsive, higher gas
     Urgent     // top speed, cost is no issue
 }
 
+impl PriorityLevel {
+    pub const ALL: [PriorityLevel; 3] = [PriorityLevel::Standard, PriorityLevel::Fast, PriorityLevel::Urgent];
+
+    /// The `eth_feeHistory` reward percentile this level tips at.
+    pub fn fee_percentile(&self) -> u8 {
+        match self {
+            PriorityLevel::Standard => 25,
+            PriorityLevel::Fast => 50,
+            PriorityLevel::Urgent => 90,
+        }
+    }
+
+    /// Blocks we expect a transaction tipped at this level to wait before inclusion.
+    pub fn expected_blocks(&self) -> u64 {
+        match self {
+            PriorityLevel::Standard => 5,
+            PriorityLevel::Fast => 2,
+            PriorityLevel::Urgent => 1,
+        }
+    }
+}
+
 /// Unsigned transaction details
 /// Value is in string format to retain blockchain compatibility
 
@@ -912,8 +1609,11 @@ pub enum PriorityLevel {
 pub struct UnsignedTransaction {
     pub transaction_id: String,
     pub tx_type: u8, // EIP-1559 = 2
-    pub to: String,
-    pub amount_base_units: String,
+    pub to: String, // the token contract for ERC-20 transfers
+    pub recipient: String,
+    pub amount_base_units: String, // in token_type's base units
+    pub value: String, // native wei to attach; 0 for ERC-20 transfers
+    pub data: String, // 0x-prefixed calldata
     pub gas_limit: String,
     pub gas_price: String,
     pub max_fee_per_gas: String,
@@ -924,13 +1624,20 @@ pub struct UnsignedTransaction {
     pub token_decimals: u8,
 }
 
-impl From<&Transaction> for UnsignedTransaction {
-    fn from(tx: &Transaction) -> Self {
-        UnsignedTransaction {
+impl TryFrom<&Transaction> for UnsignedTransaction {
+    type Error = TransactionError;
+
+    fn try_from(tx: &Transaction) -> Result<Self, Self::Error> {
+        let token = token_registry_for(&tx.network).resolve(&tx.token_type)?;
+
+        Ok(UnsignedTransaction {
             transaction_id: tx.transaction_id.clone(),
             tx_type: 2,
-            to: tx.recipient_address.clone(),
+            to: tx.to_address().to_string(),
+            recipient: tx.recipient_address.clone(),
             amount_base_units: tx.transaction_value.to_string(),
+            value: tx.native_value().to_string(),
+            data: tx.call_data()?,
             gas_limit: tx.gas_limit.unwrap_or(0).to_string(),
             gas_price: tx.gas_price.unwrap_or(0).to_string(),
             max_fee_per_gas: tx.max_fee_per_gas.unwrap_or(0).to_string(),
@@ -938,11 +1645,8 @@ impl From<&Transaction> for UnsignedTransaction {
             nonce: tx.nonce.unwrap_or_default().to_string(),
             chain_id: tx.chain_id.to_string(),
             token_type: tx.token_type.clone(),
-            token_decimals: match tx.token_type {
-                TokenType::ETH => 18,
-                TokenType::USDC => 6,
-            },
-        }
+            token_decimals: token.decimals,
+        })
     }
 }
 
@@ -953,28 +1657,78 @@ pub struct TransactionEstimateRequest {
     pub sender_address: String,
     pub recipient_address: String,
     pub token_type: TokenType,
+    #[serde(default = "get_network")]
+    pub network: Network,
+    #[serde(default)]
+    pub priority_level: PriorityLevel,
 
     #[serde(skip_serializing_if = "Option::is_none")] // Skips field if None
     pub transaction_value: Option<u128>, // Calculated from fiat_amount and exchange rate
+
+    #[serde(default)]
+    pub splits: Vec<SplitRecipient>, // Further recipients when splitting a bill, each paid by its own leg
+}
+
+/// The most recipients one bundle can pay besides `recipient_address`.
+pub const MAX_SPLIT_RECIPIENTS: usize = 8;
+
+/// A further recipient of a split payment and their share, as the client asks for it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SplitRecipient {
+    pub recipient_address: String,
+    pub fiat_value: u64, //in minor units (e.g. cents or pence)
+}
+
+/// A split recipient's share once priced, as held in the quote.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SplitShare {
+    pub recipient_address: String,
+    pub fiat_value: u64,
+    pub transaction_value: u128, // in the token's base units
+}
+
+/// A priced split share as the app displays it, stringified like the estimate's amounts.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SplitEstimate {
+    pub recipient_address: String,
+    pub fiat_amount_minor: u64,
+    pub eth_amount: String,
+    pub wei_amount: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct FeeBreakdown {
-    pub service_fee_wei: String,
-    pub service_fee_eth: String,
+    /// The service fee and the fee leg's value are in the bundle's token: base units, and a
+    /// whole-token amount for display.
+    pub service_fee_units: String,
+    pub service_fee_amount: String,
+    pub fee_tx_value_units: String,
+    pub fee_tx_value_amount: String,
+    /// Gas is always paid in ETH, whatever the token.
     pub network_fee_wei: String,
     pub network_fee_eth: String,
-    pub total_fee_wei: String,
-    pub total_fee_eth: String,
+    /// Service fee and network fee together, in fiat minor units.
+    pub total_fee_minor: String,
     pub display_total_fee: String,
-    pub fee_tx_value_eth: String,
-    pub fee_tx_value_wei: String,
     pub service_fee_minor: String,
+    /// The ETH figures, only for ETH bundles, where every fee is in ETH.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_fee_wei: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_fee_eth: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_fee_wei: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_fee_eth: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_tx_value_eth: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_tx_value_wei: Option<String>,
 }
 
 //A note to myself, as I forget why this exists.  The Android client doesn't cope well with the
 //large number formats.  This is memento class so that the client app has something to display.
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
 pub struct GasPricing {
     pub estimated_gas: String,
     pub gas_price: String,
@@ -982,6 +1736,17 @@ pub struct GasPricing {
     pub max_priority_fee_per_gas: String,
 }
 
+/// What sending at one `PriorityLevel` would cost, stringified like `GasPricing`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpeedOption {
+    pub priority_level: PriorityLevel,
+    pub max_fee_per_gas: String,
+    pub max_priority_fee_per_gas: String,
+    pub network_fee_wei: String,
+    pub network_fee_eth: String,
+    pub expected_inclusion_secs: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct TransactionEstimateResponse {
     pub token_type: TokenType,
@@ -991,12 +1756,17 @@ pub struct TransactionEstimateResponse {
     pub wei_amount: String,                    // "3440000000000000"
 
     pub fees: FeeBreakdown,
-    pub gas: GasPricing,
+    pub gas: GasPricing, // for priority_level
+    pub priority_level: PriorityLevel,
+    pub speeds: Vec<SpeedOption>, // every level, for the app's speed picker
 
     pub exchange_rate: f64,                 // 1453.23
     pub exchange_rate_expires_at: DateTime<Utc>,
+    pub quote: String, // signed, send back to /transactions/initiate before it expires
 
     pub recipient_address: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub splits: Vec<SplitEstimate>,
     #[serde(serialize_with = "serialize_flags_as_strings")]
     pub status: EstimateFlags,
     pub message: Option<String>,
@@ -1034,10 +1804,22 @@ pub struct GasEstimate {
     pub gas_price: u64,  // L2 base fee per gas (in WEI)
     #[serde(deserialize_with = "u128_from_str")]
     pub l1_fee: u128,     // L1 data fee (in WEI)
-    pub max_fee_per_gas: u64,  // Usually same as gas_price
-    pub max_priority_fee_per_gas: u64,  // 0 on Optimism
+    pub max_fee_per_gas: u64,  // Base fee headroom plus the tip
+    pub max_priority_fee_per_gas: u64,  // Tip for the requested priority level
     #[serde(deserialize_with = "u128_from_str")]
     pub network_fee: u128,  // Total transaction fee (L2 + L1)
+    #[serde(skip)]
+    pub priority_fees: Vec<PriorityFee>, // The same pricing at every level
+}
+
+/// EIP-1559 pricing for one `PriorityLevel`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriorityFee {
+    pub priority_level: PriorityLevel,
+    pub max_fee_per_gas: u64,
+    pub max_priority_fee_per_gas: u64,
+    pub network_fee: u128,
+    pub expected_inclusion_secs: u64,
 }
 
 impl TryFrom<GasPricing> for GasEstimate {
@@ -1060,6 +1842,7 @@ impl TryFrom<GasPricing> for GasEstimate {
             max_fee_per_gas,
             max_priority_fee_per_gas,
             network_fee,
+            priority_fees: Vec::new(),
         })
     }
 }
@@ -1110,6 +1893,14 @@ pub struct TransactionHistoryItem {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tx_hash: Option<String>,
 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finality: Option<FinalityStage>, // How final the leg's block is, once it is in one
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_fee_paid_eth: Option<String>, // Gas and L1 fees the row's legs paid, once mined
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_fee_estimate_eth: Option<String>, // What those legs were priced at
+
     pub timestamp: String, // ISO8601, e.g., "2025-04-23T12:01:00Z"
     pub display_total_fee: String,   // "£2.00"
     pub service_fee_minor: u64,      // e.g., 200 for £2.00
@@ -1122,8 +1913,25 @@ impl TransactionHistoryItem {
         event: &TransactionEvent,
         current_user_id: &str,
     ) -> Option<Self> {
+        Self::from_event_leg_and_user(event, TransactionLeg::Main, current_user_id)
+    }
+
+    /// The row for one recipient leg. A split leg is its own transfer with its own
+    /// recipient; the service fee is only shown once, against the main leg.
+    pub fn from_event_leg_and_user(
+        event: &TransactionEvent,
+        leg: TransactionLeg,
+        current_user_id: &str,
+    ) -> Option<Self> {
 
         let bundle = &event.bundle_snapshot;
+        let tx = match bundle.get_leg(leg) {
+            Some(tx) if leg != TransactionLeg::Fee => tx,
+            _ => {
+                info!("❌ No {} recipient leg in bundle {}", leg, bundle.bundle_id);
+                return None;
+            }
+        };
         let metadata = match bundle.metadata.as_ref() {
             Some(m) => m,
             None => {
@@ -1140,10 +1948,14 @@ impl TransactionHistoryItem {
             }
         };
 
-        let recipient = match metadata.recipient.as_ref() {
+        let recipient = match leg {
+            TransactionLeg::Split(i) => metadata.split_recipients.get(i as usize),
+            _ => metadata.recipient.as_ref(),
+        };
+        let recipient = match recipient {
             Some(r) => r,
             None => {
-                info!("❌ Missing recipient in metadata");
+                info!("❌ Missing {} recipient in metadata", leg);
                 return None;
             }
         };
@@ -1156,37 +1968,63 @@ impl TransactionHistoryItem {
         } else {
             return None; // Not relevant to this user
         };
+
+        // A split leg is settled once mined, whatever happens to the legs after it
+        let split_confirmed = matches!(leg, TransactionLeg::Split(_)) && tx.status == TransactionStatus::Confirmed;
+        let (service_fee_minor, total_fiat_minor, fee_tx_value) = match leg {
+            TransactionLeg::Split(_) => (None, tx.fiat_value, 0),
+            _ => (
+                metadata.service_fee_minor,
+                metadata.expected_currency_amount + metadata.service_fee_minor.unwrap_or(0),
+                bundle.fee_tx.transaction_value,
+            ),
+        };
+
+        // The sender pays the fee leg's gas too, shown with its service fee on the main row.
+        // A zero-value fee leg is never sent.
+        let paying_legs: Vec<&Transaction> = match leg {
+            TransactionLeg::Split(_) => vec![tx],
+            _ => vec![tx, &bundle.fee_tx],
+        };
+        let paying_legs = paying_legs.into_iter()
+            .filter(|tx| tx.status != TransactionStatus::Skipped && tx.transaction_value > 0);
+        let network_fee_paid = paying_legs.clone().filter_map(|tx| tx.total_fee_paid).map(u128::from).reduce(|a, b| a + b);
+        let network_fee_estimate = paying_legs.filter_map(|tx| tx.estimated_network_fee()).reduce(|a, b| a + b);
+
         Some(TransactionHistoryItem {
             bundle_id: bundle.bundle_id.clone(),
             direction,
             status: match event.bundle_status {
+                _ if split_confirmed => TransactionStatus::Confirmed,
                 Some(BundleStatus::Initiated) => TransactionStatus::Created,
                 Some(BundleStatus::Signed) => TransactionStatus::Signed,
                 Some(BundleStatus::MainConfirmed) | Some(BundleStatus::Completed) => TransactionStatus::Confirmed,
                 Some(BundleStatus::Failed) => TransactionStatus::Failed,
                 Some(BundleStatus::Cancelled) => TransactionStatus::Cancelled,
                 Some(BundleStatus::Errored) => TransactionStatus::Error,
+                Some(BundleStatus::Expired) => TransactionStatus::Expired,
                 None => TransactionStatus::Created,
             },
-            amount: bundle.main_tx.transaction_value as f64 / 1e18, // ETH conversion (18 decimals)
-            token: bundle.main_tx.token_type.to_string(),
-            tx_hash: bundle.main_tx.transaction_hash.clone(),
+            amount: display_amount(tx),
+            token: tx.token_type.to_string(),
+            tx_hash: tx.transaction_hash.clone(),
+            finality: tx.finality,
+            network_fee_paid_eth: network_fee_paid.map(|wei| format!("{:.8}", wei_to_eth(wei))),
+            network_fee_estimate_eth: network_fee_estimate.map(|wei| format!("{:.8}", wei_to_eth(wei))),
             message: metadata.message.clone(),
             timestamp: event.created_at.to_rfc3339(),
             counterparty,
-            display_total_fee: metadata
-                .service_fee_minor
+            display_total_fee: service_fee_minor
                 .map(|v| v.to_string())
                 .unwrap_or_else(|| "0".to_string()),
 
-            service_fee_minor: metadata.service_fee_minor.unwrap_or(0),
+            service_fee_minor: service_fee_minor.unwrap_or(0),
 
-            total_fiat_minor: metadata.expected_currency_amount
-                + metadata.service_fee_minor.unwrap_or(0),
+            total_fiat_minor,
 
             fee_tx_value_eth: format!(
                 "{:.8}",
-                wei_to_eth(bundle.fee_tx.transaction_value)
+                wei_to_eth(fee_tx_value)
             ),
         })
     }
@@ -1196,6 +2034,18 @@ fn wei_to_eth(wei: u128) -> f64 {
     (wei as f64) / 1e18
 }
 
+/// The leg's value in whole tokens. Tokens stay listed once used, so the 18-decimal
+/// fallback only matters if the registry is misconfigured.
+fn display_amount(tx: &Transaction) -> f64 {
+    match token_registry_for(&tx.network).get(&tx.token_type) {
+        Some(token) => token.from_base_units(tx.transaction_value),
+        None => {
+            warn!("{} is not in the {} token registry", tx.token_type, tx.network);
+            wei_to_eth(tx.transaction_value)
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionStatusView {
     #[serde(rename = "PK")]
@@ -1275,11 +2125,14 @@ impl Transaction {
             chain_id: 11155420,
             signed_tx: None,
             transaction_hash: None,
+            replaced_tx_hashes: Vec::new(),
             event_log: None,
             priority_level: PriorityLevel::Standard,
             network: Network::OptimismSepolia,
             gas_price: None,
             gas_used: None,
+            effective_gas_price: None,
+            l1_fee: None,
             gas_limit: None,
             nonce: None,
             max_fee_per_gas: None,
@@ -1287,7 +2140,10 @@ impl Transaction {
             total_fee_paid: None,
             exchange_rate: None,
             block_number: None,
+            block_hash: None,
+            finality: None,
             receipt_status: None,
+            failure_reason: None,
             contract_address: None,
             approval_tx_hash: None,
             recipient_tx_hash: None,
@@ -1313,11 +2169,14 @@ impl Transaction {
             chain_id: 11155420,
             signed_tx: None,
             transaction_hash: None,
+            replaced_tx_hashes: Vec::new(),
             event_log: None,
             priority_level: PriorityLevel::Standard,
             network: Network::OptimismSepolia,
             gas_price: None,
             gas_used: None,
+            effective_gas_price: None,
+            l1_fee: None,
             gas_limit: None,
             nonce: None,
             max_fee_per_gas: None,
@@ -1325,7 +2184,10 @@ impl Transaction {
             total_fee_paid: None,
             exchange_rate: None,
             block_number: None,
+            block_hash: None,
+            finality: None,
             receipt_status: None,
+            failure_reason: None,
             contract_address: None,
             approval_tx_hash: None,
             recipient_tx_hash: None,
@@ -1344,6 +2206,8 @@ mod tests {
     use ethers_core::types::H256;
     use crate::utilities::config;
     use crate::utilities::config::get_transaction_event_table;
+    use crate::database::event_store::{EventStore, InMemoryEventStore};
+    use crate::database::transaction_event::TransactionEventManager;
     #[cfg(test)]
     impl Default for Transaction {
         fn default() -> Self {
@@ -1363,11 +2227,14 @@ mod tests {
                 chain_id: 0,
                 signed_tx: None,
                 transaction_hash: None,
+                replaced_tx_hashes: Vec::new(),
                 event_log: None,
                 priority_level: PriorityLevel::Standard,
                 network: Network::OptimismSepolia,
                 gas_price: None,
                 gas_used: None,
+                effective_gas_price: None,
+                l1_fee: None,
                 gas_limit: None,
                 nonce: None,
                 max_fee_per_gas: None,
@@ -1375,7 +2242,10 @@ mod tests {
                 total_fee_paid: None,
                 exchange_rate: None,
                 block_number: None,
+                block_hash: None,
+                finality: None,
                 receipt_status: None,
+                failure_reason: None,
                 contract_address: None,
                 approval_tx_hash: None,
                 recipient_tx_hash: None,
@@ -1386,6 +2256,7 @@ mod tests {
     }
 
     static SIGNED_TX: &str = "0xf86b...";
+    static REPLACEMENT_TX: &str = "0xf86b0f830f424082520894a826d3484625b29dfcbdaee6ca636a1acb439bf885e8d4a51000808401546fdca0f11a428a380a093705b21b1d59ad21240ec5fb6a88230b6e97616ff0384c4618a02b44589337b649c9e5cdb9e0c9e191c3ccf9e2676aed5c6e4b6f3c58368fd69a";
 
     #[test]
     fn test_deserialize_transaction_request() {
@@ -1504,7 +2375,7 @@ mod tests {
         }
     });
 
-        let request: TransactionRequest = serde_json::from_value(raw).expect("should deserialize request");
+        let request = quoted_request(raw);
         let cognito = get_cognito_client_with_assumed_role().await;
         let dynamo = get_dynamodb_client_with_assumed_role().await;
 
@@ -1553,10 +2424,35 @@ mod tests {
     })
     }
 
+    /// Deserializes `raw` and attaches a signed quote for exactly that transfer.
+    fn quoted_request(raw: serde_json::Value) -> TransactionRequest {
+        let mut request: TransactionRequest = serde_json::from_value(raw).expect("should deserialize request");
+        let quote = Quote {
+            quote_id: Quote::new_id(),
+            user_id: "112527246877271240195".into(),
+            sender_address: request.sender_address.clone(),
+            recipient_address: request.recipient_address.clone(),
+            token_type: request.token_type.clone(),
+            network: request.network.clone(),
+            transaction_value: request.transaction_value,
+            fiat_value: request.fiat_value,
+            fiat_currency: request.fiat_currency_code.clone(),
+            splits: Vec::new(),
+            exchange_rate: request.exchange_rate,
+            service_fee: request.service_fee,
+            service_fee_minor: request.service_fee_minor,
+            priority_level: request.priority_level.clone(),
+            gas_pricing: request.gas_pricing.clone().unwrap_or_default(),
+            expires_at: Utc::now() + chrono::Duration::minutes(5),
+        };
+        request.quote = Some(quote.sign(&get_quote_signing_key()).unwrap());
+        request
+    }
+
     #[tokio::test]
     async fn minimal_valid_transactionrequest() {
         config::init();
-        let request: TransactionRequest = serde_json::from_value(test_request_json()).unwrap();
+        let request = quoted_request(test_request_json());
         let cognito = get_cognito_client_with_assumed_role().await.unwrap();
         let dynamo = get_dynamodb_client_with_assumed_role().await;
 
@@ -1567,17 +2463,34 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn missing_gas_pricing() {
+    async fn missing_quote() {
         config::init();
-        let mut invalid_request = test_request_json();
-        invalid_request.as_object_mut().unwrap().remove("gas_pricing");
-
-        let request: TransactionRequest = serde_json::from_value(invalid_request).unwrap();
+        let request: TransactionRequest = serde_json::from_value(test_request_json()).unwrap();
         let cognito = get_cognito_client_with_assumed_role().await.unwrap();
         let dynamo = get_dynamodb_client_with_assumed_role().await;
 
         let result = TransactionBundle::from_request("112527246877271240195".into(), request, &cognito, &dynamo).await;
-        assert!(matches!(result, Err(TransactionError::MissingGasEstimate)));
+        assert!(matches!(result, Err(TransactionError::InvalidQuote(_))));
+    }
+
+    #[tokio::test]
+    async fn quote_overrides_client_pricing_and_is_single_use() {
+        config::init();
+        let mut request = quoted_request(test_request_json());
+        request.service_fee = 0;
+        request.gas_pricing = None;
+        let mut replay: TransactionRequest = serde_json::from_value(test_request_json()).unwrap();
+        replay.quote = request.quote.clone();
+        let cognito = get_cognito_client_with_assumed_role().await.unwrap();
+        let dynamo = get_dynamodb_client_with_assumed_role().await;
+
+        let bundle = TransactionBundle::from_request("112527246877271240195".into(), request, &cognito, &dynamo).await.unwrap();
+        let metadata = bundle.metadata.as_ref().unwrap();
+        assert_eq!(metadata.service_fee, 10_000_000_000_000);
+        assert_eq!(metadata.gas_pricing.gas_price, "1000000000");
+
+        let result = TransactionBundle::from_request("112527246877271240195".into(), replay, &cognito, &dynamo).await;
+        assert!(matches!(result, Err(TransactionError::InvalidQuote(_))));
     }
 
     #[tokio::test]
@@ -1598,23 +2511,22 @@ mod tests {
         let mut event = TransactionEvent::initiate(bundle).unwrap();
         event.event_type = EventType::Broadcast;
 
-        let dynamo = get_dynamodb_client_with_assumed_role().await;
-        let manager = TransactionEventManager::new(Arc::new(dynamo), get_transaction_event_table());
-        let result = TransactionEvent::on_signed(&event, SIGNED_TX, SIGNED_TX, manager).await;
+        let store = Arc::new(InMemoryEventStore::new());
+        let result = TransactionEvent::on_signed(&event, SIGNED_TX, SIGNED_TX, &[], store).await;
         assert!(result.is_err());
     }
 
     #[tokio::test]
     async fn broadcast_main_leg_from_sign_signed() {
         config::init();
-        let request: TransactionRequest = serde_json::from_value(test_request_json()).unwrap();
+        let request = quoted_request(test_request_json());
         let cognito = get_cognito_client_with_assumed_role().await.unwrap();
         let dynamo = Arc::new(get_dynamodb_client_with_assumed_role().await);
         let bundle = TransactionBundle::from_request("112527246877271240195".into(), request, &cognito, &dynamo).await.unwrap();
         let event = TransactionEvent::initiate(bundle.clone()).unwrap();
 
         let manager = TransactionEventManager::new(dynamo.clone(), get_transaction_event_table());
-        let signed_event = TransactionEvent::on_signed(&event, SIGNED_TX, SIGNED_TX, Arc::clone(&manager)).await.unwrap();
+        let signed_event = TransactionEvent::on_signed(&event, SIGNED_TX, SIGNED_TX, &[], manager.clone()).await.unwrap();
         let broadcasted = TransactionEvent::on_broadcast(&signed_event, H256::zero(), manager).await.unwrap();
         assert_eq!(broadcasted.event_type, EventType::Broadcast);
     }
@@ -1629,7 +2541,9 @@ mod tests {
 
         let event = TransactionEvent {
             event_id: "id".into(),
-            bundle_id: "bid".into(),
+            idempotency_key: None,
+            sequence: 1,
+            bundle_id: bundle.bundle_id.clone(),
             user_id: "user".into(),
             event_type: EventType::Confirm,
             leg: None,
@@ -1639,9 +2553,8 @@ mod tests {
             bundle_snapshot: bundle,
         };
 
-        let dynamo = Arc::new(get_dynamodb_client_with_assumed_role().await);
-        let manager = TransactionEventManager::new(dynamo, get_transaction_event_table());
-        let broadcasted = TransactionEvent::on_broadcast(&event, H256::zero(), manager).await.unwrap();
+        let store = Arc::new(InMemoryEventStore::with_history(vec![event.clone()]));
+        let broadcasted = TransactionEvent::on_broadcast(&event, H256::zero(), store).await.unwrap();
         assert_eq!(broadcasted.event_type, EventType::Broadcast);
     }
 
@@ -1653,6 +2566,8 @@ mod tests {
 
         let event = TransactionEvent {
             event_id: "e".into(),
+            idempotency_key: None,
+            sequence: 1,
             bundle_id: "b".into(),
             user_id: "user".into(),
             event_type: EventType::Error,
@@ -1663,23 +2578,56 @@ mod tests {
             bundle_snapshot: bundle,
         };
 
-        let dynamo = Arc::new(get_dynamodb_client_with_assumed_role().await);
-        let manager = TransactionEventManager::new(dynamo, get_transaction_event_table());
-        let result = TransactionEvent::on_broadcast(&event, H256::zero(), manager).await;
+        let store = Arc::new(InMemoryEventStore::with_history(vec![event.clone()]));
+        let result = TransactionEvent::on_broadcast(&event, H256::zero(), store).await;
         assert!(result.is_err());
     }
 
+    #[test]
+    fn a_picked_leg_is_recorded_only_while_it_can_still_go_out() {
+        let tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 0);
+        let mut bundle = TransactionBundle::new("user".into(), tx.clone(), tx.clone(), Some(BundleMetadata::default()));
+        bundle.split_txs = vec![tx.clone(), tx];
+        let initiated = TransactionEvent::initiate(bundle).unwrap();
+        let signed = TransactionEvent::sign(&initiated, SIGNED_TX, SIGNED_TX, &[SIGNED_TX.into(), SIGNED_TX.into()]).unwrap();
+
+        // Another sender got the first split leg out first
+        let split_sent = TransactionEvent::broadcast_leg(&signed, TransactionLeg::Split(0), H256::repeat_byte(1)).unwrap();
+        assert!(TransactionEvent::broadcast_leg(&split_sent, TransactionLeg::Split(0), H256::repeat_byte(2)).is_err());
+
+        // Once it is confirmed only the next split leg may go out, never the one already sent
+        let mined = split_sent.bundle_snapshot.leg(TransactionLeg::Split(0)).clone();
+        let split_confirmed = TransactionEvent::confirm(&split_sent, &mined).unwrap();
+        assert!(TransactionEvent::broadcast_leg(&split_confirmed, TransactionLeg::Split(0), H256::repeat_byte(2)).is_err());
+        // Nor the main leg while a split leg ahead of it is unsent
+        assert!(TransactionEvent::broadcast_leg(&split_confirmed, TransactionLeg::Main, H256::repeat_byte(3)).is_err());
+        let next = TransactionEvent::broadcast_leg(&split_confirmed, TransactionLeg::Split(1), H256::repeat_byte(3)).unwrap();
+        assert_eq!(next.leg, Some(TransactionLeg::Split(1)));
+    }
+
     #[tokio::test]
     async fn marks_leg_and_bundle_as_failed() {
         config::init();
         let tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 0);
         let bundle = TransactionBundle::new("user".into(), tx.clone(), tx.clone(), Some(BundleMetadata::default()));
-        let event = TransactionEvent::initiate(bundle).unwrap();
+        let initiated = TransactionEvent::initiate(bundle).unwrap();
+        let event = TransactionEvent::sign(&initiated, SIGNED_TX, SIGNED_TX, &[]).unwrap();
 
-        let dynamo = Arc::new(get_dynamodb_client_with_assumed_role().await);
-        let manager = TransactionEventManager::new(dynamo, get_transaction_event_table());
-        let failed = TransactionEvent::on_fail(&event, TransactionLeg::Main, manager).await.unwrap();
+        let store = Arc::new(InMemoryEventStore::with_history(vec![initiated.clone(), event.clone()]));
+        let failed = TransactionEvent::on_fail(&event, TransactionLeg::Main, store).await.unwrap();
         assert_eq!(failed.bundle_status, Some(BundleStatus::Failed));
+        assert_eq!(failed.bundle_snapshot.main_tx.status, TransactionStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn rejects_failing_a_leg_that_was_never_signed() {
+        let tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 0);
+        let bundle = TransactionBundle::new("user".into(), tx.clone(), tx.clone(), Some(BundleMetadata::default()));
+        let event = TransactionEvent::initiate(bundle).unwrap();
+
+        let store = Arc::new(InMemoryEventStore::with_history(vec![event.clone()]));
+        let result = TransactionEvent::on_fail(&event, TransactionLeg::Main, store).await;
+        assert!(matches!(result, Err(TransactionError::InvalidStateTransition { .. })));
     }
 
     #[tokio::test]
@@ -1689,12 +2637,381 @@ mod tests {
         let bundle = TransactionBundle::new("user".into(), tx.clone(), tx.clone(), Some(BundleMetadata::default()));
         let event = TransactionEvent::initiate(bundle).unwrap();
 
-        let dynamo = Arc::new(get_dynamodb_client_with_assumed_role().await);
-        let manager = TransactionEventManager::new(dynamo, get_transaction_event_table());
-        let errored = TransactionEvent::on_error(&event, TransactionLeg::Fee, manager).await.unwrap();
+        let store = Arc::new(InMemoryEventStore::with_history(vec![event.clone()]));
+        let errored = TransactionEvent::on_error(&event, TransactionLeg::Fee, store).await.unwrap();
         assert_eq!(errored.bundle_status, Some(BundleStatus::Errored));
     }
 
+    #[test]
+    fn receipt_costs_sit_next_to_the_estimate() {
+        let pricing = GasPricing {
+            estimated_gas: "21000".into(),
+            gas_price: "1000".into(),
+            max_fee_per_gas: "2000".into(),
+            max_priority_fee_per_gas: "100".into(),
+        };
+        let tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 0)
+            .with_gas_pricing(&pricing)
+            .with_gas_paid(Some(21_000), Some(1_500), Some(10_500_000));
+
+        // The quoted price is kept alongside the one paid
+        assert_eq!(tx.gas_price, Some(1_000));
+        assert_eq!(tx.effective_gas_price, Some(1_500));
+        assert_eq!(tx.total_fee_paid, Some(42_000_000));
+        assert_eq!(tx.estimated_network_fee(), Some(42_000_000));
+        assert_eq!(tx.fee_paid_percent_of_estimate(), Some(100.0));
+
+        let unmined = tx.with_gas_paid(None, None, None);
+        assert_eq!(unmined.total_fee_paid, None);
+        assert_eq!(unmined.fee_paid_percent_of_estimate(), None);
+    }
+
+    #[test]
+    fn reverted_leg_fails_the_bundle_and_replays() {
+        let fee_tx = Transaction::new("user".into(), "from".into(), "foxy".into(), 100, TokenType::ETH, 1, "GBP".into(), 1);
+        let main_tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 0);
+        let bundle = TransactionBundle::new("user".into(), fee_tx, main_tx, Some(BundleMetadata::default()));
+
+        let initiated = TransactionEvent::initiate(bundle).unwrap();
+        let signed = TransactionEvent::sign(&initiated, SIGNED_TX, SIGNED_TX, &[]).unwrap();
+        let main_sent = TransactionEvent::broadcast(&signed, H256::repeat_byte(1)).unwrap();
+        let reverted_tx = main_sent.bundle_snapshot.main_tx.clone()
+            .with_block_number(Some(10))
+            .with_receipt_status(Some(0))
+            .with_gas_paid(Some(30_000), Some(2_000), Some(1_000_000))
+            .with_failure_reason(Some("ERC20: transfer amount exceeds balance".into()));
+        let reverted = TransactionEvent::revert(&main_sent, &reverted_tx).unwrap();
+
+        assert_eq!(reverted.event_type, EventType::Fail);
+        assert_eq!(reverted.bundle_status, Some(BundleStatus::Failed));
+        let main_tx = &reverted.bundle_snapshot.main_tx;
+        assert_eq!(main_tx.status, TransactionStatus::Failed);
+        assert_eq!(main_tx.total_fee_paid, Some(61_000_000));
+        assert_eq!(main_tx.failure_reason.as_deref(), Some("ERC20: transfer amount exceeds balance"));
+
+        let mut events = vec![initiated, signed, main_sent, reverted];
+        for (i, event) in events.iter_mut().enumerate() {
+            event.event_id = format!("event-{}", i);
+        }
+        let bundle = TransactionEvent::replay(&events).expect("history should replay cleanly");
+        assert_eq!(bundle.main_tx.receipt_status, Some(0));
+        assert_eq!(bundle.main_tx.failure_reason.as_deref(), Some("ERC20: transfer amount exceeds balance"));
+    }
+
+    #[tokio::test]
+    async fn reverted_fee_leg_fails_the_bundle_after_the_main_leg() {
+        config::init();
+        let fee_tx = Transaction::new("user".into(), "from".into(), "foxy".into(), 100, TokenType::ETH, 1, "GBP".into(), 1);
+        let main_tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 0);
+        let bundle = TransactionBundle::new("user".into(), fee_tx, main_tx, Some(BundleMetadata::default()));
+
+        let initiated = TransactionEvent::initiate(bundle).unwrap();
+        let signed = TransactionEvent::sign(&initiated, SIGNED_TX, SIGNED_TX, &[]).unwrap();
+        let main_sent = TransactionEvent::broadcast(&signed, H256::repeat_byte(1)).unwrap();
+        let main_mined = main_sent.bundle_snapshot.main_tx.clone();
+        let main_confirmed = TransactionEvent::confirm(&main_sent, &main_mined).unwrap();
+        let fee_sent = TransactionEvent::broadcast(&main_confirmed, H256::repeat_byte(2)).unwrap();
+        assert_eq!(fee_sent.leg, Some(TransactionLeg::Fee));
+
+        let store = Arc::new(InMemoryEventStore::with_history(vec![
+            initiated.clone(), signed.clone(), main_sent.clone(), main_confirmed.clone(), fee_sent.clone(),
+        ]));
+        let reverted_tx = fee_sent.bundle_snapshot.fee_tx.clone()
+            .with_block_number(Some(12))
+            .with_receipt_status(Some(0));
+        let reverted = TransactionEvent::on_reverted(&fee_sent, &reverted_tx, store.clone()).await.unwrap();
+
+        // The bundle no longer sits in MainConfirmed for the finalizer to pick up again
+        assert_eq!(reverted.event_type, EventType::Fail);
+        assert_eq!(reverted.leg, Some(TransactionLeg::Fee));
+        assert_eq!(reverted.bundle_status, Some(BundleStatus::Failed));
+        assert_eq!(reverted.bundle_snapshot.fee_tx.status, TransactionStatus::Failed);
+        assert_eq!(reverted.bundle_snapshot.main_tx.status, TransactionStatus::Confirmed);
+        assert!(state_machine::is_terminal(&reverted.bundle_snapshot.status));
+
+        let history = store.get_events(&reverted.bundle_id).await.unwrap();
+        let bundle = TransactionEvent::replay(&history).expect("history should replay cleanly");
+        assert_eq!(bundle.fee_tx.receipt_status, Some(0));
+    }
+
+    fn replayable_history() -> Vec<TransactionEvent> {
+        let fee_tx = Transaction::new("user".into(), "from".into(), "foxy".into(), 100, TokenType::ETH, 1, "GBP".into(), 1);
+        let main_tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 0);
+        let bundle = TransactionBundle::new("user".into(), fee_tx, main_tx, Some(BundleMetadata::default()));
+
+        let initiated = TransactionEvent::initiate(bundle).unwrap();
+        let signed = TransactionEvent::sign(&initiated, SIGNED_TX, SIGNED_TX, &[]).unwrap();
+        let main_sent = TransactionEvent::broadcast(&signed, H256::repeat_byte(1)).unwrap();
+        let main_tx = main_sent.bundle_snapshot.main_tx.clone()
+            .with_status(TransactionStatus::Confirmed)
+            .with_block_number(Some(10));
+        let main_confirmed = TransactionEvent::confirm(&main_sent, &main_tx).unwrap();
+        let fee_sent = TransactionEvent::broadcast(&main_confirmed, H256::repeat_byte(2)).unwrap();
+        let fee_tx = fee_sent.bundle_snapshot.fee_tx.clone().with_status(TransactionStatus::Confirmed);
+        let completed = TransactionEvent::confirm(&fee_sent, &fee_tx).unwrap();
+
+        let mut events = vec![initiated, signed, main_sent, main_confirmed, fee_sent, completed];
+        for (i, event) in events.iter_mut().enumerate() {
+            event.event_id = format!("event-{}", i);
+        }
+        events
+    }
+
+    #[test]
+    fn replay_rebuilds_completed_bundle() {
+        let events = replayable_history();
+
+        let bundle = TransactionEvent::replay(&events).expect("history should replay cleanly");
+
+        assert_eq!(bundle.status, BundleStatus::Completed);
+        assert_eq!(bundle.main_tx.status, TransactionStatus::Confirmed);
+        assert_eq!(bundle.fee_tx.status, TransactionStatus::Confirmed);
+        assert_eq!(bundle.main_tx.block_number, Some(10));
+        assert_eq!(bundle.fee_tx.transaction_hash, Some(format!("{:#x}", H256::repeat_byte(2))));
+    }
+
+    #[test]
+    fn replay_rebuilds_split_bundle() {
+        let fee_tx = Transaction::new("user".into(), "from".into(), "foxy".into(), 100, TokenType::ETH, 1, "GBP".into(), 2);
+        let main_tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 1);
+        let split_tx = Transaction::new("user".into(), "from".into(), "carol".into(), 500, TokenType::ETH, 50, "GBP".into(), 0);
+        let bundle = TransactionBundle::new("user".into(), fee_tx, main_tx, Some(BundleMetadata::default()))
+            .with_split_txs(vec![split_tx]);
+
+        let initiated = TransactionEvent::initiate(bundle).unwrap();
+        let signed = TransactionEvent::sign(&initiated, SIGNED_TX, SIGNED_TX, &[SIGNED_TX.to_string()]).unwrap();
+        let split_sent = TransactionEvent::broadcast(&signed, H256::repeat_byte(1)).unwrap();
+        let split_tx = split_sent.bundle_snapshot.split_txs[0].clone();
+        let split_confirmed = TransactionEvent::confirm(&split_sent, &split_tx).unwrap();
+        let main_sent = TransactionEvent::broadcast(&split_confirmed, H256::repeat_byte(2)).unwrap();
+
+        let mut events = vec![initiated, signed, split_sent, split_confirmed, main_sent];
+        for (i, event) in events.iter_mut().enumerate() {
+            event.event_id = format!("event-{}", i);
+        }
+
+        let bundle = TransactionEvent::replay(&events).expect("history should replay cleanly");
+        assert_eq!(bundle.status, BundleStatus::Signed);
+        assert_eq!(bundle.split_txs[0].status, TransactionStatus::Confirmed);
+        assert_eq!(bundle.main_tx.status, TransactionStatus::Pending);
+
+        // A split leg dropped from a later snapshot no longer matches the replay
+        events[4].bundle_snapshot.split_txs.clear();
+        assert!(TransactionEvent::replay(&events).is_err());
+    }
+
+    #[test]
+    fn replay_rebuilds_inclusions_and_reorgs() {
+        let fee_tx = Transaction::new("user".into(), "from".into(), "foxy".into(), 100, TokenType::ETH, 1, "GBP".into(), 1);
+        let main_tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 0);
+        let bundle = TransactionBundle::new("user".into(), fee_tx, main_tx, Some(BundleMetadata::default()));
+
+        let initiated = TransactionEvent::initiate(bundle).unwrap();
+        let signed = TransactionEvent::sign(&initiated, SIGNED_TX, SIGNED_TX, &[]).unwrap();
+        let main_sent = TransactionEvent::broadcast(&signed, H256::repeat_byte(1)).unwrap();
+        let mined = main_sent.bundle_snapshot.main_tx.clone()
+            .with_block_number(Some(10))
+            .with_block_hash(Some("0xaa".into()))
+            .with_finality(Some(FinalityStage::Included));
+        let included = TransactionEvent::include(&main_sent, &mined).unwrap();
+        let reorged = TransactionEvent::reorg(&included, TransactionLeg::Main).unwrap();
+        let remined = mined.with_block_number(Some(11)).with_block_hash(Some("0xbb".into()));
+        let reincluded = TransactionEvent::include(&reorged, &remined).unwrap();
+        let safe = TransactionEvent::confirm(&reincluded, &remined.with_finality(Some(FinalityStage::Safe))).unwrap();
+
+        let mut events = vec![initiated, signed, main_sent, included, reorged, reincluded, safe];
+        for (i, event) in events.iter_mut().enumerate() {
+            event.event_id = format!("event-{}", i);
+        }
+
+        let bundle = TransactionEvent::replay(&events).expect("history should replay cleanly");
+        assert_eq!(bundle.status, BundleStatus::MainConfirmed);
+        assert_eq!(bundle.main_tx.block_hash.as_deref(), Some("0xbb"));
+        assert_eq!(bundle.main_tx.finality, Some(FinalityStage::Safe));
+        assert_eq!(events[4].bundle_snapshot.main_tx.block_number, None);
+    }
+
+    #[test]
+    fn rebroadcasts_are_counted_and_replayed() {
+        let fee_tx = Transaction::new("user".into(), "from".into(), "foxy".into(), 100, TokenType::ETH, 1, "GBP".into(), 1);
+        let main_tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 0);
+        let bundle = TransactionBundle::new("user".into(), fee_tx, main_tx, Some(BundleMetadata::default()));
+
+        let initiated = TransactionEvent::initiate(bundle).unwrap();
+        let signed = TransactionEvent::sign(&initiated, SIGNED_TX, SIGNED_TX, &[]).unwrap();
+        // Nothing has been sent that could be dropped
+        assert!(TransactionEvent::rebroadcast(&signed, TransactionLeg::Main).is_err());
+
+        let main_sent = TransactionEvent::broadcast(&signed, H256::repeat_byte(1)).unwrap();
+        let resent = TransactionEvent::rebroadcast(&main_sent, TransactionLeg::Main).unwrap();
+        let resent_again = TransactionEvent::rebroadcast(&resent, TransactionLeg::Main).unwrap();
+        assert_eq!(resent_again.bundle_snapshot.rebroadcast_attempts, 2);
+        assert_eq!(resent_again.bundle_snapshot.main_tx.transaction_hash, main_sent.bundle_snapshot.main_tx.transaction_hash);
+        assert_eq!(resent_again.bundle_snapshot.main_tx.status, TransactionStatus::Pending);
+        assert_eq!(resent_again.bundle_snapshot.status, BundleStatus::Signed);
+
+        let errored = TransactionEvent::error(&resent_again, TransactionLeg::Main).unwrap();
+        assert_eq!(errored.bundle_snapshot.status, BundleStatus::Errored);
+
+        let mut events = vec![initiated, signed, main_sent, resent, resent_again, errored];
+        for (i, event) in events.iter_mut().enumerate() {
+            event.event_id = format!("event-{}", i);
+        }
+
+        let bundle = TransactionEvent::replay(&events).expect("history should replay cleanly");
+        assert_eq!(bundle.rebroadcast_attempts, 2);
+
+        events[4].bundle_snapshot.rebroadcast_attempts = 5;
+        assert!(matches!(TransactionEvent::replay(&events), Err(TransactionError::StateMachine(msg)) if msg.contains("event-4")));
+    }
+
+    #[test]
+    fn replay_rejects_tampered_history() {
+        let mut events = replayable_history();
+        events[3].bundle_snapshot.status = BundleStatus::Completed;
+        events[3].bundle_status = Some(BundleStatus::Completed);
+
+        let result = TransactionEvent::replay(&events);
+        assert!(matches!(result, Err(TransactionError::StateMachine(msg)) if msg.contains("event-3")));
+    }
+
+    #[test]
+    fn transitions_advance_the_sequence() {
+        let events = replayable_history();
+
+        let sequences: Vec<u64> = events.iter().map(|e| e.sequence).collect();
+        assert_eq!(sequences, vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn replay_rejects_sequence_gap() {
+        let mut events = replayable_history();
+        events[3].sequence = 7;
+
+        let result = TransactionEvent::replay(&events);
+        assert!(matches!(result, Err(TransactionError::StateMachine(msg)) if msg.contains("sequence")));
+    }
+
+    #[test]
+    fn replay_rejects_stream_not_starting_with_initiate() {
+        let events = replayable_history();
+
+        assert!(TransactionEvent::replay(&events[1..]).is_err());
+        assert!(TransactionEvent::replay(&[]).is_err());
+    }
+
+    #[test]
+    fn replacement_keeps_every_hash_the_leg_has_had() {
+        let events = replayable_history();
+        let main_sent = &events[2];
+        let original_hash = format!("{:#x}", H256::repeat_byte(1));
+
+        let replaced = TransactionEvent::replace(main_sent, TransactionLeg::Main, REPLACEMENT_TX, 3_000, 200).unwrap();
+        let main_tx = &replaced.bundle_snapshot.main_tx;
+        assert_eq!(replaced.event_type, EventType::Replace);
+        assert_eq!(replaced.bundle_status, Some(BundleStatus::Signed));
+        assert_eq!(main_tx.replaced_tx_hashes, vec![original_hash.clone()]);
+        assert_eq!(main_tx.transaction_hash, Some(format!("{:#x}", main_tx.tx_hash().unwrap())));
+        assert_eq!((main_tx.max_fee_per_gas, main_tx.max_priority_fee_per_gas), (Some(3_000), Some(200)));
+
+        // Only a leg that has been broadcast can be replaced
+        assert!(TransactionEvent::replace(&events[1], TransactionLeg::Main, REPLACEMENT_TX, 3_000, 200).is_err());
+        assert!(TransactionEvent::replace(main_sent, TransactionLeg::Fee, REPLACEMENT_TX, 3_000, 200).is_err());
+
+        // The original was mined after all
+        let mined = main_tx.clone()
+            .with_status(TransactionStatus::Confirmed)
+            .with_transaction_hash(&original_hash);
+        let confirmed = TransactionEvent::confirm(&replaced, &mined).unwrap();
+
+        let mut history = events[..3].to_vec();
+        history.extend([replaced.clone(), confirmed]);
+        for (i, event) in history.iter_mut().enumerate() {
+            event.event_id = format!("event-{}", i);
+        }
+
+        let bundle = TransactionEvent::replay(&history).expect("history should replay cleanly");
+        assert_eq!(bundle.status, BundleStatus::MainConfirmed);
+        assert_eq!(bundle.main_tx.transaction_hash, Some(original_hash.clone()));
+        assert_eq!(bundle.main_tx.replaced_tx_hashes, vec![original_hash]);
+    }
+
+    #[tokio::test]
+    async fn owner_can_cancel_before_broadcast() {
+        let tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 0);
+        let bundle = TransactionBundle::new("user".into(), tx.clone(), tx.clone(), Some(BundleMetadata::default()));
+        let initiated = TransactionEvent::initiate(bundle).unwrap();
+        let signed = TransactionEvent::sign(&initiated, SIGNED_TX, SIGNED_TX, &[]).unwrap();
+
+        let store = Arc::new(InMemoryEventStore::with_history(vec![initiated.clone(), signed.clone()]));
+        let cancelled = TransactionEvent::on_cancel(&signed, "user", store.clone()).await.unwrap();
+
+        assert_eq!(cancelled.bundle_status, Some(BundleStatus::Cancelled));
+        assert_eq!(cancelled.bundle_snapshot.main_tx.status, TransactionStatus::Cancelled);
+        assert_eq!(cancelled.bundle_snapshot.fee_tx.status, TransactionStatus::Cancelled);
+
+        let rebuilt = store.replay(&cancelled.bundle_id).await.unwrap();
+        assert_eq!(rebuilt.status, BundleStatus::Cancelled);
+    }
+
+    #[test]
+    fn cancel_is_rejected_for_other_users_and_after_broadcast() {
+        let tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 0);
+        let bundle = TransactionBundle::new("user".into(), tx.clone(), tx.clone(), Some(BundleMetadata::default()));
+        let initiated = TransactionEvent::initiate(bundle).unwrap();
+        let signed = TransactionEvent::sign(&initiated, SIGNED_TX, SIGNED_TX, &[]).unwrap();
+        let broadcast = TransactionEvent::broadcast(&signed, H256::zero()).unwrap();
+
+        assert!(matches!(TransactionEvent::cancel(&initiated, "someone-else"), Err(TransactionError::Unauthorized)));
+        assert!(matches!(TransactionEvent::cancel(&broadcast, "user"), Err(TransactionError::InvalidStateTransition { .. })));
+        assert!(TransactionEvent::cancel(&initiated, "user").is_ok());
+    }
+
+    #[test]
+    fn bundle_expires_only_while_unsigned_past_ttl() {
+        let tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 0);
+        let mut bundle = TransactionBundle::new("user".into(), tx.clone(), tx.clone(), Some(BundleMetadata::default()));
+        let ttl = chrono::Duration::minutes(5);
+        let now = bundle.created_at;
+
+        assert!(!bundle.is_expired(now + chrono::Duration::minutes(4), ttl));
+        assert!(bundle.is_expired(now + chrono::Duration::minutes(6), ttl));
+
+        bundle.status = BundleStatus::Signed;
+        assert!(!bundle.is_expired(now + chrono::Duration::minutes(6), ttl));
+    }
+
+    #[tokio::test]
+    async fn initiated_bundle_expires_and_cannot_be_signed() {
+        let tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 0);
+        let bundle = TransactionBundle::new("user".into(), tx.clone(), tx.clone(), Some(BundleMetadata::default()));
+        let initiated = TransactionEvent::initiate(bundle).unwrap();
+
+        let store = Arc::new(InMemoryEventStore::with_history(vec![initiated.clone()]));
+        let expired = TransactionEvent::on_expire(&initiated, store.clone()).await.unwrap();
+
+        assert_eq!(expired.bundle_status, Some(BundleStatus::Expired));
+        assert_eq!(expired.bundle_snapshot.main_tx.status, TransactionStatus::Expired);
+        assert_eq!(expired.bundle_snapshot.fee_tx.status, TransactionStatus::Expired);
+        assert!(matches!(
+            TransactionEvent::sign(&expired, SIGNED_TX, SIGNED_TX, &[]),
+            Err(TransactionError::InvalidStateTransition { .. })
+        ));
+
+        let rebuilt = store.replay(&expired.bundle_id).await.unwrap();
+        assert_eq!(rebuilt.status, BundleStatus::Expired);
+    }
+
+    #[test]
+    fn signed_bundle_cannot_expire() {
+        let tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 0);
+        let bundle = TransactionBundle::new("user".into(), tx.clone(), tx.clone(), Some(BundleMetadata::default()));
+        let initiated = TransactionEvent::initiate(bundle).unwrap();
+        let signed = TransactionEvent::sign(&initiated, SIGNED_TX, SIGNED_TX, &[]).unwrap();
+
+        assert!(matches!(TransactionEvent::expire(&signed), Err(TransactionError::InvalidStateTransition { .. })));
+    }
+
     #[tokio::test]
     async fn create_from_transaction_with_complete_gas_data() {
         config::init();
@@ -1706,9 +3023,40 @@ mod tests {
                 max_priority_fee_per_gas: "0".into()
             });
 
-        let unsigned = UnsignedTransaction::from(&tx);
+        let unsigned = UnsignedTransaction::try_from(&tx).unwrap();
         assert_eq!(unsigned.nonce, "7");
         assert_eq!(unsigned.token_decimals, 18);
+        assert_eq!(unsigned.to, "to");
+        assert_eq!(unsigned.value, "1000000");
+        assert_eq!(unsigned.data, "0x");
+    }
+
+    #[test]
+    fn unsigned_usdc_transfer_targets_the_token_contract() {
+        config::init();
+        let tx = Transaction::new("user".into(),
+                                  "0xe006487c4cec454574b6c9a9f79ff8a5dee636a0".into(),
+                                  "0xa826d3484625b29dfcbdaee6ca636a1acb439bf8".into(),
+                                  1_500_000, TokenType::USDC, 100, "GBP".into(), 7);
+        let contract = tx.contract_address.clone().expect("USDC legs carry the token contract");
+
+        let unsigned = UnsignedTransaction::try_from(&tx).unwrap();
+        assert_eq!(unsigned.to, contract);
+        assert_eq!(unsigned.recipient, "0xa826d3484625b29dfcbdaee6ca636a1acb439bf8");
+        assert_eq!(unsigned.value, "0");
+        assert_eq!(unsigned.amount_base_units, "1500000");
+        assert_eq!(unsigned.token_decimals, 6);
+        assert!(unsigned.data.starts_with("0xa9059cbb"));
+        assert_eq!(unsigned.data.len(), 2 + 68 * 2);
+    }
+
+    #[test]
+    fn only_token_legs_need_a_transfer_log() {
+        let eth = Transaction::new("user".into(), "0xa".into(), "0xb".into(), 1, TokenType::ETH, 1, "GBP".into(), 0);
+        let usdc = Transaction::new("user".into(), "0xa".into(), "0xa826d3484625b29dfcbdaee6ca636a1acb439bf8".into(), 1, TokenType::USDC, 1, "GBP".into(), 0);
+
+        assert!(eth.transfer_logged(&[]));
+        assert!(!usdc.transfer_logged(&[]));
     }
 
     #[test]
@@ -1745,6 +3093,7 @@ mod tests {
             gas_pricing: GasPricing::default(),
             service_fee_minor: Some(0),
             user_device,
+            split_recipients: Vec::new(),
         };
 
         let main_tx = Transaction::new(
@@ -1766,6 +3115,8 @@ mod tests {
             status: BundleStatus::Completed,
             fee_tx,
             main_tx,
+            split_txs: Vec::new(),
+            rebroadcast_attempts: 0,
             metadata: Some(metadata),
             created_at: Utc::now(),
             updated_at: Utc::now(),
@@ -1773,6 +3124,8 @@ mod tests {
 
         let event = TransactionEvent {
             event_id: "event-1".into(),
+            idempotency_key: None,
+            sequence: 1,
             bundle_id: bundle.bundle_id.clone(),
             user_id: sender.user_id.clone(),
             event_type: EventType::Confirm,
@@ -1794,4 +3147,40 @@ mod tests {
         assert_eq!(item.tx_hash.as_deref(), Some("0xabc123"));
         assert_eq!(item.message.as_deref(), Some("Thanks for the pizza!"));
     }
+
+    #[test]
+    fn leg_names_round_trip() {
+        for leg in [TransactionLeg::Fee, TransactionLeg::Main, TransactionLeg::Split(0), TransactionLeg::Split(7)] {
+            assert_eq!(TransactionLeg::from_str(&leg.to_string()).unwrap(), leg);
+        }
+
+        assert!(TransactionLeg::from_str("Split#").is_err());
+        assert!(TransactionLeg::from_str("Split#-1").is_err());
+    }
+
+    #[test]
+    fn network_names_round_trip_and_accept_legacy_values() {
+        for network in [Network::OptimismMainnet, Network::OptimismSepolia, Network::EthereumMainnet, Network::EthereumSepolia] {
+            assert_eq!(Network::from_str(&network.to_string()).unwrap(), network);
+        }
+
+        assert_eq!(Network::from_str("mainnet").unwrap(), Network::OptimismMainnet);
+        assert_eq!(Network::from_str("testnet").unwrap(), Network::OptimismSepolia);
+        assert!(Network::from_str("solana").is_err());
+    }
+
+    #[test]
+    fn legs_target_the_requested_network() {
+        let tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::USDC, 100, "GBP".into(), 0)
+            .with_network(&Network::EthereumSepolia);
+
+        assert_eq!(tx.network, Network::EthereumSepolia);
+        assert_eq!(tx.chain_id, 11_155_111);
+        assert_eq!(
+            tx.contract_address,
+            token_registry_for(&Network::EthereumSepolia).get(&TokenType::USDC).unwrap().contract_address
+        );
+        assert_eq!(Network::OptimismSepolia.settlement_layer(), Some(Network::EthereumSepolia));
+        assert_eq!(Network::EthereumSepolia.settlement_layer(), None);
+    }
 }
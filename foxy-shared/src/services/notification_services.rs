@@ -8,7 +8,7 @@ use crate::models::notifications::{FirebaseClaims, NotificationPayload, ServiceA
 use crate::models::user_device::UserDevice;
 use aws_sdk_cloudwatch::{Client as CloudWatchClient};
 use crate::models::errors::NotificationError;
-use crate::models::transactions::TransactionBundle;
+use crate::models::transactions::{TransactionBundle, TransactionLeg};
 use crate::repositories::device_repository::DeviceRepository;
 use crate::services::cloudwatch_services::{create_cloudwatch_client, emit_metric};
 
@@ -89,6 +89,64 @@ impl FirebaseClient {
         Ok(())
     }
 
+    /// Tells both parties that `leg` failed on chain, so the payment did not arrive. A failed
+    /// fee leg comes after the payment arrived, so only the sender is told, about the fee.
+    pub async fn notify_transaction_failed(
+        &self,
+        bundle: &TransactionBundle,
+        leg: TransactionLeg,
+    ) -> Result<(), NotificationError> {
+        let (Some(metadata), Some(tx)) = (&bundle.metadata, bundle.get_leg(leg)) else {
+            return Ok(());
+        };
+
+        if leg == TransactionLeg::Fee {
+            let Some(sender) = &metadata.sender else {
+                return Ok(());
+            };
+            let recipient_name = metadata.recipient.as_ref().map(|r| r.name.as_str()).unwrap_or("<unknown>");
+            let body = format!("The service fee of £{:.2} for your payment to {} failed", tx.fiat_value as f64 / 100.0, recipient_name);
+
+            if let Err(e) = self.notify_user(&sender.user_id, "⚠️ Service Fee Failed", &body).await {
+                log::error!("❌ Failed to notify sender {}: {:?}", sender.user_id, e);
+            } else {
+                log::info!("📲 Notified sender {}", sender.user_id);
+            }
+            return Ok(());
+        }
+        let recipient = match leg {
+            TransactionLeg::Split(i) => metadata.split_recipients.get(i as usize),
+            _ => metadata.recipient.as_ref(),
+        };
+
+        let title = "⚠️ Payment Failed";
+        let amount = format!("£{:.2}", tx.fiat_value as f64 / 100.0);
+
+        if let Some(sender) = &metadata.sender {
+            let recipient_name = recipient.map(|r| r.name.as_str()).unwrap_or("<unknown>");
+            let sender_body = format!("Your payment of {} to {} failed and was not sent", amount, recipient_name);
+
+            if let Err(e) = self.notify_user(&sender.user_id, title, &sender_body).await {
+                log::error!("❌ Failed to notify sender {}: {:?}", sender.user_id, e);
+            } else {
+                log::info!("📲 Notified sender {}", sender.user_id);
+            }
+        }
+
+        if let Some(recipient) = recipient {
+            let sender_name = metadata.sender.as_ref().map(|s| s.name.as_str()).unwrap_or("<unknown>");
+            let recipient_body = format!("A payment of {} from {} failed and was not received", amount, sender_name);
+
+            if let Err(e) = self.notify_user(&recipient.user_id, title, &recipient_body).await {
+                log::error!("❌ Failed to notify recipient {}: {:?}", recipient.user_id, e);
+            } else {
+                log::info!("📲 Notified recipient {}", recipient.user_id);
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn notify_user(
         &self,
         user_id: &str,
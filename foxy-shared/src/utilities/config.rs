@@ -2,6 +2,7 @@
 use dotenv::dotenv;
 use std::env;
 use crate::models::transactions::{Network, TokenType};
+use crate::utilities::finality::ConfirmationPolicy;
 
 /// Initialize dotenv (only needs to be called once at startup)
 pub fn init() {
@@ -60,13 +61,17 @@ pub fn get_aws_region() -> String {
     get_env_var("AWS_REGION")
 }
 
+/// RPC endpoint for the default network.
 pub fn get_rpc_url() -> String {
-    let network = env::var("NETWORK").unwrap_or_else(|_| "mainnet".to_string());
+    get_rpc_url_for(&get_network())
+}
 
-    match network.as_str() {
-        "mainnet" => get_env_var("INFURA_RPC_MAINNET"),
-        "testnet" => get_env_var("INFURA_RPC_TESTNET"),
-        _ => panic!("Invalid NETWORK value: must be 'mainnet' or 'testnet'"),
+pub fn get_rpc_url_for(network: &Network) -> String {
+    match network {
+        Network::OptimismMainnet => get_env_var("INFURA_RPC_MAINNET"),
+        Network::OptimismSepolia => get_env_var("INFURA_RPC_TESTNET"),
+        Network::EthereumMainnet => get_ethereum_url(),
+        Network::EthereumSepolia => env::var("ETHEREUM_RPC_SEPOLIA").unwrap_or_else(|_| "https://ethereum-sepolia-rpc.publicnode.com".to_string()),
     }
 }
 
@@ -88,28 +93,106 @@ pub fn get_visibility_timeout() -> String {
     get_env_var("VISIBILITY_TIMEOUT_SECS")
 }
 
+/// How long an Initiated bundle may wait for a signature before it expires.
+pub fn get_bundle_ttl() -> chrono::Duration {
+    let secs = env::var("BUNDLE_TTL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(300);
+    chrono::Duration::seconds(secs)
+}
+
+/// How long a sent leg may go without a receipt before the watcher checks whether the node
+/// dropped it, from `STUCK_AFTER_SECS`.
+pub fn get_stuck_after() -> chrono::Duration {
+    let secs = env::var("STUCK_AFTER_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(600);
+    chrono::Duration::seconds(secs)
+}
+
+/// How many times the watcher resends a bundle's dropped legs before escalating, from
+/// `MAX_REBROADCASTS`.
+pub fn get_max_rebroadcasts() -> u32 {
+    env::var("MAX_REBROADCASTS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(3)
+}
+
+/// When the watcher confirms a mined leg, from `CONFIRMATION_STAGE` (`included`, `safe` or
+/// `finalized`) and `CONFIRMATION_DEPTH`. Defaults to the safe head at any depth.
+pub fn get_confirmation_policy() -> ConfirmationPolicy {
+    let default = ConfirmationPolicy::default();
+    let stage = env::var("CONFIRMATION_STAGE")
+        .map(|v| v.parse().unwrap_or_else(|e| panic!("Invalid CONFIRMATION_STAGE value: {}", e)))
+        .unwrap_or(default.stage);
+    let depth = env::var("CONFIRMATION_DEPTH")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default.depth);
+
+    ConfirmationPolicy { stage, depth }
+}
+
+/// Key for the HMAC on estimate quotes. Rotating it voids every outstanding quote.
+pub fn get_quote_signing_key() -> Vec<u8> {
+    get_env_var("QUOTE_SIGNING_KEY").into_bytes()
+}
+
 pub fn get_foxy_wallet() -> String {
     get_env_var("FOXY_WALLET_ADDRESS")
 }
 
+/// Chain ID of the default network.
 pub fn get_chain_id() -> u64 {
-    let network = env::var("NETWORK").unwrap_or_else(|_| "mainnet".to_string());
+    get_chain_id_for(&get_network())
+}
 
-    match network.as_str() {
-        "mainnet" => get_env_var("OPTIMISM_CHAIN_MAINNET").parse().unwrap_or(10),
-        "testnet" => get_env_var("OPTIMISM_CHAIN_TESTNET").parse().unwrap_or(420),
-        _ => panic!("Invalid NETWORK value"),
+pub fn get_chain_id_for(network: &Network) -> u64 {
+    match network {
+        Network::OptimismMainnet => get_env_var("OPTIMISM_CHAIN_MAINNET").parse().unwrap_or(10),
+        Network::OptimismSepolia => get_env_var("OPTIMISM_CHAIN_TESTNET").parse().unwrap_or(420),
+        Network::EthereumMainnet => 1,
+        Network::EthereumSepolia => 11_155_111,
     }
 }
 
+/// The network used when a request does not name one. `NETWORK` takes a network name, or
+/// the older `mainnet`/`testnet`, which mean Optimism.
 pub fn get_network() -> Network {
     let network = env::var("NETWORK").unwrap_or_else(|_| "mainnet".to_string());
+    network.parse().unwrap_or_else(|e| panic!("Invalid NETWORK value: {}", e))
+}
 
-    match network.as_str() {
-        "mainnet" => Network::OptimismMainnet,
-        "testnet" => Network::OptimismSepolia,
-        _ => panic!("Invalid NETWORK value"),
-    }
+/// Every network this deployment serves, from the comma-separated `SUPPORTED_NETWORKS`.
+/// Defaults to just the default network.
+pub fn get_supported_networks() -> Vec<Network> {
+    let Ok(networks) = env::var("SUPPORTED_NETWORKS") else {
+        return vec![get_network()];
+    };
+
+    networks
+        .split(',')
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .map(|n| n.parse().unwrap_or_else(|e| panic!("Invalid SUPPORTED_NETWORKS value: {}", e)))
+        .collect()
+}
+
+pub fn is_supported_network(network: &Network) -> bool {
+    get_supported_networks().contains(network)
+}
+
+/// The supported network with `chain_id`, e.g. from a scanned payment request.
+pub fn get_network_for_chain_id(chain_id: u64) -> Option<Network> {
+    get_supported_networks().into_iter().find(|n| get_chain_id_for(n) == chain_id)
+}
+
+/// A JSON token registry replacing the bundled one, see `token_registry`.
+pub fn get_token_registry_override() -> Option<String> {
+    env::var("TOKEN_REGISTRY").ok().filter(|v| !v.trim().is_empty())
 }
 
 pub fn get_default_token() -> TokenType {
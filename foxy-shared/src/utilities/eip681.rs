@@ -0,0 +1,290 @@
+use std::str::FromStr;
+use ethers_core::types::Address;
+use serde::{Deserialize, Serialize};
+use crate::models::errors::TransactionError;
+use crate::models::transactions::Network;
+use crate::utilities::config::{get_chain_id_for, get_network, get_network_for_chain_id};
+use crate::utilities::token_registry::{token_registry_for, TokenConfig};
+
+const SCHEME: &str = "ethereum:";
+
+/// Foxy's own parameter carrying a note for the payer. Other wallets ignore unknown keys.
+const MEMO_PARAM: &str = "foxy_memo";
+
+/// An EIP-681 payment request, the payload of a Foxy QR code.
+///
+/// Native transfers encode as `ethereum:<recipient>@<chain>?value=<wei>`, ERC-20 transfers as
+/// `ethereum:<contract>@<chain>/transfer?address=<recipient>&uint256=<base units>`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaymentRequest {
+    pub recipient: String,
+    /// `None` for the chain's native token.
+    pub contract_address: Option<String>,
+    /// `None` leaves the network to the payer; we then assume the default network.
+    pub chain_id: Option<u64>,
+    /// In the token's base units. `None` lets the payer choose.
+    pub amount: Option<u128>,
+    pub memo: Option<String>,
+}
+
+impl PaymentRequest {
+    /// A request for `token` on `network`, paid to `recipient`.
+    pub fn new(recipient: &str, token: &TokenConfig, network: &Network) -> Self {
+        Self {
+            recipient: recipient.to_string(),
+            contract_address: token.contract_address.clone(),
+            chain_id: Some(get_chain_id_for(network)),
+            amount: None,
+            memo: None,
+        }
+    }
+
+    pub fn with_amount(mut self, amount: Option<u128>) -> Self {
+        self.amount = amount;
+        self
+    }
+
+    pub fn with_memo(mut self, memo: Option<String>) -> Self {
+        self.memo = memo.filter(|m| !m.is_empty());
+        self
+    }
+
+    pub fn to_uri(&self) -> String {
+        let chain = self.chain_id.map(|id| format!("@{}", id)).unwrap_or_default();
+
+        let (mut uri, mut params) = match &self.contract_address {
+            None => {
+                let params = self.amount.map(|a| format!("value={}", a)).into_iter().collect::<Vec<_>>();
+                (format!("{}{}{}", SCHEME, self.recipient, chain), params)
+            }
+            Some(contract) => {
+                let mut params = vec![format!("address={}", self.recipient)];
+                params.extend(self.amount.map(|a| format!("uint256={}", a)));
+                (format!("{}{}{}/transfer", SCHEME, contract, chain), params)
+            }
+        };
+
+        if let Some(memo) = &self.memo {
+            params.push(format!("{}={}", MEMO_PARAM, percent_encode(memo)));
+        }
+        if !params.is_empty() {
+            uri.push('?');
+            uri.push_str(&params.join("&"));
+        }
+        uri
+    }
+
+    /// Parses a scanned URI. Only plain transfers are accepted: ENS names and calls to
+    /// functions other than `transfer` are rejected.
+    pub fn parse(uri: &str) -> Result<Self, String> {
+        let rest = uri.trim();
+        if rest.len() < SCHEME.len() || !rest[..SCHEME.len()].eq_ignore_ascii_case(SCHEME) {
+            return Err(format!("Not an {} URI", SCHEME));
+        }
+        let rest = &rest[SCHEME.len()..];
+        let rest = rest.strip_prefix("pay-").unwrap_or(rest);
+
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let (target, function) = match path.split_once('/') {
+            Some((target, function)) => (target, Some(function)),
+            None => (path, None),
+        };
+        let (target, chain_id) = match target.split_once('@') {
+            Some((target, chain)) => {
+                let chain_id = chain.parse::<u64>().map_err(|_| format!("Invalid chain ID: {}", chain))?;
+                (target, Some(chain_id))
+            }
+            None => (target, None),
+        };
+        let target = parse_address(target)?;
+
+        let mut params = Vec::new();
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').ok_or_else(|| format!("Invalid parameter: {}", pair))?;
+            params.push((key, value));
+        }
+        let param = |key: &str| params.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+
+        let memo = param(MEMO_PARAM).map(percent_decode).transpose()?;
+
+        match function {
+            None => Ok(Self {
+                recipient: target,
+                contract_address: None,
+                chain_id,
+                amount: param("value").map(parse_number).transpose()?,
+                memo,
+            }),
+            Some("transfer") => {
+                let recipient = param("address").ok_or("ERC-20 transfer is missing its address")?;
+                Ok(Self {
+                    recipient: parse_address(recipient)?,
+                    contract_address: Some(target),
+                    chain_id,
+                    amount: param("uint256").map(parse_number).transpose()?,
+                    memo,
+                })
+            }
+            Some(other) => Err(format!("Unsupported function: {}", other)),
+        }
+    }
+
+    /// The network and registered token this request pays in.
+    pub fn resolve(&self) -> Result<(Network, &'static TokenConfig), TransactionError> {
+        let network = match self.chain_id {
+            Some(chain_id) => get_network_for_chain_id(chain_id).ok_or(TransactionError::InvalidNetwork)?,
+            None => get_network(),
+        };
+
+        let token = token_registry_for(&network)
+            .enabled()
+            .find(|t| match (&t.contract_address, &self.contract_address) {
+                (None, None) => true,
+                (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+                _ => false,
+            })
+            .ok_or_else(|| TransactionError::InvalidToken(
+                self.contract_address.clone().unwrap_or_else(|| "native token".to_string())
+            ))?;
+
+        Ok((network, token))
+    }
+}
+
+fn parse_address(address: &str) -> Result<String, String> {
+    Address::from_str(address)
+        .map(|_| address.to_string())
+        .map_err(|_| format!("Invalid address: {}", address))
+}
+
+/// An EIP-681 number such as `1000`, `2.014e18` or `1e6`, which must come out whole.
+fn parse_number(number: &str) -> Result<u128, String> {
+    let invalid = || format!("Invalid amount: {}", number);
+
+    let (mantissa, exponent) = match number.split_once(['e', 'E']) {
+        Some((m, e)) => (m, e.trim_start_matches('+').parse::<u32>().map_err(|_| invalid())?),
+        None => (number, 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let frac = frac.trim_end_matches('0');
+    if (int.is_empty() && frac.is_empty()) || !int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let shift = exponent.checked_sub(frac.len() as u32).ok_or_else(|| format!("Amount is not whole: {}", number))?;
+    let digits = format!("{}{}", int, frac);
+    let digits = digits.trim_start_matches('0');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+
+    digits.parse::<u128>().ok()
+        .and_then(|d| 10u128.checked_pow(shift).and_then(|scale| d.checked_mul(scale)))
+        .ok_or_else(invalid)
+}
+
+fn percent_encode(value: &str) -> String {
+    value.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+        _ => format!("%{:02X}", b),
+    }).collect()
+}
+
+fn percent_decode(value: &str) -> Result<String, String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let byte = value.get(i + 1..i + 3)
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .ok_or_else(|| format!("Invalid escape in {}", value))?;
+                decoded.push(byte);
+                i += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            b => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).map_err(|e| format!("Invalid memo: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECIPIENT: &str = "0xa826d3484625b29dfcbdaee6ca636a1acb439bf8";
+    const USDC: &str = "0x0b2C639c533813f4Aa9D7837CAf62653d097Ff85";
+
+    fn native(amount: Option<u128>) -> PaymentRequest {
+        PaymentRequest {
+            recipient: RECIPIENT.to_string(),
+            contract_address: None,
+            chain_id: Some(10),
+            amount,
+            memo: None,
+        }
+    }
+
+    #[test]
+    fn encodes_native_and_erc20_requests() {
+        assert_eq!(native(Some(1_500_000_000_000_000_000)).to_uri(),
+                   format!("ethereum:{}@10?value=1500000000000000000", RECIPIENT));
+        assert_eq!(native(None).to_uri(), format!("ethereum:{}@10", RECIPIENT));
+
+        let usdc = PaymentRequest { contract_address: Some(USDC.to_string()), ..native(Some(2_500_000)) };
+        assert_eq!(usdc.to_uri(), format!("ethereum:{}@10/transfer?address={}&uint256=2500000", USDC, RECIPIENT));
+    }
+
+    #[test]
+    fn round_trips_with_memo() {
+        let request = native(Some(42)).with_memo(Some("Pizza & drinks 🍕".to_string()));
+        let uri = request.to_uri();
+
+        assert!(uri.contains("foxy_memo=Pizza%20%26%20drinks%20%F0%9F%8D%95"));
+        assert_eq!(PaymentRequest::parse(&uri).unwrap(), request);
+
+        let usdc = PaymentRequest { contract_address: Some(USDC.to_string()), ..request };
+        assert_eq!(PaymentRequest::parse(&usdc.to_uri()).unwrap(), usdc);
+    }
+
+    #[test]
+    fn parses_requests_from_other_wallets() {
+        let parsed = PaymentRequest::parse(&format!("ethereum:pay-{}?value=2.014e18&gas=21000", RECIPIENT)).unwrap();
+        assert_eq!(parsed.amount, Some(2_014_000_000_000_000_000));
+        assert_eq!(parsed.chain_id, None);
+
+        let parsed = PaymentRequest::parse(&format!("ETHEREUM:{}@1/transfer?address={}&uint256=1e6", USDC, RECIPIENT)).unwrap();
+        assert_eq!(parsed.contract_address.as_deref(), Some(USDC));
+        assert_eq!(parsed.recipient, RECIPIENT);
+        assert_eq!(parsed.amount, Some(1_000_000));
+    }
+
+    #[test]
+    fn rejects_unsupported_uris() {
+        assert!(PaymentRequest::parse(&format!("bitcoin:{}", RECIPIENT)).is_err());
+        assert!(PaymentRequest::parse("ethereum:foxy.eth?value=1").is_err(), "ENS names are not resolved");
+        assert!(PaymentRequest::parse(&format!("ethereum:{}/approve?address={}&uint256=1", USDC, RECIPIENT)).is_err());
+        assert!(PaymentRequest::parse(&format!("ethereum:{}/transfer?uint256=1", USDC)).is_err());
+        assert!(PaymentRequest::parse(&format!("ethereum:{}@optimism", RECIPIENT)).is_err());
+    }
+
+    #[test]
+    fn parses_eip681_numbers() {
+        assert_eq!(parse_number("1000"), Ok(1000));
+        assert_eq!(parse_number("1.5e3"), Ok(1500));
+        assert_eq!(parse_number("2E+2"), Ok(200));
+        assert_eq!(parse_number("0.000"), Ok(0));
+        assert!(parse_number("1.5").is_err(), "base units must be whole");
+        assert!(parse_number("1e40").is_err(), "overflows u128");
+        assert!(parse_number("-1").is_err());
+        assert!(parse_number("").is_err());
+    }
+}
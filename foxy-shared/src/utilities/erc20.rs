@@ -0,0 +1,124 @@
+use std::str::FromStr;
+use ethers_core::types::{Address, Log, H256, U256};
+use ethers_core::utils::keccak256;
+
+/// `transfer(address,uint256)`
+pub const TRANSFER_SELECTOR: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+
+/// `balanceOf(address)`
+pub const BALANCE_OF_SELECTOR: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+
+/// Topic 0 of the `Transfer(address,address,uint256)` event.
+pub fn transfer_event_topic() -> H256 {
+    H256::from(keccak256("Transfer(address,address,uint256)"))
+}
+
+/// ABI-encoded calldata for `transfer(recipient, amount)`, as 0x-prefixed hex (68 bytes).
+pub fn transfer_calldata(recipient: &str, amount: u128) -> Result<String, String> {
+    let recipient = parse_address(recipient)?;
+
+    let mut data = Vec::with_capacity(68);
+    data.extend_from_slice(&TRANSFER_SELECTOR);
+    data.extend_from_slice(H256::from(recipient).as_bytes());
+    data.extend_from_slice(&word(U256::from(amount)));
+
+    Ok(format!("0x{}", hex::encode(data)))
+}
+
+/// ABI-encoded calldata for `balanceOf(owner)`, as 0x-prefixed hex.
+pub fn balance_of_calldata(owner: &str) -> Result<String, String> {
+    let owner = parse_address(owner)?;
+
+    let mut data = Vec::with_capacity(36);
+    data.extend_from_slice(&BALANCE_OF_SELECTOR);
+    data.extend_from_slice(H256::from(owner).as_bytes());
+
+    Ok(format!("0x{}", hex::encode(data)))
+}
+
+/// Whether `logs` contain a `Transfer` of exactly `amount` to `recipient` emitted by `contract`.
+/// A successful receipt alone is not enough for tokens: a non-reverting contract may still
+/// have moved nothing.
+pub fn has_transfer(logs: &[Log], contract: &str, recipient: &str, amount: u128) -> bool {
+    let (Ok(contract), Ok(recipient)) = (parse_address(contract), parse_address(recipient)) else {
+        return false;
+    };
+    let topic = transfer_event_topic();
+
+    logs.iter().any(|log| {
+        log.address == contract
+            && log.topics.len() == 3
+            && log.topics[0] == topic
+            && log.topics[2] == H256::from(recipient)
+            && log.data.len() == 32
+            && U256::from_big_endian(&log.data) == U256::from(amount)
+    })
+}
+
+fn parse_address(address: &str) -> Result<Address, String> {
+    Address::from_str(address).map_err(|e| format!("Invalid address {}: {}", address, e))
+}
+
+fn word(value: U256) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers_core::types::Bytes;
+
+    const USDC: &str = "0x0b2C639c533813f4Aa9D7837CAf62653d097Ff85";
+    const RECIPIENT: &str = "0xa826d3484625b29dfcbdaee6ca636a1acb439bf8";
+
+    fn transfer_log(contract: &str, recipient: &str, amount: u128) -> Log {
+        Log {
+            address: parse_address(contract).unwrap(),
+            topics: vec![
+                transfer_event_topic(),
+                H256::from(parse_address("0xe006487c4cec454574b6c9a9f79ff8a5dee636a0").unwrap()),
+                H256::from(parse_address(recipient).unwrap()),
+            ],
+            data: Bytes::from(word(U256::from(amount)).to_vec()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn encodes_transfer_calldata() {
+        let data = transfer_calldata(RECIPIENT, 1_500_000).unwrap();
+
+        assert_eq!(
+            data,
+            "0xa9059cbb\
+             000000000000000000000000a826d3484625b29dfcbdaee6ca636a1acb439bf8\
+             000000000000000000000000000000000000000000000000000000000016e360"
+        );
+        assert_eq!(hex::decode(&data[2..]).unwrap().len(), 68);
+    }
+
+    #[test]
+    fn rejects_malformed_recipient() {
+        assert!(transfer_calldata("0x1234", 1).is_err());
+    }
+
+    #[test]
+    fn transfer_topic_matches_the_standard_event() {
+        assert_eq!(
+            format!("{:#x}", transfer_event_topic()),
+            "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+        );
+    }
+
+    #[test]
+    fn finds_matching_transfer_log() {
+        let logs = vec![transfer_log(USDC, RECIPIENT, 1_500_000)];
+
+        assert!(has_transfer(&logs, USDC, RECIPIENT, 1_500_000));
+        assert!(!has_transfer(&logs, USDC, RECIPIENT, 1_000_000), "amount must match");
+        assert!(!has_transfer(&logs, "0x5fd84259d66Cd46123540766Be93DFE6D43130D7", RECIPIENT, 1_500_000), "contract must match");
+        assert!(!has_transfer(&[], USDC, RECIPIENT, 1_500_000));
+    }
+}
@@ -4,7 +4,7 @@ use std::collections::HashMap;
 use std::future::Future;
 use once_cell::sync::Lazy;
 use crate::models::errors::FetchRateError;
-use crate::models::transactions::TokenType;
+use crate::utilities::token_registry::{PriceSource, TokenConfig};
 use crate::services::cloudwatch_services::OperationMetricTracker;
 
 static SHARED_CLIENT: Lazy<Client> = Lazy::new(Client::new);
@@ -25,7 +25,7 @@ struct CoinbaseData {
 }
 
 //TODO: Should these be environment variables?
-const FALLBACK_API: &str = "https://api.coinbase.com/v2/exchange-rates?currency=ETH";
+const FALLBACK_API: &str = "https://api.coinbase.com/v2/exchange-rates";
 
 
 pub struct ExchangeRateManager {
@@ -39,13 +39,17 @@ impl ExchangeRateManager {
         }
     }
 
-    //TODO: Get exchange rates for other tokens
-    pub async fn get_latest_rate(&self, fiat_currency: &str, _token_type: &TokenType) -> Result<f64, FetchRateError> {
+    /// Price of one whole `token` (1 ETH, 1 USDC) in `fiat_currency`.
+    pub async fn get_latest_rate(&self, fiat_currency: &str, token: &TokenConfig) -> Result<f64, FetchRateError> {
         let tracker = OperationMetricTracker::build("ExchangeRate").await;
 
+        // The registry says which symbol to quote and in which order to ask the sources
+        let feed = &token.price_feed;
+        let (primary, fallback) = (feed.sources.first(), feed.sources.get(1));
+
         let result = self.fetch_exchange_rate(
-            || async { self.fetch_chainlink_rate(fiat_currency).await },
-            || async { self.fetch_coinbase_rate(fiat_currency).await },
+            || async { self.fetch_source_rate(primary, fiat_currency, &feed.base).await },
+            || async { self.fetch_source_rate(fallback, fiat_currency, &feed.base).await },
         ).await;
 
         // Emit fatal if both failed
@@ -80,20 +84,32 @@ impl ExchangeRateManager {
         Err(FetchRateError::MissingRate)
     }
 
-    fn chainlink_url(fiat_currency: &str) -> String {
-        format!("https://api.chainlink.com/eth-{}", fiat_currency.to_lowercase())
+    async fn fetch_source_rate(&self, source: Option<&PriceSource>, fiat_currency: &str, base: &str) -> Result<f64, FetchRateError> {
+        match source {
+            Some(PriceSource::Chainlink) => self.fetch_chainlink_rate(fiat_currency, base).await,
+            Some(PriceSource::Coinbase) => self.fetch_coinbase_rate(fiat_currency, base).await,
+            None => Err(FetchRateError::MissingRate),
+        }
+    }
+
+    fn chainlink_url(fiat_currency: &str, base: &str) -> String {
+        format!("https://api.chainlink.com/{}-{}", base.to_lowercase(), fiat_currency.to_lowercase())
+    }
+
+    fn coinbase_url(base: &str) -> String {
+        format!("{}?currency={}", FALLBACK_API, base.to_uppercase())
     }
 
-    async fn fetch_chainlink_rate(&self, fiat_currency: &str) -> Result<f64, FetchRateError> {
-        let url = Self::chainlink_url(fiat_currency);
+    async fn fetch_chainlink_rate(&self, fiat_currency: &str, base: &str) -> Result<f64, FetchRateError> {
+        let url = Self::chainlink_url(fiat_currency, base);
         let client = &self.client;
         let response: ExchangeRateResponse = client.get(url).send().await?.json().await?;
         Ok(response.rate)
     }
 
-    async fn fetch_coinbase_rate(&self, fiat_currency: &str) -> Result<f64, FetchRateError> {
+    async fn fetch_coinbase_rate(&self, fiat_currency: &str, base: &str) -> Result<f64, FetchRateError> {
         let client = &self.client;
-        let url = format!("{}", FALLBACK_API);
+        let url = Self::coinbase_url(base);
 
         let response: CoinbaseResponse = client.get(&url).send().await?.json().await?;
 
@@ -136,6 +152,20 @@ mod tests {
         assert_eq!(result.unwrap(), 2650.0, "Should fallback to Coinbase if Chainlink fails");
     }
 
+    #[test]
+    fn test_rate_urls_follow_the_price_feed() {
+        assert_eq!(ExchangeRateManager::chainlink_url("GBP", "USDC"), "https://api.chainlink.com/usdc-gbp");
+        assert_eq!(ExchangeRateManager::coinbase_url("eth"), "https://api.coinbase.com/v2/exchange-rates?currency=ETH");
+        assert_eq!(ExchangeRateManager::coinbase_url("USDC"), "https://api.coinbase.com/v2/exchange-rates?currency=USDC");
+    }
+
+    #[tokio::test]
+    async fn test_missing_source_has_no_rate() {
+        let erm = ExchangeRateManager::new();
+        let result = erm.fetch_source_rate(None, "GBP", "ETH").await;
+        assert!(matches!(result, Err(FetchRateError::MissingRate)));
+    }
+
     #[tokio::test]
     async fn test_fetch_exchange_rate_failure() {
         let erm = ExchangeRateManager::new();
@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use crate::database::errors::DynamoDbError;
 use crate::services::cloudwatch_services::{result_to_f64, OperationMetricTracker};
 use crate::utilities::config::get_env_var;
+use crate::utilities::token_registry::TokenConfig;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct FeeStructure {
@@ -13,6 +14,25 @@ pub struct FeeStructure {
     pub percentage_fee_bps: u64,   // Stored in basis points (e.g., 100 for 1%)
 }
 
+impl FeeStructure {
+    /// The service fee on `amount`, in `amount`'s units. The base fee is set in wei, so
+    /// `base_fee` converts it into those units first.
+    pub fn service_fee(&self, amount: u128, base_fee: impl Fn(u128) -> u128) -> u128 {
+        base_fee(self.base_fee_wei) + (amount * self.percentage_fee_bps as u128) / 10_000
+    }
+}
+
+/// Converts a wei amount into the base units of a token worth `token_rate`, given ETH is
+/// worth `eth_rate`, both in the same fiat currency.
+pub fn wei_to_token_units(wei: u128, eth_rate: f64, token_rate: f64, token: &TokenConfig) -> u128 {
+    token.to_base_units(wei as f64 / 1e18 * eth_rate / token_rate)
+}
+
+/// Converts a wei amount into fiat minor units, given ETH is worth `eth_rate`.
+pub fn wei_to_fiat_minor(wei: u128, eth_rate: f64) -> u128 {
+    (wei as f64 / 1e18 * eth_rate * 100.0).round() as u128
+}
+
 #[async_trait::async_trait]
 pub trait FeeFetcher: Send + Sync {
     async fn fetch_fees(&self) -> Result<FeeStructure, DynamoDbError>;
@@ -74,17 +94,17 @@ pub async fn get_latest_fee_structure(
     dynamo_client.fetch_fees().await.map_err(DynamoDbError::from)
 }
 
+/// The service fee on `amount`, in whatever units `amount` is in. `base_fee` converts the
+/// wei base fee into them, e.g. `|wei| wei` for an ETH amount.
 pub async fn calculate_service_fee(
     dynamo_client: &dyn FeeFetcher,
-    wei_amount: u128,
+    amount: u128,
+    base_fee: impl Fn(u128) -> u128,
 ) -> Result<u128, DynamoDbError> {
     let tracker = OperationMetricTracker::build("Fee").await;
 
     let result = get_latest_fee_structure(dynamo_client, || None).await
-        .map(|fees| {
-            let percentage_fee = (wei_amount * fees.percentage_fee_bps as u128) / 10_000;
-            fees.base_fee_wei + percentage_fee
-        });
+        .map(|fees| fees.service_fee(amount, base_fee));
 
     tracker.track(&result, result_to_f64(&result)).await;
 
@@ -145,10 +165,32 @@ mod tests {
         let fiat_amount = 10_000;
         let expected_fee = 10_025;
 
-        let service_fee = calculate_service_fee(&dynamo_client, fiat_amount).await;
+        let service_fee = calculate_service_fee(&dynamo_client, fiat_amount, |wei| wei).await;
         assert_eq!(service_fee.unwrap(), expected_fee, "Incorrect service fee calculation");
     }
 
+    #[test]
+    fn usdc_fees_convert_the_wei_base_fee_into_usdc_units() {
+        let usdc: TokenConfig = serde_json::from_value(serde_json::json!({
+            "symbol": "USDC",
+            "decimals": 6,
+            "contract_address": "0x5fd84259d66Cd46123540766Be93DFE6D43130D7",
+            "price_feed": { "base": "USDC", "sources": ["coinbase"] }
+        })).unwrap();
+        let fees = FeeStructure {
+            base_fee_wei: 50_000_000_000_000, // 0.00005 ETH
+            percentage_fee_bps: 25,
+        };
+
+        // At £2000 per ETH and £0.80 per USDC the base fee is 0.125 USDC
+        let amount = 100_000_000; // 100 USDC
+        let fee = fees.service_fee(amount, |wei| wei_to_token_units(wei, 2000.0, 0.8, &usdc));
+        assert_eq!(fee, 125_000 + 250_000);
+
+        // The same base fee is 10p, whatever the token
+        assert_eq!(wei_to_fiat_minor(fees.base_fee_wei, 2000.0), 10);
+    }
+
     #[tokio::test]
     async fn integration_test() {
         dotenv().ok();
@@ -157,7 +199,7 @@ mod tests {
         let dynamodb_client = get_dynamodb_client_with_assumed_role().await;
         let fiat_amount = 10_000;
 
-        let service_fee = calculate_service_fee(&dynamodb_client, fiat_amount).await;
+        let service_fee = calculate_service_fee(&dynamodb_client, fiat_amount, |wei| wei).await;
 
         match service_fee{
             Ok(_) => {
@@ -0,0 +1,70 @@
+use crate::models::transactions::FinalityStage;
+
+/// When a mined leg counts as confirmed: once its block has reached `stage` and is `depth`
+/// blocks deep, counting its own block. Anything less may yet be reorged out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfirmationPolicy {
+    pub stage: FinalityStage,
+    pub depth: u64,
+}
+
+impl Default for ConfirmationPolicy {
+    fn default() -> Self {
+        Self { stage: FinalityStage::Safe, depth: 1 }
+    }
+}
+
+impl ConfirmationPolicy {
+    pub fn is_met(&self, stage: FinalityStage, confirmations: u64) -> bool {
+        stage >= self.stage && confirmations >= self.depth
+    }
+}
+
+/// The stage of the block numbered `block`, from the chain's `safe` and `finalized` heads.
+/// A head the node does not report counts as not reached.
+pub fn stage_of(block: u64, safe_head: Option<u64>, finalized_head: Option<u64>) -> FinalityStage {
+    if finalized_head.is_some_and(|head| block <= head) {
+        FinalityStage::Finalized
+    } else if safe_head.is_some_and(|head| block <= head) {
+        FinalityStage::Safe
+    } else {
+        FinalityStage::Included
+    }
+}
+
+/// How deep the block numbered `block` is below `latest`, counting itself.
+pub fn confirmations(block: u64, latest: u64) -> u64 {
+    latest.checked_sub(block).map_or(0, |behind| behind + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_follow_the_heads() {
+        assert_eq!(stage_of(100, Some(90), Some(80)), FinalityStage::Included);
+        assert_eq!(stage_of(90, Some(90), Some(80)), FinalityStage::Safe);
+        assert_eq!(stage_of(80, Some(90), Some(80)), FinalityStage::Finalized);
+        assert_eq!(stage_of(80, None, None), FinalityStage::Included);
+    }
+
+    #[test]
+    fn counts_confirmations_from_the_latest_block() {
+        assert_eq!(confirmations(100, 100), 1);
+        assert_eq!(confirmations(100, 111), 12);
+        // A node behind the one that served the receipt
+        assert_eq!(confirmations(100, 99), 0);
+    }
+
+    #[test]
+    fn policy_needs_both_stage_and_depth() {
+        let policy = ConfirmationPolicy { stage: FinalityStage::Safe, depth: 3 };
+
+        assert!(!policy.is_met(FinalityStage::Included, 50));
+        assert!(!policy.is_met(FinalityStage::Safe, 2));
+        assert!(policy.is_met(FinalityStage::Safe, 3));
+        assert!(policy.is_met(FinalityStage::Finalized, 3));
+        assert!(ConfirmationPolicy { stage: FinalityStage::Included, depth: 1 }.is_met(FinalityStage::Included, 1));
+    }
+}
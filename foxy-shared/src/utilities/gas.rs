@@ -3,11 +3,12 @@ use reqwest::{Client, Response};
 use serde_json::json;
 use crate::models::errors::GasEstimateError;
 use crate::models::estimate_flags::EstimateFlags;
-use crate::models::transactions::{GasEstimate, TokenType, TransactionEstimateRequest};
+use crate::models::transactions::{GasEstimate, Network, PriorityFee, PriorityLevel, TokenType, TransactionEstimateRequest};
 use crate::services::cloudwatch_services::OperationMetricTracker;
 use crate::track_rpc_call;
-use crate::utilities::config;
-use crate::utilities::config::get_rpc_url;
+use crate::utilities::erc20;
+use crate::utilities::token_registry::token_registry_for;
+use crate::utilities::config::get_rpc_url_for;
 
 pub async fn estimate_gas(request: &TransactionEstimateRequest) -> Result<GasEstimate, GasEstimateError> {
     fetch_gas_from_source(request, ||None).await
@@ -21,20 +22,52 @@ pub async fn fetch_gas_from_source(
         return Ok(gas_fees);
     }
 
-    //This url is only for L1 pricing, which we can't get from infura
-    let network_url = config::get_ethereum_url();
-
     fetch_gas_from_api(&request.sender_address,
                        &request.recipient_address,
                        request.transaction_value,
                        &request.token_type,
-                       &network_url).await
+                       &request.network,
+                       &request.priority_level).await
+}
+
+pub fn estimate_calldata_length(token_type: TokenType, network: &Network) -> usize {
+    match token_registry_for(network).get(&token_type) {
+        Some(token) if !token.is_native() => 68, // transfer(address,uint256)
+        _ => 0,
+    }
 }
 
-pub fn estimate_calldata_length(token_type: TokenType) -> usize {
-    match token_type {
-        TokenType::ETH => 0,
-        TokenType::USDC => 68,
+/// The call object to estimate a transfer of `amount` base units. ERC-20 transfers are calls
+/// to the token contract carrying no native value.
+pub fn estimate_call(
+    sender: &str,
+    recipient: &str,
+    amount: u128,
+    token_type: &TokenType,
+    network: &Network,
+) -> Result<Value, GasEstimateError> {
+    let token = token_registry_for(network)
+        .resolve(token_type)
+        .map_err(|_| GasEstimateError::UnsupportedToken(token_type.to_string()))?;
+
+    match &token.contract_address {
+        None => Ok(json!({
+            "from": sender,
+            "to": recipient,
+            "value": format!("0x{:x}", amount),
+            "data": "0x"
+        })),
+        Some(contract) => {
+            let data = erc20::transfer_calldata(recipient, amount)
+                .map_err(|e| GasEstimateError::ParseError("Transfer calldata".to_string(), e))?;
+
+            Ok(json!({
+                "from": sender,
+                "to": contract,
+                "value": "0x0",
+                "data": data
+            }))
+        }
     }
 }
 
@@ -43,18 +76,19 @@ pub async fn fetch_gas_from_api(
     recipient: &str,
     amount_in_base_units: Option<u128>,
     token_type: &TokenType,
-    eth_mainnet_url: &str,
+    network: &Network,
+    priority_level: &PriorityLevel,
 ) -> Result<GasEstimate, GasEstimateError> {
     let tracker = OperationMetricTracker::build("Gas").await;
 
-    let optimism_rpc = get_rpc_url();
+    let rpc_url = get_rpc_url_for(network);
     let client = Client::new();
 
     // Parallel fetch for gas price + gas limit (L2)
     let gas_price_res = track_rpc_call!(
             tracker,
             "eth_gasPrice",
-            client.post(&optimism_rpc)
+            client.post(&rpc_url)
                 .json(&json!({
                     "jsonrpc": "2.0",
                     "id": 1,
@@ -67,38 +101,34 @@ pub async fn fetch_gas_from_api(
     let gas_limit_res = track_rpc_call!(
             tracker,
             "eth_estimateGas",
-            client.post(&optimism_rpc)
+            client.post(&rpc_url)
                 .json(&json!({
                     "jsonrpc": "2.0",
                     "id": 1,
                     "method": "eth_estimateGas",
-                    "params": [{
-                        "from": sender,
-                        "to": recipient,
-                        "value": format!("0x{:x}", amount_in_base_units.unwrap()),
-                        "data": "0x"
-                    }]
+                    "params": [estimate_call(sender, recipient, amount_in_base_units.unwrap(), token_type, network)?]
                 }))
                 .send()
         );
 
-    // Separate fetch for L1 gas price (from Ethereum mainnet)
-    let l1_price_res = track_rpc_call!(
-                tracker,
-                "l1_gas_price",
-                client.post(eth_mainnet_url)
-                    .json(&json!({
-                        "jsonrpc": "2.0",
-                        "id": 1,
-                        "method": "eth_gasPrice",
-                        "params": []
-                    }))
-                    .send()
-            );
+    // Recent tips at each level's percentile, plus the next block's base fee
+    let percentiles: Vec<u8> = PriorityLevel::ALL.iter().map(|level| level.fee_percentile()).collect();
+    let fee_history_res = track_rpc_call!(
+            tracker,
+            "eth_feeHistory",
+            client.post(&rpc_url)
+                .json(&json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "eth_feeHistory",
+                    "params": [format!("{:#x}", FEE_HISTORY_BLOCKS), "latest", percentiles]
+                }))
+                .send()
+        );
 
     let gas_price_json = validate_response("Gas Price", gas_price_res).await?;
     let gas_limit_json = validate_response("Gas Limit", gas_limit_res).await?;
-    let l1_price_json = validate_response("L1 Gas Price", l1_price_res).await?;
+    let fee_history_json = validate_response("Fee History", fee_history_res).await?;
 
     let mut estimate_flags = EstimateFlags::empty();
     let (gas_limit, gas_flag) = classify_and_maybe_return("Gas Limit", &gas_limit_json)?;
@@ -107,24 +137,57 @@ pub async fn fetch_gas_from_api(
     let (gas_price, price_flag) = classify_and_maybe_return("Gas Price", &gas_price_json)?;
     estimate_flags |= price_flag;
 
-    let (l1_gas_price, l1_gas_flag) = classify_and_maybe_return("L1 Gas Price", &l1_price_json)?;
-    estimate_flags |= l1_gas_flag;
+    // Rollups also pay for posting calldata to their settlement layer; L1s have no such fee
+    let l1_gas_price = match network.settlement_layer() {
+        Some(l1) => {
+            let l1_price_res = track_rpc_call!(
+                tracker,
+                "l1_gas_price",
+                client.post(get_rpc_url_for(&l1))
+                    .json(&json!({
+                        "jsonrpc": "2.0",
+                        "id": 1,
+                        "method": "eth_gasPrice",
+                        "params": []
+                    }))
+                    .send()
+            );
+            let l1_price_json = validate_response("L1 Gas Price", l1_price_res).await?;
+
+            let (l1_gas_price, l1_gas_flag) = classify_and_maybe_return("L1 Gas Price", &l1_price_json)?;
+            estimate_flags |= l1_gas_flag;
+            l1_gas_price
+        }
+        None => 0,
+    };
 
     // Apply fixed scalar in basis points
     const L1_SCALAR_BPS: u64 = 12000; // 1.2x
-    let calldata_len = estimate_calldata_length(token_type.clone());
+    let calldata_len = estimate_calldata_length(token_type.clone(), network);
     let l1_gas_used = 4 * calldata_len as u64;
     let l1_fee = ((l1_gas_used as u128 * l1_gas_price as u128) * L1_SCALAR_BPS as u128) / 10_000;
 
-    // Final fee summary -
-    let priority_fee = 1_000u64; // 1000 wei (0.000001 gwei) is a good floor
-    let max_fee_per_gas = gas_price + 10 * priority_fee; // generous buffer
+    let priority_fees = match fee_history_json.get("result") {
+        Some(history) => priority_fees_from_history(history, gas_limit, network)?,
+        None => {
+            // Not every RPC serves fee history; fall back to tipping the floor on top of the gas price
+            log::warn!("Fee history unavailable, pricing from eth_gasPrice: {}", fee_history_json);
+            PriorityLevel::ALL
+                .iter()
+                .map(|level| priority_fee(level, gas_price, MIN_PRIORITY_FEE, gas_limit, network))
+                .collect()
+        }
+    };
 
-    let network_fee = (gas_limit as u128) * (max_fee_per_gas as u128);
+    let selected = priority_fees
+        .iter()
+        .find(|fee| &fee.priority_level == priority_level)
+        .cloned()
+        .ok_or_else(|| GasEstimateError::IncompleteResponse(format!("No pricing for {:?}", priority_level)))?;
 
     tracker.track::<(), ()>(
         &Ok(()),
-        Some(network_fee as f64),
+        Some(selected.network_fee as f64),
     ).await;
 
     tracker.emit(
@@ -136,7 +199,7 @@ pub async fn fetch_gas_from_api(
 
     tracker.emit(
         "GasValue",
-        max_fee_per_gas as f64,
+        selected.max_fee_per_gas as f64,
         "None",
         &[("Type", "max_fee_per_gas")],
     ).await;
@@ -146,12 +209,68 @@ pub async fn fetch_gas_from_api(
         gas_limit,
         gas_price,
         l1_fee,
-        max_fee_per_gas,
-        max_priority_fee_per_gas: 150000,
-        network_fee,
+        max_fee_per_gas: selected.max_fee_per_gas,
+        max_priority_fee_per_gas: selected.max_priority_fee_per_gas,
+        network_fee: selected.network_fee,
+        priority_fees,
     })
 }
 
+/// Blocks of history sampled for tips; enough to smooth over a single busy block.
+const FEE_HISTORY_BLOCKS: u64 = 20;
+
+/// 1000 wei (0.000001 gwei) is a good floor, quiet chains often report zero tips.
+const MIN_PRIORITY_FEE: u64 = 1_000;
+
+/// Prices every `PriorityLevel` from an `eth_feeHistory` result requested with their percentiles,
+/// in `PriorityLevel::ALL` order. Each tip is the median of its percentile across the sampled blocks.
+pub fn priority_fees_from_history(
+    history: &Value,
+    gas_limit: u64,
+    network: &Network,
+) -> Result<Vec<PriorityFee>, GasEstimateError> {
+    // The last entry is the base fee of the block after the newest sampled one
+    let next_base_fee = history.get("baseFeePerGas")
+        .and_then(|fees| fees.as_array())
+        .and_then(|fees| fees.last())
+        .and_then(|fee| fee.as_str())
+        .and_then(parse_hex)
+        .ok_or_else(|| GasEstimateError::IncompleteResponse("Fee History baseFeePerGas".to_string()))?;
+
+    let rewards = history.get("reward")
+        .and_then(|r| r.as_array())
+        .ok_or_else(|| GasEstimateError::IncompleteResponse("Fee History reward".to_string()))?;
+
+    let fees = PriorityLevel::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, level)| {
+            let mut tips: Vec<u64> = rewards.iter()
+                .filter_map(|block| block.get(i)?.as_str().and_then(parse_hex))
+                .collect();
+            tips.sort_unstable();
+            let median = tips.get(tips.len() / 2).copied().unwrap_or(0);
+
+            priority_fee(level, next_base_fee, median.max(MIN_PRIORITY_FEE), gas_limit, network)
+        })
+        .collect();
+
+    Ok(fees)
+}
+
+/// Allows the base fee to double before the transaction is priced out, the usual EIP-1559 headroom.
+fn priority_fee(level: &PriorityLevel, base_fee: u64, tip: u64, gas_limit: u64, network: &Network) -> PriorityFee {
+    let max_fee_per_gas = base_fee.saturating_mul(2).saturating_add(tip);
+
+    PriorityFee {
+        priority_level: level.clone(),
+        max_fee_per_gas,
+        max_priority_fee_per_gas: tip,
+        network_fee: (gas_limit as u128) * (max_fee_per_gas as u128),
+        expected_inclusion_secs: level.expected_blocks() * network.block_time_secs(),
+    }
+}
+
 pub fn classify_and_maybe_return(
     label: &str,
     json: &serde_json::Value,
@@ -268,10 +387,14 @@ pub async fn validate_response(
 fn parse_json_hex(json: &serde_json::Value, key: &str) -> Result<u64, GasEstimateError> {
     json.get(key)
         .and_then(|v| v.as_str())
-        .and_then(|hex| u64::from_str_radix(&hex[2..], 16).ok())
+        .and_then(parse_hex)
         .ok_or_else(|| GasEstimateError::IncompleteResponse(format!("Missing or invalid {} field", key)))
 }
 
+fn parse_hex(hex: &str) -> Option<u64> {
+    u64::from_str_radix(hex.trim_start_matches("0x"), 16).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -279,27 +402,87 @@ mod tests {
 
     #[test]
     fn test_calldata_length_eth() {
-        let len = estimate_calldata_length(TokenType::ETH);
+        let len = estimate_calldata_length(TokenType::ETH, &Network::OptimismMainnet);
         assert_eq!(len, 0, "ETH transfers should have 0 calldata bytes");
     }
 
     #[test]
     fn test_calldata_length_usdc() {
-        let len = estimate_calldata_length(TokenType::USDC);
+        let len = estimate_calldata_length(TokenType::USDC, &Network::OptimismMainnet);
         assert_eq!(len, 68, "USDC (ERC-20) transfers should have 68 calldata bytes");
     }
 
+    #[test]
+    fn test_estimate_call_eth() {
+        let call = estimate_call("0xC4027B0df7B2d1fAf281169D78E252f8D86E4cdC",
+                                 "0x1aB7Bc9CA7586fa0D9c6293A27d5c001622E08C7",
+                                 255,
+                                 &TokenType::ETH,
+                                 &Network::OptimismMainnet).unwrap();
+
+        assert_eq!(call["to"], "0x1aB7Bc9CA7586fa0D9c6293A27d5c001622E08C7");
+        assert_eq!(call["value"], "0xff");
+        assert_eq!(call["data"], "0x");
+    }
+
+    #[test]
+    fn test_estimate_call_usdc() {
+        let call = estimate_call("0xC4027B0df7B2d1fAf281169D78E252f8D86E4cdC",
+                                 "0x1aB7Bc9CA7586fa0D9c6293A27d5c001622E08C7",
+                                 1_000_000,
+                                 &TokenType::USDC,
+                                 &Network::OptimismMainnet).unwrap();
+
+        assert_eq!(call["to"], *token_registry_for(&Network::OptimismMainnet).resolve(&TokenType::USDC).unwrap().contract_address.as_ref().unwrap());
+        assert_eq!(call["value"], "0x0");
+
+        let data = call["data"].as_str().unwrap();
+        assert_eq!((data.len() - 2) / 2, estimate_calldata_length(TokenType::USDC, &Network::OptimismMainnet), "Calldata should match the L1 fee estimate");
+    }
+
+    #[test]
+    fn test_priority_fees_from_fee_history() {
+        let history = json!({
+            "baseFeePerGas": ["0x64", "0x64", "0xc8"],
+            "reward": [
+                ["0x0", "0x1388", "0x2710"],
+                ["0x7d0", "0xfa0", "0x4e20"]
+            ]
+        });
+
+        let fees = priority_fees_from_history(&history, 21_000, &Network::OptimismMainnet).unwrap();
+        let tips: Vec<u64> = fees.iter().map(|f| f.max_priority_fee_per_gas).collect();
+
+        assert_eq!(fees.iter().map(|f| f.priority_level.clone()).collect::<Vec<_>>(), PriorityLevel::ALL.to_vec());
+        assert_eq!(tips, vec![2_000, 5_000, 20_000], "Tips should be the median of each percentile");
+        assert_eq!(fees[0].max_fee_per_gas, 2 * 200 + 2_000, "Max fee should cover a doubled next base fee");
+        assert_eq!(fees[2].network_fee, 21_000 * (2 * 200 + 20_000));
+        assert_eq!(fees[0].expected_inclusion_secs, 10);
+        assert_eq!(fees[2].expected_inclusion_secs, 2);
+    }
+
+    #[test]
+    fn test_priority_fees_floor_zero_tips() {
+        let history = json!({ "baseFeePerGas": ["0x1"], "reward": [["0x0", "0x0", "0x0"]] });
+
+        let fees = priority_fees_from_history(&history, 21_000, &Network::EthereumMainnet).unwrap();
+
+        assert!(fees.iter().all(|f| f.max_priority_fee_per_gas == MIN_PRIORITY_FEE));
+        assert_eq!(fees[0].expected_inclusion_secs, 60);
+        assert!(priority_fees_from_history(&json!({}), 21_000, &Network::EthereumMainnet).is_err());
+    }
+
     #[tokio::test]
     async fn test_transaction_estimate() {
         dotenv::dotenv().ok(); // Load .env with RPC URLs
         let _ = env_logger::builder().is_test(true).try_init();
-        let ethereum_url = config::get_ethereum_url();
 
         let result = fetch_gas_from_api("0xC4027B0df7B2d1fAf281169D78E252f8D86E4cdC",
                                         "0x1aB7Bc9CA7586fa0D9c6293A27d5c001622E08C7",
                                         Some(1_000_000_000_000_000_000_000_000_000u128),
                                         &TokenType::ETH,
-                                        &ethereum_url).await;
+                                        &Network::OptimismMainnet,
+                                        &PriorityLevel::Standard).await;
 
         assert!(result.is_ok(), "Gas estimation failed: {:?}", result.err());
 
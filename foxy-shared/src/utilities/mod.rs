@@ -16,3 +16,11 @@ pub mod wallet;
 pub mod requests;
 pub mod nonce_manager;
 pub mod parsers;
+pub mod erc20;
+pub mod token_registry;
+pub mod eip681;
+pub mod quote;
+pub mod signed_tx;
+pub mod replacement;
+pub mod revert;
+pub mod finality;
@@ -2,7 +2,8 @@ use ethers_core::types::Address;
 use reqwest::Client;
 use serde_json::json;
 use std::str::FromStr;
-use crate::utilities::config::get_rpc_url;
+use crate::utilities::config::{get_network, get_rpc_url_for};
+use crate::models::transactions::Network;
 use once_cell::sync::Lazy;
 use crate::models::errors::NonceError;
 
@@ -15,21 +16,36 @@ pub struct NonceManager {
 
 impl NonceManager {
     pub fn new() -> Result<Self, NonceError> {
-        let rpc_url = get_rpc_url();
+        Self::for_network(&get_network())
+    }
+
+    /// Nonces are per chain, so each network needs its own manager.
+    pub fn for_network(network: &Network) -> Result<Self, NonceError> {
+        let rpc_url = get_rpc_url_for(network);
         Ok(Self {
             rpc_url,
             client: SHARED_CLIENT.clone(),
         })
     }
 
+    /// The next nonce, counting transactions still in the node's mempool.
     pub async fn get_nonce(&self, address: &str) -> Result<u64, NonceError> {
+        self.transaction_count(address, "pending").await
+    }
+
+    /// The number of the wallet's transactions that have been mined.
+    pub async fn get_mined_nonce(&self, address: &str) -> Result<u64, NonceError> {
+        self.transaction_count(address, "latest").await
+    }
+
+    async fn transaction_count(&self, address: &str, block: &str) -> Result<u64, NonceError> {
         let parsed_address = Address::from_str(address)
             .map_err(|_| NonceError::InvalidAddress(address.to_string()))?;
 
         let payload = json!({
             "jsonrpc": "2.0",
             "method": "eth_getTransactionCount",
-            "params": [format!("0x{:x}", parsed_address), "pending"],
+            "params": [format!("0x{:x}", parsed_address), block],
             "id": 1
         });
 
@@ -0,0 +1,213 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use uuid::Uuid;
+use crate::models::errors::TransactionError;
+use crate::models::transactions::{GasPricing, Network, PriorityLevel, SplitShare, TokenType, TransactionRequest};
+
+/// The terms `/transactions/estimate` priced, signed so that `/transactions/initiate` can hold
+/// the client to them. Each quote may start one bundle.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Quote {
+    pub quote_id: String,
+    pub user_id: String,
+    pub sender_address: String,
+    pub recipient_address: String,
+    pub token_type: TokenType,
+    pub network: Network,
+    pub transaction_value: u128,
+    pub fiat_value: u64,
+    pub fiat_currency: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub splits: Vec<SplitShare>, // further recipients, each sent their own leg
+
+    // What the client may not change
+    pub exchange_rate: f64,
+    pub service_fee: u128, // the fee leg's value
+    pub service_fee_minor: u64,
+    pub priority_level: PriorityLevel,
+    pub gas_pricing: GasPricing,
+
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Quote {
+    pub fn new_id() -> String {
+        Uuid::new_v4().to_string()
+    }
+
+    /// `<payload>.<signature>`, both base64url: the quote as JSON and its HMAC-SHA256 under `key`.
+    pub fn sign(&self, key: &[u8]) -> Result<String, TransactionError> {
+        let payload = serde_json::to_vec(self)
+            .map_err(|e| TransactionError::InvalidQuote(format!("Cannot encode quote: {}", e)))?;
+        let payload = URL_SAFE_NO_PAD.encode(payload);
+        let mut mac = mac(key)?;
+        mac.update(payload.as_bytes());
+        let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+
+        Ok(format!("{}.{}", payload, signature))
+    }
+
+    /// Checks the signature before trusting any field, then the expiry.
+    pub fn verify(token: &str, key: &[u8], now: DateTime<Utc>) -> Result<Self, TransactionError> {
+        let invalid = || TransactionError::InvalidQuote("Malformed quote".to_string());
+
+        let (payload, signature) = token.split_once('.').ok_or_else(invalid)?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid())?;
+        let mut mac = mac(key)?;
+        mac.update(payload.as_bytes());
+        mac.verify_slice(&signature)
+            .map_err(|_| TransactionError::InvalidQuote("Signature does not match".to_string()))?;
+
+        let payload = URL_SAFE_NO_PAD.decode(payload).map_err(|_| invalid())?;
+        let quote: Quote = serde_json::from_slice(&payload).map_err(|_| invalid())?;
+        if quote.expires_at <= now {
+            return Err(TransactionError::InvalidQuote(format!("Quote {} expired at {}", quote.quote_id, quote.expires_at)));
+        }
+
+        Ok(quote)
+    }
+
+    /// Holds `request` to this quote: the transfer must be the one quoted, and the priced
+    /// fields are replaced with the quoted ones whatever the client sent. Split shares are
+    /// only ever read from the quote.
+    pub fn bind(&self, user_id: &str, mut request: TransactionRequest) -> Result<TransactionRequest, TransactionError> {
+        let mismatch = |field: &str| TransactionError::InvalidQuote(format!("Request {} differs from the quote", field));
+
+        if self.user_id != user_id {
+            return Err(TransactionError::Unauthorized);
+        }
+        if !self.sender_address.eq_ignore_ascii_case(&request.sender_address) {
+            return Err(mismatch("sender_address"));
+        }
+        if !self.recipient_address.eq_ignore_ascii_case(&request.recipient_address) {
+            return Err(mismatch("recipient_address"));
+        }
+        if self.token_type != request.token_type {
+            return Err(mismatch("token_type"));
+        }
+        if self.network != request.network {
+            return Err(mismatch("network"));
+        }
+        if self.transaction_value != request.transaction_value {
+            return Err(mismatch("transaction_value"));
+        }
+        if self.fiat_value != request.fiat_value || self.fiat_currency != request.fiat_currency_code {
+            return Err(mismatch("fiat value"));
+        }
+
+        request.exchange_rate = self.exchange_rate;
+        request.service_fee = self.service_fee;
+        request.service_fee_minor = self.service_fee_minor;
+        request.priority_level = self.priority_level.clone();
+        request.gas_pricing = Some(self.gas_pricing.clone());
+        Ok(request)
+    }
+}
+
+fn mac(key: &[u8]) -> Result<Hmac<Sha256>, TransactionError> {
+    Hmac::<Sha256>::new_from_slice(key)
+        .map_err(|e| TransactionError::InvalidQuote(format!("Cannot key quote signature: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user_device::UserDevice;
+
+    const KEY: &[u8] = b"test-quote-key";
+
+    fn quote(expires_at: DateTime<Utc>) -> Quote {
+        Quote {
+            quote_id: Quote::new_id(),
+            user_id: "user".into(),
+            sender_address: "0xe006487c4cec454574b6c9a9f79ff8a5dee636a0".into(),
+            recipient_address: "0xa826d3484625b29dfcbdaee6ca636a1acb439bf8".into(),
+            token_type: TokenType::ETH,
+            network: Network::OptimismSepolia,
+            transaction_value: 1_000_000_000_000_000,
+            fiat_value: 5000,
+            fiat_currency: "GBP".into(),
+            splits: Vec::new(),
+            exchange_rate: 2300.0,
+            service_fee: 10_000_000_000_000,
+            service_fee_minor: 100,
+            priority_level: PriorityLevel::Fast,
+            gas_pricing: GasPricing {
+                estimated_gas: "21000".into(),
+                gas_price: "1000000000".into(),
+                max_fee_per_gas: "2000000000".into(),
+                max_priority_fee_per_gas: "1000".into(),
+            },
+            expires_at,
+        }
+    }
+
+    fn request(quote: &Quote) -> TransactionRequest {
+        TransactionRequest {
+            sender_address: quote.sender_address.to_uppercase().replace("0X", "0x"),
+            recipient_address: quote.recipient_address.clone(),
+            fiat_value: quote.fiat_value,
+            fiat_currency_code: quote.fiat_currency.clone(),
+            transaction_value: quote.transaction_value,
+            token_type: quote.token_type.clone(),
+            network: quote.network.clone(),
+            priority_level: PriorityLevel::Standard,
+            message: None,
+            gas_pricing: None,
+            gas_estimate: None,
+            exchange_rate: 1.0,
+            service_fee: 0,
+            service_fee_minor: 0,
+            user_device: UserDevice::new("device".into(), "token".into(), "Android".into(), "0.1.0".into()),
+            quote: None,
+        }
+    }
+
+    #[test]
+    fn signed_quote_round_trips() {
+        let now = Utc::now();
+        let quote = quote(now + chrono::Duration::seconds(60));
+
+        let token = quote.sign(KEY).unwrap();
+        assert_eq!(Quote::verify(&token, KEY, now).unwrap(), quote);
+    }
+
+    #[test]
+    fn rejects_tampered_forged_and_expired_quotes() {
+        let now = Utc::now();
+        let quote = quote(now + chrono::Duration::seconds(60));
+        let token = quote.sign(KEY).unwrap();
+
+        let mut cheaper = quote.clone();
+        cheaper.service_fee = 0;
+        let (_, signature) = token.split_once('.').unwrap();
+        let (forged_payload, _) = cheaper.sign(KEY).unwrap().split_once('.').map(|(p, s)| (p.to_string(), s.to_string())).unwrap();
+        let tampered = format!("{}.{}", forged_payload, signature);
+
+        assert!(matches!(Quote::verify(&tampered, KEY, now), Err(TransactionError::InvalidQuote(_))));
+        assert!(Quote::verify(&cheaper.sign(b"someone-elses-key").unwrap(), KEY, now).is_err());
+        assert!(Quote::verify(&token, KEY, now + chrono::Duration::seconds(61)).is_err());
+        assert!(Quote::verify("not-a-quote", KEY, now).is_err());
+    }
+
+    #[test]
+    fn bind_replaces_client_pricing_with_the_quote() {
+        let quote = quote(Utc::now());
+
+        let bound = quote.bind("user", request(&quote)).unwrap();
+        assert_eq!(bound.service_fee, quote.service_fee);
+        assert_eq!(bound.service_fee_minor, quote.service_fee_minor);
+        assert_eq!(bound.exchange_rate, quote.exchange_rate);
+        assert_eq!(bound.priority_level, PriorityLevel::Fast);
+        assert_eq!(bound.gas_pricing.unwrap().max_fee_per_gas, "2000000000");
+
+        let mut larger = request(&quote);
+        larger.transaction_value += 1;
+        assert!(matches!(quote.bind("user", larger), Err(TransactionError::InvalidQuote(_))));
+        assert!(matches!(quote.bind("someone-else", request(&quote)), Err(TransactionError::Unauthorized)));
+    }
+}
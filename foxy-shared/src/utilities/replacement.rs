@@ -0,0 +1,118 @@
+use crate::models::errors::TransactionError;
+use crate::models::transactions::{Transaction, TransactionBundle, TransactionLeg, TransactionStatus};
+
+/// Nodes drop a same-nonce replacement unless both fees rise by at least this much.
+pub const PRICE_BUMP_PERCENT: u64 = 10;
+
+fn bumped(fee: u64) -> u64 {
+    fee.saturating_mul(100 + PRICE_BUMP_PERCENT).div_ceil(100)
+}
+
+/// The lowest `(max_fee_per_gas, max_priority_fee_per_gas)` a node will accept in place of `tx`.
+pub fn minimum_replacement_fees(tx: &Transaction) -> (u64, u64) {
+    (
+        bumped(tx.max_fee_per_gas.unwrap_or_default()),
+        bumped(tx.max_priority_fee_per_gas.unwrap_or_default()),
+    )
+}
+
+/// The leg that is on the chain but not yet mined. Legs go out one at a time, so there is
+/// at most one. A leg already in a block is waiting on finality, which fees cannot speed up.
+pub fn pending_leg(bundle: &TransactionBundle) -> Option<TransactionLeg> {
+    bundle.legs().find(|leg| {
+        let tx = bundle.leg(*leg);
+        tx.status == TransactionStatus::Pending && tx.block_hash.is_none()
+    })
+}
+
+/// `tx` repriced to outbid the market, at no less than the bump nodes require. Fails when `tx`
+/// already pays the market's max fee: it is waiting on the chain, not on its price.
+pub fn reprice(
+    tx: &Transaction,
+    market_max_fee_per_gas: u64,
+    market_max_priority_fee_per_gas: u64,
+) -> Result<Transaction, TransactionError> {
+    let current = tx.max_fee_per_gas.unwrap_or_default();
+    if current >= market_max_fee_per_gas {
+        return Err(TransactionError::NotReplaceable(format!(
+            "max fee {} already meets the market's {}", current, market_max_fee_per_gas
+        )));
+    }
+
+    let (min_max_fee, min_priority_fee) = minimum_replacement_fees(tx);
+    let priority_fee = market_max_priority_fee_per_gas.max(min_priority_fee);
+    let max_fee = market_max_fee_per_gas.max(min_max_fee).max(priority_fee);
+
+    let mut replacement = tx.clone();
+    replacement.max_fee_per_gas = Some(max_fee);
+    replacement.max_priority_fee_per_gas = Some(priority_fee);
+    Ok(replacement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::transactions::{BundleMetadata, TokenType};
+    use crate::utilities::config;
+
+    fn priced(max_fee: u64, priority_fee: u64) -> Transaction {
+        config::init();
+        let mut tx = Transaction::new("user".into(), "from".into(), "to".into(), 1000, TokenType::ETH, 100, "GBP".into(), 7);
+        tx.max_fee_per_gas = Some(max_fee);
+        tx.max_priority_fee_per_gas = Some(priority_fee);
+        tx
+    }
+
+    #[test]
+    fn minimum_bump_rounds_up() {
+        assert_eq!(minimum_replacement_fees(&priced(1_000, 15)), (1_100, 17));
+        assert_eq!(minimum_replacement_fees(&priced(u64::MAX, 0)), (u64::MAX / 100 + 1, 0));
+    }
+
+    #[test]
+    fn reprices_to_the_market() {
+        let tx = priced(1_000, 100);
+
+        let replacement = reprice(&tx, 2_000, 500).unwrap();
+        assert_eq!(replacement.max_fee_per_gas, Some(2_000));
+        assert_eq!(replacement.max_priority_fee_per_gas, Some(500));
+        assert_eq!(replacement.nonce, tx.nonce);
+    }
+
+    #[test]
+    fn never_bids_less_than_the_required_bump() {
+        // The market has barely moved and tips have fallen
+        let replacement = reprice(&priced(1_000, 100), 1_050, 50).unwrap();
+        assert_eq!(replacement.max_fee_per_gas, Some(1_100));
+        assert_eq!(replacement.max_priority_fee_per_gas, Some(110));
+    }
+
+    #[test]
+    fn refuses_legs_already_priced_at_the_market() {
+        assert!(matches!(reprice(&priced(2_000, 100), 2_000, 500), Err(TransactionError::NotReplaceable(_))));
+    }
+
+    #[test]
+    fn finds_the_pending_leg() {
+        let main_tx = priced(1_000, 100);
+        let fee_tx = Transaction::new("user".into(), "from".into(), "foxy".into(), 10, TokenType::ETH, 0, "GBP".into(), 8);
+        let mut bundle = TransactionBundle::new("user".into(), fee_tx, main_tx, Some(BundleMetadata::default()));
+        assert_eq!(pending_leg(&bundle), None);
+
+        bundle.main_tx.status = TransactionStatus::Pending;
+        assert_eq!(pending_leg(&bundle), Some(TransactionLeg::Main));
+
+        bundle.main_tx.status = TransactionStatus::Confirmed;
+        bundle.fee_tx.status = TransactionStatus::Pending;
+        assert_eq!(pending_leg(&bundle), Some(TransactionLeg::Fee));
+
+        let mut split_tx = priced(1_000, 100);
+        split_tx.status = TransactionStatus::Pending;
+        bundle.fee_tx.status = TransactionStatus::Signed;
+        bundle.split_txs.push(split_tx);
+        assert_eq!(pending_leg(&bundle), Some(TransactionLeg::Split(0)));
+
+        bundle.split_txs[0].block_hash = Some("0xaa".into());
+        assert_eq!(pending_leg(&bundle), None);
+    }
+}
@@ -13,3 +13,11 @@ pub fn extract_bearer_token(event: &Request) -> Option<&str> {
         .and_then(|header| header.to_str().ok())
         .and_then(|auth_header| auth_header.strip_prefix("Bearer "))
 }
+
+/// Extracts the client's Idempotency-Key header, used to make retried writes no-ops.
+pub fn extract_idempotency_key(event: &Request) -> Option<&str> {
+    event.headers()
+        .get("Idempotency-Key")
+        .and_then(|header| header.to_str().ok())
+        .filter(|key| !key.is_empty())
+}
@@ -0,0 +1,83 @@
+use ethers_core::types::U256;
+
+/// `Error(string)`, raised by `require` and `revert` with a message.
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// `Panic(uint256)`, raised by failed asserts, overflows and the like.
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// A readable reason from the data a call reverted with. Custom errors and bare reverts
+/// carry nothing we can read, so give `None`.
+pub fn decode_revert_reason(data: &[u8]) -> Option<String> {
+    let (selector, payload) = data.split_first_chunk::<4>()?;
+
+    match *selector {
+        ERROR_SELECTOR => decode_string(payload),
+        PANIC_SELECTOR => {
+            let code = U256::from_big_endian(payload.get(..32)?);
+            Some(format!("panic {:#04x}", code.low_u64()))
+        }
+        _ => None,
+    }
+}
+
+/// An ABI-encoded `string`: an offset to its length, then the UTF-8 bytes.
+fn decode_string(payload: &[u8]) -> Option<String> {
+    let offset = small_word(payload.get(..32)?)?;
+    let len = small_word(payload.get(offset..offset.checked_add(32)?)?)?;
+    let start = offset + 32;
+    let bytes = payload.get(start..start.checked_add(len)?)?;
+
+    String::from_utf8(bytes.to_vec()).ok()
+}
+
+/// A word holding a length or offset. Anything past `u32` cannot fit in revert data.
+fn small_word(word: &[u8]) -> Option<usize> {
+    let value = U256::from_big_endian(word);
+    (value <= U256::from(u32::MAX)).then(|| value.as_usize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(value: u64) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        U256::from(value).to_big_endian(&mut bytes);
+        bytes
+    }
+
+    fn error_data(message: &str) -> Vec<u8> {
+        let mut data = ERROR_SELECTOR.to_vec();
+        data.extend_from_slice(&word(32));
+        data.extend_from_slice(&word(message.len() as u64));
+        data.extend_from_slice(message.as_bytes());
+        data.resize(4 + 64 + message.len().next_multiple_of(32), 0);
+        data
+    }
+
+    #[test]
+    fn decodes_error_strings() {
+        let data = error_data("ERC20: transfer amount exceeds balance");
+        assert_eq!(decode_revert_reason(&data).as_deref(), Some("ERC20: transfer amount exceeds balance"));
+    }
+
+    #[test]
+    fn decodes_panic_codes() {
+        let mut data = PANIC_SELECTOR.to_vec();
+        data.extend_from_slice(&word(0x11));
+        assert_eq!(decode_revert_reason(&data).as_deref(), Some("panic 0x11"));
+    }
+
+    #[test]
+    fn gives_up_on_what_it_cannot_read() {
+        // A bare revert, a custom error and a truncated string
+        assert_eq!(decode_revert_reason(&[]), None);
+        assert_eq!(decode_revert_reason(&[0xe4, 0x50, 0xd3, 0x8c, 0x00]), None);
+        assert_eq!(decode_revert_reason(&error_data("too short")[..40]), None);
+
+        let mut huge_offset = ERROR_SELECTOR.to_vec();
+        huge_offset.extend_from_slice(&[0xff; 32]);
+        assert_eq!(decode_revert_reason(&huge_offset), None);
+    }
+}
@@ -0,0 +1,272 @@
+use std::str::FromStr;
+use ethers_core::types::transaction::eip1559::Eip1559TransactionRequest;
+use ethers_core::types::transaction::eip2718::TypedTransaction;
+use ethers_core::types::{Address, NameOrAddress, U256};
+use ethers_core::utils::rlp::Rlp;
+use crate::models::errors::TransactionError;
+use crate::models::transactions::{TransactionLeg, UnsignedTransaction};
+
+/// EIP-2718 type byte of an EIP-1559 transaction.
+const EIP1559_TX_TYPE: u8 = 0x02;
+
+/// Decodes a signed EIP-1559 transaction and recovers the wallet that signed it.
+pub fn decode(leg: TransactionLeg, signed_tx: &str) -> Result<(Eip1559TransactionRequest, Address), TransactionError> {
+    let malformed = |msg: String| TransactionError::MalformedSignedTransaction(format!("{} transaction: {}", leg, msg));
+
+    let raw = hex::decode(signed_tx.trim_start_matches("0x")).map_err(|e| malformed(format!("not hex: {}", e)))?;
+    if raw.first() != Some(&EIP1559_TX_TYPE) {
+        return Err(malformed("not an EIP-1559 transaction".to_string()));
+    }
+
+    let (tx, signature) = TypedTransaction::decode_signed(&Rlp::new(&raw))
+        .map_err(|e| malformed(format!("cannot decode: {}", e)))?;
+    let signer = signature.recover(tx.sighash())
+        .map_err(|e| malformed(format!("cannot recover signer: {}", e)))?;
+
+    match tx {
+        TypedTransaction::Eip1559(tx) => Ok((tx, signer)),
+        _ => Err(malformed("not an EIP-1559 transaction".to_string())),
+    }
+}
+
+/// Checks that `signed_tx` is `intent`, exactly as we issued it, signed by `sender`. The
+/// broadcaster would otherwise be the first to look inside, after the bundle was committed.
+pub fn verify(
+    leg: TransactionLeg,
+    signed_tx: &str,
+    intent: &UnsignedTransaction,
+    sender: &str,
+) -> Result<(), TransactionError> {
+    let tx = verify_transfer(leg, signed_tx, intent, sender)?;
+
+    let fees = [
+        ("max_fee_per_gas", &intent.max_fee_per_gas, tx.max_fee_per_gas),
+        ("max_priority_fee_per_gas", &intent.max_priority_fee_per_gas, tx.max_priority_fee_per_gas),
+    ];
+    for (field, expected, signed) in fees {
+        let signed = signed.unwrap_or_default();
+        if U256::from_dec_str(expected).ok() != Some(signed) {
+            return Err(mismatch(leg, field, expected.clone(), signed.to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that `signed_tx` replaces `intent`: the same transfer and nonce, signed by `sender`,
+/// paying at least the fees in `intent`. Returns the fees it pays, which the wallet may have
+/// raised further.
+pub fn verify_replacement(
+    leg: TransactionLeg,
+    signed_tx: &str,
+    intent: &UnsignedTransaction,
+    sender: &str,
+) -> Result<(u64, u64), TransactionError> {
+    let tx = verify_transfer(leg, signed_tx, intent, sender)?;
+
+    let fees = [
+        ("max_fee_per_gas", &intent.max_fee_per_gas, tx.max_fee_per_gas),
+        ("max_priority_fee_per_gas", &intent.max_priority_fee_per_gas, tx.max_priority_fee_per_gas),
+    ];
+    let mut paid = [0u64; 2];
+    for (i, (field, minimum, signed)) in fees.into_iter().enumerate() {
+        let signed = signed.unwrap_or_default();
+        let enough = U256::from_dec_str(minimum).map(|minimum| signed >= minimum).unwrap_or(false);
+        if !enough || signed > U256::from(u64::MAX) {
+            return Err(mismatch(leg, field, format!("at least {}", minimum), signed.to_string()));
+        }
+        paid[i] = signed.as_u64();
+    }
+
+    Ok((paid[0], paid[1]))
+}
+
+/// Everything but the fees: signer, recipient, calldata, chain, value, nonce and gas limit.
+fn verify_transfer(
+    leg: TransactionLeg,
+    signed_tx: &str,
+    intent: &UnsignedTransaction,
+    sender: &str,
+) -> Result<Eip1559TransactionRequest, TransactionError> {
+    let (tx, signer) = decode(leg, signed_tx)?;
+
+    let expected_signer = parse_address(sender)?;
+    if signer != expected_signer {
+        return Err(TransactionError::SignerMismatch {
+            leg,
+            expected: format!("{:?}", expected_signer),
+            signer: format!("{:?}", signer),
+        });
+    }
+
+    let expected_to = parse_address(&intent.to)?;
+    match &tx.to {
+        Some(NameOrAddress::Address(to)) if *to == expected_to => {}
+        other => return Err(mismatch(leg, "to", format!("{:?}", expected_to), format!("{:?}", other))),
+    }
+
+    let expected_data = hex::decode(intent.data.trim_start_matches("0x"))
+        .map_err(|_| TransactionError::InvalidRequest)?;
+    let signed_data = tx.data.as_ref().map(|d| d.to_vec()).unwrap_or_default();
+    if signed_data != expected_data {
+        return Err(mismatch(leg, "data", intent.data.clone(), format!("0x{}", hex::encode(signed_data))));
+    }
+
+    let chain_id = tx.chain_id.map(|c| c.as_u64()).unwrap_or_default();
+    if chain_id.to_string() != intent.chain_id {
+        return Err(mismatch(leg, "chain_id", intent.chain_id.clone(), chain_id.to_string()));
+    }
+
+    let quantities = [
+        ("value", &intent.value, tx.value),
+        ("nonce", &intent.nonce, tx.nonce),
+        ("gas_limit", &intent.gas_limit, tx.gas),
+    ];
+    for (field, expected, signed) in quantities {
+        let signed = signed.unwrap_or_default();
+        if U256::from_dec_str(expected).ok() != Some(signed) {
+            return Err(mismatch(leg, field, expected.clone(), signed.to_string()));
+        }
+    }
+
+    Ok(tx)
+}
+
+fn mismatch(leg: TransactionLeg, field: &str, expected: String, signed: String) -> TransactionError {
+    TransactionError::SignedFieldMismatch {
+        leg,
+        field: field.to_string(),
+        expected,
+        signed,
+    }
+}
+
+fn parse_address(address: &str) -> Result<Address, TransactionError> {
+    Address::from_str(address).map_err(|_| TransactionError::InvalidAddress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers_core::types::Bytes;
+    use ethers_signers::{LocalWallet, Signer};
+    use crate::models::transactions::{GasPricing, TokenType, Transaction};
+    use crate::utilities::config;
+
+    const SENDER_KEY: &str = "f47e2803d900f88dd8bdb7ddf538047fae4728814f556e3b9906fe8e0d71080f";
+    const SENDER: &str = "0xe006487c4cec454574b6c9a9f79ff8a5dee636a0";
+    const RECIPIENT: &str = "0xa826d3484625b29dfcbdaee6ca636a1acb439bf8";
+
+    fn intent(token_type: TokenType) -> UnsignedTransaction {
+        let tx = Transaction::new("user".into(), SENDER.into(), RECIPIENT.into(), 1_500_000, token_type, 100, "GBP".into(), 7)
+            .with_gas_pricing(&GasPricing {
+                estimated_gas: "65000".into(),
+                gas_price: "1000000000".into(),
+                max_fee_per_gas: "2000000000".into(),
+                max_priority_fee_per_gas: "1000".into(),
+            });
+        UnsignedTransaction::try_from(&tx).unwrap()
+    }
+
+    /// Signs `intent` as the wallet would, after letting `tweak` change it.
+    fn sign(key: &str, intent: &UnsignedTransaction, tweak: impl FnOnce(&mut Eip1559TransactionRequest)) -> String {
+        let wallet = LocalWallet::from_str(key).unwrap();
+        let mut tx = Eip1559TransactionRequest::new()
+            .from(wallet.address())
+            .to(Address::from_str(&intent.to).unwrap())
+            .value(U256::from_dec_str(&intent.value).unwrap())
+            .data(Bytes::from(hex::decode(intent.data.trim_start_matches("0x")).unwrap()))
+            .nonce(U256::from_dec_str(&intent.nonce).unwrap())
+            .gas(U256::from_dec_str(&intent.gas_limit).unwrap())
+            .max_fee_per_gas(U256::from_dec_str(&intent.max_fee_per_gas).unwrap())
+            .max_priority_fee_per_gas(U256::from_dec_str(&intent.max_priority_fee_per_gas).unwrap())
+            .chain_id(intent.chain_id.parse::<u64>().unwrap());
+        tweak(&mut tx);
+
+        let tx = TypedTransaction::Eip1559(tx);
+        let signature = wallet.sign_transaction_sync(&tx).unwrap();
+        format!("0x{}", hex::encode(tx.rlp_signed(&signature)))
+    }
+
+    #[test]
+    fn accepts_exactly_the_issued_transactions() {
+        config::init();
+        for token_type in [TokenType::ETH, TokenType::USDC] {
+            let intent = intent(token_type);
+            let signed = sign(SENDER_KEY, &intent, |_| {});
+
+            assert!(verify(TransactionLeg::Main, &signed, &intent, SENDER).is_ok());
+        }
+    }
+
+    #[test]
+    fn rejects_another_signer() {
+        config::init();
+        let intent = intent(TokenType::ETH);
+        let signed = sign("0101010101010101010101010101010101010101010101010101010101010101", &intent, |_| {});
+
+        assert!(matches!(
+            verify(TransactionLeg::Fee, &signed, &intent, SENDER),
+            Err(TransactionError::SignerMismatch { leg: TransactionLeg::Fee, .. })
+        ));
+    }
+
+    #[test]
+    fn names_the_field_that_was_changed() {
+        config::init();
+        let intent = intent(TokenType::ETH);
+        let cases: [(&str, Box<dyn FnOnce(&mut Eip1559TransactionRequest)>); 6] = [
+            ("to", Box::new(|tx| tx.to = Some(Address::from_str(SENDER).unwrap().into()))),
+            ("value", Box::new(|tx| tx.value = Some(U256::from(2_000_000u64)))),
+            ("nonce", Box::new(|tx| tx.nonce = Some(U256::from(8u64)))),
+            ("chain_id", Box::new(|tx| tx.chain_id = Some(1u64.into()))),
+            ("gas_limit", Box::new(|tx| tx.gas = Some(U256::from(21_000u64)))),
+            ("max_fee_per_gas", Box::new(|tx| tx.max_fee_per_gas = Some(U256::from(1u64)))),
+        ];
+
+        for (expected_field, tweak) in cases {
+            let signed = sign(SENDER_KEY, &intent, tweak);
+            match verify(TransactionLeg::Main, &signed, &intent, SENDER) {
+                Err(TransactionError::SignedFieldMismatch { field, .. }) => assert_eq!(field, expected_field),
+                other => panic!("expected a {} mismatch, got {:?}", expected_field, other),
+            }
+        }
+    }
+
+    #[test]
+    fn accepts_replacements_paying_at_least_the_bump() {
+        config::init();
+        let intent = intent(TokenType::USDC);
+        let signed = sign(SENDER_KEY, &intent, |tx| tx.max_fee_per_gas = Some(U256::from(3_000_000_000u64)));
+
+        assert_eq!(
+            verify_replacement(TransactionLeg::Main, &signed, &intent, SENDER).unwrap(),
+            (3_000_000_000, 1000)
+        );
+
+        let underpriced = sign(SENDER_KEY, &intent, |tx| tx.max_priority_fee_per_gas = Some(U256::from(999u64)));
+        match verify_replacement(TransactionLeg::Main, &underpriced, &intent, SENDER) {
+            Err(TransactionError::SignedFieldMismatch { field, expected, .. }) => {
+                assert_eq!(field, "max_priority_fee_per_gas");
+                assert_eq!(expected, "at least 1000");
+            }
+            other => panic!("expected a tip mismatch, got {:?}", other),
+        }
+
+        let other_nonce = sign(SENDER_KEY, &intent, |tx| tx.nonce = Some(U256::from(8u64)));
+        assert!(verify_replacement(TransactionLeg::Main, &other_nonce, &intent, SENDER).is_err());
+    }
+
+    #[test]
+    fn rejects_payloads_that_are_not_signed_eip1559_transactions() {
+        config::init();
+        let intent = intent(TokenType::ETH);
+
+        for payload in ["0xzz", "0x", "0xf86b0f830f4240", "0x02c0"] {
+            assert!(matches!(
+                verify(TransactionLeg::Main, payload, &intent, SENDER),
+                Err(TransactionError::MalformedSignedTransaction(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use ethers_core::types::Address;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use crate::models::errors::TransactionError;
+use crate::models::transactions::{Network, TokenType};
+use crate::utilities::config;
+
+/// Tokens shipped with the build. `TOKEN_REGISTRY` replaces them without a release.
+const DEFAULT_REGISTRY: &str = include_str!("../../config/tokens.json");
+
+static REGISTRIES: Lazy<HashMap<Network, TokenRegistry>> = Lazy::new(|| {
+    TokenRegistry::load_all().unwrap_or_else(|e| panic!("Invalid token registry: {}", e))
+});
+
+static EMPTY: Lazy<TokenRegistry> = Lazy::new(|| TokenRegistry { tokens: Vec::new() });
+
+/// The registry for the default network.
+pub fn token_registry() -> &'static TokenRegistry {
+    token_registry_for(&config::get_network())
+}
+
+/// The registry for `network`. A network with no entries has no tokens to send.
+pub fn token_registry_for(network: &Network) -> &'static TokenRegistry {
+    REGISTRIES.get(network).unwrap_or(&EMPTY)
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSource {
+    Chainlink,
+    Coinbase,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PriceFeed {
+    /// Symbol the sources quote, which may differ from the token's own (e.g. ETH for WETH).
+    pub base: String,
+    /// Tried in order until one answers.
+    pub sources: Vec<PriceSource>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenConfig {
+    pub symbol: TokenType,
+    pub decimals: u8,
+    /// `None` for the chain's native token.
+    #[serde(default)]
+    pub contract_address: Option<String>,
+    pub price_feed: PriceFeed,
+    /// Disabled tokens can no longer be sent, but existing bundles still resolve.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl TokenConfig {
+    pub fn is_native(&self) -> bool {
+        self.contract_address.is_none()
+    }
+
+    /// Converts a whole-token amount (e.g. 1.5 USDC) into base units, rounding down.
+    pub fn to_base_units(&self, amount: f64) -> u128 {
+        (amount * 10f64.powi(self.decimals as i32)).floor() as u128
+    }
+
+    /// Converts base units (wei, or USDC's 6-decimal units) into a whole-token amount.
+    pub fn from_base_units(&self, units: u128) -> f64 {
+        units as f64 / 10f64.powi(self.decimals as i32)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenRegistry {
+    tokens: Vec<TokenConfig>,
+}
+
+impl TokenRegistry {
+    /// Reads every network's registry from `TOKEN_REGISTRY` if set, otherwise the bundled defaults.
+    pub fn load_all() -> Result<HashMap<Network, Self>, String> {
+        let json = config::get_token_registry_override().unwrap_or_else(|| DEFAULT_REGISTRY.to_string());
+        let networks: HashMap<Network, Vec<TokenConfig>> =
+            serde_json::from_str(&json).map_err(|e| format!("Cannot parse token registry: {}", e))?;
+
+        networks
+            .into_iter()
+            .map(|(network, tokens)| {
+                let registry = Self::from_tokens(tokens).map_err(|e| format!("{}: {}", network, e))?;
+                Ok((network, registry))
+            })
+            .collect()
+    }
+
+    /// Parses the entries for `network` from a document keyed by network name.
+    pub fn from_json(json: &str, network: &Network) -> Result<Self, String> {
+        let mut networks: HashMap<Network, Vec<TokenConfig>> =
+            serde_json::from_str(json).map_err(|e| format!("Cannot parse token registry: {}", e))?;
+        let tokens = networks.remove(network).ok_or(format!("No tokens listed for {:?}", network))?;
+
+        Self::from_tokens(tokens)
+    }
+
+    fn from_tokens(tokens: Vec<TokenConfig>) -> Result<Self, String> {
+        let mut symbols = HashSet::new();
+        for token in &tokens {
+            if !symbols.insert(&token.symbol) {
+                return Err(format!("{} is listed twice", token.symbol));
+            }
+            // 10^decimals must fit in a u128
+            if token.decimals > 38 {
+                return Err(format!("{} has {} decimals", token.symbol, token.decimals));
+            }
+            if let Some(contract) = &token.contract_address {
+                Address::from_str(contract).map_err(|e| format!("{} has an invalid contract address: {}", token.symbol, e))?;
+            }
+        }
+
+        Ok(Self { tokens })
+    }
+
+    /// Looks up a token whether or not it is enabled, e.g. to display an old bundle.
+    pub fn get(&self, token: &TokenType) -> Option<&TokenConfig> {
+        self.tokens.iter().find(|t| t.symbol == *token)
+    }
+
+    /// Looks up a token that may be used for new transfers.
+    pub fn resolve(&self, token: &TokenType) -> Result<&TokenConfig, TransactionError> {
+        self.get(token)
+            .filter(|t| t.enabled)
+            .ok_or_else(|| TransactionError::InvalidToken(token.to_string()))
+    }
+
+    pub fn enabled(&self) -> impl Iterator<Item = &TokenConfig> {
+        self.tokens.iter().filter(|t| t.enabled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGISTRY_WITH_DAI: &str = r#"{
+        "OptimismMainnet": [
+            { "symbol": "ETH", "decimals": 18, "price_feed": { "base": "ETH", "sources": ["chainlink"] } },
+            {
+                "symbol": "DAI",
+                "decimals": 18,
+                "contract_address": "0xDA10009cBd5D07dd0CeCc66161FC93D7c9000da1",
+                "price_feed": { "base": "DAI", "sources": ["coinbase"] },
+                "enabled": false
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn bundled_registry_lists_eth_and_usdc_on_every_network() {
+        for network in [Network::OptimismMainnet, Network::OptimismSepolia, Network::EthereumMainnet, Network::EthereumSepolia] {
+            let registry = TokenRegistry::from_json(DEFAULT_REGISTRY, &network).unwrap();
+
+            let eth = registry.resolve(&TokenType::ETH).unwrap();
+            assert!(eth.is_native());
+            assert_eq!(eth.decimals, 18);
+
+            let usdc = registry.resolve(&TokenType::USDC).unwrap();
+            assert!(!usdc.is_native());
+            assert_eq!(usdc.decimals, 6);
+        }
+    }
+
+    #[test]
+    fn lists_new_tokens_from_config() {
+        let registry = TokenRegistry::from_json(REGISTRY_WITH_DAI, &Network::OptimismMainnet).unwrap();
+        let dai = TokenType::from_str("dai").unwrap();
+
+        assert_eq!(registry.get(&dai).unwrap().price_feed.sources, vec![PriceSource::Coinbase]);
+        assert!(matches!(registry.resolve(&dai), Err(TransactionError::InvalidToken(_))), "disabled tokens cannot be sent");
+        assert!(matches!(registry.resolve(&TokenType::USDC), Err(TransactionError::InvalidToken(_))));
+        assert_eq!(registry.enabled().count(), 1);
+    }
+
+    #[test]
+    fn rejects_inconsistent_registries() {
+        assert!(TokenRegistry::from_json(REGISTRY_WITH_DAI, &Network::OptimismSepolia).is_err());
+
+        let duplicated = r#"{ "OptimismMainnet": [
+            { "symbol": "ETH", "decimals": 18, "price_feed": { "base": "ETH", "sources": [] } },
+            { "symbol": "ETH", "decimals": 18, "price_feed": { "base": "ETH", "sources": [] } }
+        ] }"#;
+        assert!(TokenRegistry::from_json(duplicated, &Network::OptimismMainnet).is_err());
+
+        let bad_contract = r#"{ "OptimismMainnet": [
+            { "symbol": "USDC", "decimals": 6, "contract_address": "0x1234", "price_feed": { "base": "USDC", "sources": [] } }
+        ] }"#;
+        assert!(TokenRegistry::from_json(bad_contract, &Network::OptimismMainnet).is_err());
+    }
+
+    #[test]
+    fn converts_between_whole_tokens_and_base_units() {
+        let registry = TokenRegistry::from_json(DEFAULT_REGISTRY, &Network::OptimismMainnet).unwrap();
+        let usdc = registry.resolve(&TokenType::USDC).unwrap();
+        let eth = registry.resolve(&TokenType::ETH).unwrap();
+
+        assert_eq!(usdc.to_base_units(12.5), 12_500_000);
+        assert_eq!(eth.to_base_units(0.5), 500_000_000_000_000_000);
+        assert_eq!(usdc.from_base_units(2_000_000), 2.0);
+    }
+}
@@ -5,7 +5,9 @@ use alloy_primitives::U256;
 use rust_decimal::Decimal;
 use rust_decimal::prelude::{FromStr, ToPrimitive};
 use crate::models::errors::WalletError;
-use crate::utilities::config;
+use crate::models::transactions::Network;
+use crate::utilities::token_registry::TokenConfig;
+use crate::utilities::{config, erc20};
 
 // Helper function to parse hex values from JSON response
 fn parse_json_hex(json: &serde_json::Value, key: &str) -> std::result::Result<U256, WalletError> {
@@ -38,6 +40,20 @@ pub fn format_wei_to_eth_string(wei: U256, precision: usize) -> String {
     format!("{:.*}", precision, eth)
 }
 
+/// Formats base units of `token` as whole tokens, e.g. 1500000 USDC units as "1.500000".
+pub fn format_token_units_string(units: U256, token: &TokenConfig, precision: usize) -> String {
+    format!("{:.*}", precision, token_units_to_decimal(units, token))
+}
+
+pub fn format_token_units_f64(units: U256, token: &TokenConfig) -> f64 {
+    Decimal::to_f64(&token_units_to_decimal(units, token)).unwrap_or_default()
+}
+
+fn token_units_to_decimal(units: U256, token: &TokenConfig) -> Decimal {
+    let units = Decimal::from_str(&units.to_string()).unwrap_or(Decimal::ZERO);
+    units / Decimal::from(10u128.pow(token.decimals as u32))
+}
+
 pub fn format_wei_to_eth_f64(wei: U256) -> f64 {
     let wei_str = wei.to_string(); // e.g., "13816614144794697"
     let wei_decimal = Decimal::from_str(&wei_str).unwrap_or(Decimal::ZERO);
@@ -53,6 +69,39 @@ pub async fn get_wallet_balance(wallet_address: &str) -> Result<U256, WalletErro
     fetch_balance(&client, wallet_address, &url).await
 }
 
+/// Balance of `token` on `network` in its base units: wei for ETH, the contract's own units for ERC-20s.
+pub async fn get_token_balance(wallet_address: &str, token: &TokenConfig, network: &Network) -> Result<U256, WalletError> {
+    let client = Client::new();
+    let url = config::get_rpc_url_for(network);
+
+    match &token.contract_address {
+        None => fetch_balance(&client, wallet_address, &url).await,
+        Some(contract) => fetch_erc20_balance(&client, wallet_address, contract, &url).await,
+    }
+}
+
+async fn fetch_erc20_balance(client: &Client, wallet_address: &str, contract: &str, rpc_url: &str) -> Result<U256, WalletError> {
+    let data = erc20::balance_of_calldata(wallet_address).map_err(|e| {
+        log::error!("Cannot query token balance: {}", e);
+        WalletError::InvalidWalletAddress
+    })?;
+
+    let payload = json!({
+        "jsonrpc": "2.0",
+        "method": "eth_call",
+        "params": [{ "to": contract, "data": data }, "latest"],
+        "id": 1
+    });
+
+    let balance_of = client.post(rpc_url)
+        .json(&payload)
+        .send()
+        .await;
+
+    let balance = validate_response("Token Balance", balance_of).await?;
+    parse_json_hex(&balance, "result")
+}
+
 async fn fetch_balance(client: &Client, wallet_address: &str, rpc_url: &str) -> Result<U256, WalletError> {
     let payload = json!({
         "jsonrpc": "2.0",
@@ -77,6 +126,8 @@ async fn fetch_balance(client: &Client, wallet_address: &str, rpc_url: &str) ->
 mod tests {
     use super::*;
     use alloy_primitives::U256;
+    use crate::models::transactions::TokenType;
+    use crate::utilities::token_registry::token_registry;
 
     #[test]
     fn test_format_wei_to_eth_string() {
@@ -100,6 +151,19 @@ mod tests {
         let eth = format_wei_to_eth_string(wei, 18);
         assert_eq!(eth, "0.000000000000012345");
     }
+    #[test]
+    fn test_format_token_units_string() {
+        let registry = token_registry();
+        let usdc = registry.resolve(&TokenType::USDC).unwrap();
+        let units = U256::from(1_500_000u64);
+        assert_eq!(format_token_units_string(units, usdc, 2), "1.50");
+        assert_eq!(format_token_units_f64(units, usdc), 1.5);
+
+        let eth = registry.resolve(&TokenType::ETH).unwrap();
+        let wei = U256::from_str("1000000000000000000").unwrap();
+        assert_eq!(format_token_units_string(wei, eth, 6), format_wei_to_eth_string(wei, 6));
+    }
+
     #[tokio::test]
     async fn integration_test()
     {
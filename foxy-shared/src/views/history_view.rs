@@ -4,7 +4,7 @@ use std::sync::Arc;
 use aws_sdk_dynamodb::{Client as DynamoDbClient, types::AttributeValue};
 use aws_sdk_dynamodb::types::Select;
 use base64::Engine;
-use crate::models::transactions::{TransactionEvent, TransactionHistoryItem};
+use crate::models::transactions::{TransactionEvent, TransactionHistoryItem, TransactionLeg};
 use tracing::{info, warn};
 
 pub struct TransactionHistoryViewManager {
@@ -39,6 +39,7 @@ impl TransactionHistoryViewManager {
             .expression_attribute_values(
                 ":prefix", AttributeValue::S(prefix.clone()),
             )
+            .scan_index_forward(false) // latest projection for the bundle, e.g. after a cancel
             .limit(1)
             .send()
             .await?;
@@ -51,43 +52,50 @@ impl TransactionHistoryViewManager {
     }
 
     pub async fn project_from_event(&self, event: &TransactionEvent) -> Result<(), anyhow::Error> {
+        let tasks = Self::items_for_event(event)?
+            .into_iter()
+            .map(|item| self.write_item(item));
+
+        futures::future::try_join_all(tasks).await?;
+
+        info!(bundle_id = %event.bundle_id, "✅ Projected history view for every party");
+        Ok(())
+    }
+
+    /// The rows `project_from_event` writes for an event, one per party to each recipient leg.
+    /// Split legs sort ahead of the main leg, so the latest row for a bundle is the main one.
+    pub fn items_for_event(event: &TransactionEvent) -> Result<Vec<HashMap<String, AttributeValue>>, anyhow::Error> {
         let metadata = event.bundle_snapshot.metadata.as_ref()
             .ok_or_else(|| anyhow::anyhow!("Missing bundle metadata"))?;
 
         let sender = metadata.sender.as_ref()
             .ok_or_else(|| anyhow::anyhow!("Missing sender in metadata"))?;
 
-        let recipient = metadata.recipient.as_ref()
-            .ok_or_else(|| anyhow::anyhow!("Missing recipient in metadata"))?;
+        let mut items = vec![];
 
-        let senderid = &sender.user_id;
-        let recipientid = &recipient.user_id;
+        for leg in event.bundle_snapshot.recipient_legs() {
+            let recipient = match leg {
+                TransactionLeg::Split(i) => metadata.split_recipients.get(i as usize),
+                _ => metadata.recipient.as_ref(),
+            }.ok_or_else(|| anyhow::anyhow!("Missing {} recipient in metadata", leg))?;
 
-        let sender_item = TransactionHistoryItem::from_event_and_user(event, senderid);
-        let recipient_item = TransactionHistoryItem::from_event_and_user(event, recipientid);
+            let senderid = &sender.user_id;
+            let recipientid = &recipient.user_id;
 
-        let mut tasks = vec![];
-
-        if let Some(sender_view) = sender_item {
-            let pk = format!("User#{}", sender_view.counterparty.user_id);
-            let sk = format!("Bundle#{}|{}", sender_view.bundle_id, sender_view.timestamp);
-            let item = Self::to_dynamo_item(&pk, &sk, &sender_view)?;
-            info!(?item, "Writing item to History View");
-            tasks.push(self.write_item(item));
-        }
+            let sender_item = TransactionHistoryItem::from_event_leg_and_user(event, leg, senderid);
+            let recipient_item = TransactionHistoryItem::from_event_leg_and_user(event, leg, recipientid);
 
-        if let Some(recipient_view) = recipient_item {
-            let pk = format!("User#{}", recipient_view.counterparty.user_id);
-            let sk = format!("Bundle#{}|{}", recipient_view.bundle_id, recipient_view.timestamp);
-            let item = Self::to_dynamo_item(&pk, &sk, &recipient_view)?;
-            info!(?item, "Writing item to History View");
-            tasks.push(self.write_item(item));
+            for view in [sender_item, recipient_item].into_iter().flatten() {
+                let pk = format!("User#{}", view.counterparty.user_id);
+                let sk = match leg {
+                    TransactionLeg::Main => format!("Bundle#{}|{}", view.bundle_id, view.timestamp),
+                    leg => format!("Bundle#{}#{}|{}", view.bundle_id, leg, view.timestamp),
+                };
+                items.push(Self::to_dynamo_item(&pk, &sk, &view)?);
+            }
         }
 
-        futures::future::try_join_all(tasks).await?;
-
-        info!(bundle_id = %event.bundle_id, "✅ Projected history view for both parties");
-        Ok(())
+        Ok(items)
     }
 
     pub fn encode_page_token(key: &HashMap<String, AttributeValue>) -> Result<String, anyhow::Error> {
@@ -124,6 +132,9 @@ impl TransactionHistoryViewManager {
             },
             message: item.get("Message").and_then(|v| v.as_s().ok()).map(String::from),
             tx_hash: item.get("TxHash").and_then(|v| v.as_s().ok()).map(String::from),
+            finality: item.get("Finality").and_then(|v| v.as_s().ok()).and_then(|v| v.parse().ok()),
+            network_fee_paid_eth: item.get("NetworkFeePaidEth").and_then(|v| v.as_s().ok()).map(String::from),
+            network_fee_estimate_eth: item.get("NetworkFeeEstimateEth").and_then(|v| v.as_s().ok()).map(String::from),
             display_total_fee: item.get("DisplayTotalFee")?.as_s().ok()?.clone(),
             service_fee_minor: item.get("ServiceFeeMinor")?.as_n().ok()?.parse().ok()?,
             total_fiat_minor: item.get("TotalFiatMinor")?.as_n().ok()?.parse().ok()?,
@@ -211,6 +222,15 @@ impl TransactionHistoryViewManager {
         if let Some(ref tx_hash) = view.tx_hash {
             item.insert("TxHash".to_string(), AttributeValue::S(tx_hash.clone()));
         }
+        if let Some(finality) = view.finality {
+            item.insert("Finality".to_string(), AttributeValue::S(finality.to_string()));
+        }
+        if let Some(ref paid) = view.network_fee_paid_eth {
+            item.insert("NetworkFeePaidEth".to_string(), AttributeValue::S(paid.clone()));
+        }
+        if let Some(ref estimate) = view.network_fee_estimate_eth {
+            item.insert("NetworkFeeEstimateEth".to_string(), AttributeValue::S(estimate.clone()));
+        }
 
         Ok(item)
     }
@@ -220,7 +240,7 @@ impl TransactionHistoryViewManager {
 mod tests {
     use chrono::Utc;
     use super::*;
-    use crate::models::transactions::{BundleMetadata, BundleStatus, Direction, EventType, GasPricing, PartyDetails, Transaction, TransactionBundle, TransactionStatus};
+    use crate::models::transactions::{BundleMetadata, BundleStatus, Direction, EventType, FinalityStage, GasPricing, PartyDetails, Transaction, TransactionBundle, TransactionStatus};
     use crate::models::user_device::UserDevice;
     use crate::utilities::config;
     use crate::utilities::config::get_history_view_table;
@@ -261,6 +281,7 @@ mod tests {
             },
             service_fee_minor: Some(20),
             user_device,
+            split_recipients: Vec::new(),
         };
 
         let bundle = TransactionBundle {
@@ -269,6 +290,8 @@ mod tests {
             status: BundleStatus::Initiated,
             fee_tx: Transaction::mock_fee(sender_id, 100000000000000u128),
             main_tx: Transaction::mock_main(sender_id, recipient_id, 5000000000000000u128),
+            split_txs: Vec::new(),
+            rebroadcast_attempts: 0,
             metadata: Some(metadata),
             created_at: Utc::now(),
             updated_at: Utc::now(),
@@ -276,6 +299,8 @@ mod tests {
 
         TransactionEvent {
             event_id: "event-id".to_string(),
+            idempotency_key: None,
+            sequence: 1,
             bundle_id: bundle.bundle_id.clone(),
             user_id: sender_id.to_string(),
             event_type: EventType::Initiate,
@@ -309,6 +334,76 @@ mod tests {
         assert_eq!(view.counterparty.user_id, sender_id);
     }
 
+    #[test]
+    fn split_legs_get_their_own_rows() {
+        let sender_id = "user_sender";
+        let mut event = mock_event(sender_id, "user_recipient");
+        event.bundle_snapshot.split_txs.push(Transaction::mock_main(sender_id, "user_split", 2000000000000000u128));
+        if let Some(metadata) = event.bundle_snapshot.metadata.as_mut() {
+            metadata.split_recipients.push(PartyDetails {
+                user_id: "user_split".to_string(),
+                name: "Split".to_string(),
+                wallet: "0xsplit".to_string(),
+            });
+        }
+
+        let items = TransactionHistoryViewManager::items_for_event(&event).unwrap();
+        assert_eq!(items.len(), 4);
+
+        let split_view = TransactionHistoryItem::from_event_leg_and_user(&event, TransactionLeg::Split(0), "user_split").unwrap();
+        assert_eq!(split_view.direction, Direction::Incoming);
+        assert_eq!(split_view.counterparty.user_id, sender_id);
+        assert_eq!(split_view.service_fee_minor, 0);
+        assert_eq!(split_view.total_fiat_minor, event.bundle_snapshot.split_txs[0].fiat_value);
+
+        // The main leg's recipient sees nothing of the split
+        assert!(TransactionHistoryItem::from_event_leg_and_user(&event, TransactionLeg::Split(0), "user_recipient").is_none());
+
+        let sk = |item: &HashMap<String, AttributeValue>| item["SK"].as_s().unwrap().clone();
+        let main_sk = items.iter().map(sk).find(|sk| !sk.contains("Split")).unwrap();
+        assert!(items.iter().map(sk).all(|sk| sk <= main_sk));
+    }
+
+    #[test]
+    fn rows_show_the_finality_stage() {
+        let mut event = mock_event("user_sender", "user_recipient");
+        event.bundle_snapshot.main_tx.finality = Some(FinalityStage::Included);
+
+        let items = TransactionHistoryViewManager::items_for_event(&event).unwrap();
+        assert!(items.iter().all(|item| item["Finality"].as_s().unwrap() == "Included"));
+
+        // Rows written before the leg was mined have no stage
+        let parsed = TransactionHistoryViewManager::parse_history_item(&items[0]).unwrap();
+        assert_eq!(parsed.finality, Some(FinalityStage::Included));
+        event.bundle_snapshot.main_tx.finality = None;
+        let items = TransactionHistoryViewManager::items_for_event(&event).unwrap();
+        assert!(items.iter().all(|item| !item.contains_key("Finality")));
+    }
+
+    #[test]
+    fn main_row_shows_the_network_fees_of_main_and_fee_legs() {
+        let mut event = mock_event("user_sender", "user_recipient");
+        for tx in [&mut event.bundle_snapshot.main_tx, &mut event.bundle_snapshot.fee_tx] {
+            tx.gas_limit = Some(21_000);
+            tx.max_fee_per_gas = Some(2_000_000_000);
+        }
+
+        let view = TransactionHistoryItem::from_event_and_user(&event, "user_sender").unwrap();
+        assert_eq!(view.network_fee_estimate_eth.as_deref(), Some("0.00008400"));
+        assert_eq!(view.network_fee_paid_eth, None);
+
+        // Only the main leg is mined so far
+        event.bundle_snapshot.main_tx = event.bundle_snapshot.main_tx.clone()
+            .with_gas_paid(Some(21_000), Some(1_000_000_000), Some(4_000_000_000_000));
+        let view = TransactionHistoryItem::from_event_and_user(&event, "user_sender").unwrap();
+        assert_eq!(view.network_fee_paid_eth.as_deref(), Some("0.00002500"));
+
+        let item = TransactionHistoryViewManager::to_dynamo_item("User#user_sender", "Bundle#test-bundle-id|now", &view).unwrap();
+        let parsed = TransactionHistoryViewManager::parse_history_item(&item).unwrap();
+        assert_eq!(parsed.network_fee_paid_eth, view.network_fee_paid_eth);
+        assert_eq!(parsed.network_fee_estimate_eth, view.network_fee_estimate_eth);
+    }
+
     #[test]
     fn test_parse_history_item_happy_path() {
         let mut item = HashMap::new();
@@ -323,6 +418,7 @@ mod tests {
         item.insert("CounterpartyWallet".to_string(), AttributeValue::S("0xabc".to_string()));
         item.insert("Message".to_string(), AttributeValue::S("Lunch".to_string()));
         item.insert("TxHash".to_string(), AttributeValue::S("0xhash".to_string()));
+        item.insert("Finality".to_string(), AttributeValue::S("Safe".to_string()));
 
         let parsed = TransactionHistoryViewManager::parse_history_item(&item).unwrap();
 
@@ -337,6 +433,7 @@ mod tests {
         assert_eq!(parsed.counterparty.wallet, "0xabc");
         assert_eq!(parsed.message.as_deref(), Some("Lunch"));
         assert_eq!(parsed.tx_hash.as_deref(), Some("0xhash"));
+        assert_eq!(parsed.finality, Some(FinalityStage::Safe));
     }
 
     #[tokio::test]
@@ -1,2 +1,4 @@
 pub mod status_view;
-pub mod history_view;
\ No newline at end of file
+pub mod history_view;
+pub mod outbox;
+pub mod rebuild;
\ No newline at end of file
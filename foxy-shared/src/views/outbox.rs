@@ -0,0 +1,425 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use aws_sdk_dynamodb::{Client as DynamoDbClient, types::AttributeValue};
+use aws_sdk_dynamodb::types::{Delete, Put, TransactWriteItem};
+use chrono::{DateTime, Duration, Utc};
+use tracing::{error, info, warn};
+use crate::database::errors::DynamoDbError;
+use crate::database::event_store::EventStore;
+use crate::models::transactions::TransactionEvent;
+use crate::utilities::config::{get_history_view_table, get_transaction_view_table};
+use crate::views::history_view::TransactionHistoryViewManager;
+use crate::views::status_view::TransactionStatusViewManager;
+
+/// Entries are spread over this many partitions, `Outbox#0` to `Outbox#15`, so appends to
+/// different bundles do not all write to one key. A bundle's entries share one.
+pub const OUTBOX_SHARDS: u32 = 16;
+const DEAD_LETTER_PK: &str = "Outbox#DeadLetter";
+
+/// After this many failed attempts an entry is moved to the dead-letter partition for
+/// investigation, so it no longer holds up the entries behind it. The projection rebuild
+/// command repairs the views it left stale.
+pub const DEAD_LETTER_AFTER: u32 = 5;
+
+const MAX_BACKOFF_SECS: i64 = 600;
+
+/// A projection that still has to be applied for one appended event. Written in the same
+/// transaction as the event, so the read models can lag the log but never miss an event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxEntry {
+    pub bundle_id: String,
+    pub sequence: u64,
+    pub event_id: String,
+    pub created_at: DateTime<Utc>,
+    pub attempts: u32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+}
+
+impl OutboxEntry {
+    pub fn for_event(event: &TransactionEvent) -> Self {
+        Self {
+            bundle_id: event.bundle_id.clone(),
+            sequence: event.sequence,
+            event_id: event.event_id.clone(),
+            created_at: event.created_at,
+            attempts: 0,
+            next_attempt_at: event.created_at,
+            last_error: None,
+        }
+    }
+
+    /// The outbox partition holding this entry, picked by bundle so its entries stay in order.
+    fn partition(&self) -> String {
+        shard_partition(shard_of(&self.bundle_id))
+    }
+
+    // Oldest first across bundles, and in sequence order within one.
+    fn sort_key(&self) -> String {
+        format!("{}#{}#{:020}", self.created_at.to_rfc3339(), self.bundle_id, self.sequence)
+    }
+
+    /// Exponential backoff from 10s, capped at 10 minutes.
+    fn backoff(attempts: u32) -> Duration {
+        let secs = 10i64.saturating_mul(1 << attempts.min(16));
+        Duration::seconds(secs.min(MAX_BACKOFF_SECS))
+    }
+
+    fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.next_attempt_at <= now
+    }
+
+    fn to_item(&self, pk: &str) -> HashMap<String, AttributeValue> {
+        let mut item = HashMap::new();
+        item.insert("PK".to_string(), AttributeValue::S(pk.to_string()));
+        item.insert("SK".to_string(), AttributeValue::S(self.sort_key()));
+        item.insert("BundleID".to_string(), AttributeValue::S(self.bundle_id.clone()));
+        item.insert("Sequence".to_string(), AttributeValue::N(self.sequence.to_string()));
+        item.insert("EventID".to_string(), AttributeValue::S(self.event_id.clone()));
+        item.insert("CreatedAt".to_string(), AttributeValue::S(self.created_at.to_rfc3339()));
+        item.insert("Attempts".to_string(), AttributeValue::N(self.attempts.to_string()));
+        item.insert("NextAttemptAt".to_string(), AttributeValue::S(self.next_attempt_at.to_rfc3339()));
+
+        if let Some(last_error) = &self.last_error {
+            item.insert("LastError".to_string(), AttributeValue::S(last_error.clone()));
+        }
+
+        item
+    }
+
+    fn from_item(item: &HashMap<String, AttributeValue>) -> Result<Self, DynamoDbError> {
+        let string = |key: &str| {
+            item.get(key)
+                .and_then(|v| v.as_s().ok())
+                .cloned()
+                .ok_or_else(|| DynamoDbError::Deserialization(format!("Outbox entry is missing {}", key)))
+        };
+        let number = |key: &str| {
+            item.get(key)
+                .and_then(|v| v.as_n().ok())
+                .and_then(|n| n.parse::<u64>().ok())
+                .ok_or_else(|| DynamoDbError::Deserialization(format!("Outbox entry has no valid {}", key)))
+        };
+        let timestamp = |key: &str| {
+            string(key).and_then(|s| {
+                DateTime::parse_from_rfc3339(&s)
+                    .map(|dt| dt.with_timezone(&Utc))
+                    .map_err(|e| DynamoDbError::Deserialization(format!("Invalid {}: {}", key, e)))
+            })
+        };
+
+        Ok(Self {
+            bundle_id: string("BundleID")?,
+            sequence: number("Sequence")?,
+            event_id: string("EventID")?,
+            created_at: timestamp("CreatedAt")?,
+            attempts: number("Attempts")? as u32,
+            next_attempt_at: timestamp("NextAttemptAt")?,
+            last_error: string("LastError").ok(),
+        })
+    }
+}
+
+/// A stable hash, so an entry is always found in the partition it was written to.
+fn shard_of(bundle_id: &str) -> u32 {
+    let hash = bundle_id.bytes().fold(0u32, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as u32));
+    hash % OUTBOX_SHARDS
+}
+
+fn shard_partition(shard: u32) -> String {
+    format!("Outbox#{}", shard)
+}
+
+/// Pending projections, kept in the event table so they can join the append transaction.
+#[derive(Clone)]
+pub struct ProjectionOutbox {
+    client: Arc<DynamoDbClient>,
+    table_name: String,
+}
+
+impl ProjectionOutbox {
+    // table_name is the event log table, probably from get_transaction_event_table()
+    pub fn new(client: Arc<DynamoDbClient>, table_name: String) -> Self {
+        Self { client, table_name }
+    }
+
+    /// The write to include in the event's append transaction.
+    pub fn put(&self, entry: &OutboxEntry) -> Result<Put, DynamoDbError> {
+        Put::builder()
+            .table_name(&self.table_name)
+            .set_item(Some(entry.to_item(&entry.partition())))
+            .build()
+            .map_err(|e| DynamoDbError::KeyBuildFailed(e.to_string()))
+    }
+
+    /// Up to `limit` entries whose next attempt is due, oldest first across every partition.
+    /// Entries still backing off are skipped over, however many of them there are.
+    pub async fn due(&self, limit: i32) -> Result<Vec<OutboxEntry>, DynamoDbError> {
+        let now = Utc::now();
+        let mut entries = Vec::new();
+        for shard in 0..OUTBOX_SHARDS {
+            entries.extend(self.due_in(&shard_partition(shard), limit, now).await?);
+        }
+
+        entries.sort_by_key(OutboxEntry::sort_key);
+        entries.truncate(limit.max(0) as usize);
+        Ok(entries)
+    }
+
+    async fn due_in(&self, partition: &str, limit: i32, now: DateTime<Utc>) -> Result<Vec<OutboxEntry>, DynamoDbError> {
+        let mut entries = Vec::new();
+        let mut start_key = None;
+
+        // DynamoDB applies the limit before the filter, so keep paging until enough are found
+        loop {
+            let result = self.client
+                .query()
+                .table_name(&self.table_name)
+                .key_condition_expression("PK = :pk")
+                .filter_expression("NextAttemptAt <= :now")
+                .expression_attribute_values(":pk", AttributeValue::S(partition.to_string()))
+                .expression_attribute_values(":now", AttributeValue::S(now.to_rfc3339()))
+                .scan_index_forward(true)
+                .limit(limit)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+
+            for item in result.items() {
+                let entry = OutboxEntry::from_item(item)?;
+                if entry.is_due(now) && entries.len() < limit as usize {
+                    entries.push(entry);
+                }
+            }
+
+            start_key = result.last_evaluated_key;
+            if start_key.is_none() || entries.len() >= limit as usize {
+                return Ok(entries);
+            }
+        }
+    }
+
+    pub async fn dead_letters(&self) -> Result<Vec<OutboxEntry>, DynamoDbError> {
+        let result = self.client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("PK = :pk")
+            .expression_attribute_values(":pk", AttributeValue::S(DEAD_LETTER_PK.to_string()))
+            .send()
+            .await?;
+
+        result.items().iter().map(OutboxEntry::from_item).collect()
+    }
+
+    /// Removes an applied entry, along with its dead-letter record if it had one.
+    pub async fn complete(&self, entry: &OutboxEntry) -> Result<(), DynamoDbError> {
+        for pk in [entry.partition(), DEAD_LETTER_PK.to_string()] {
+            self.client
+                .delete_item()
+                .table_name(&self.table_name)
+                .key("PK", AttributeValue::S(pk))
+                .key("SK", AttributeValue::S(entry.sort_key()))
+                .send()
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Records a failed attempt and schedules the next one, or moves the entry to the
+    /// dead-letter partition once it has failed `DEAD_LETTER_AFTER` times.
+    pub async fn record_failure(&self, entry: &OutboxEntry, error: &str) -> Result<OutboxEntry, DynamoDbError> {
+        let mut failed = entry.clone();
+        failed.attempts += 1;
+        failed.next_attempt_at = Utc::now() + OutboxEntry::backoff(failed.attempts);
+        failed.last_error = Some(error.to_string());
+
+        if failed.attempts >= DEAD_LETTER_AFTER {
+            self.dead_letter(&failed).await?;
+            return Ok(failed);
+        }
+
+        self.client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("PK", AttributeValue::S(failed.partition()))
+            .key("SK", AttributeValue::S(failed.sort_key()))
+            .update_expression("SET Attempts = :attempts, NextAttemptAt = :next, LastError = :error")
+            .condition_expression("attribute_exists(PK)")
+            .expression_attribute_values(":attempts", AttributeValue::N(failed.attempts.to_string()))
+            .expression_attribute_values(":next", AttributeValue::S(failed.next_attempt_at.to_rfc3339()))
+            .expression_attribute_values(":error", AttributeValue::S(error.to_string()))
+            .send()
+            .await?;
+
+        Ok(failed)
+    }
+
+    async fn dead_letter(&self, entry: &OutboxEntry) -> Result<(), DynamoDbError> {
+        let put = Put::builder()
+            .table_name(&self.table_name)
+            .set_item(Some(entry.to_item(DEAD_LETTER_PK)))
+            .build()
+            .map_err(|e| DynamoDbError::KeyBuildFailed(e.to_string()))?;
+        let delete = Delete::builder()
+            .table_name(&self.table_name)
+            .key("PK", AttributeValue::S(entry.partition()))
+            .key("SK", AttributeValue::S(entry.sort_key()))
+            .condition_expression("attribute_exists(PK)")
+            .build()
+            .map_err(|e| DynamoDbError::KeyBuildFailed(e.to_string()))?;
+
+        self.client
+            .transact_write_items()
+            .transact_items(TransactWriteItem::builder().put(put).build())
+            .transact_items(TransactWriteItem::builder().delete(delete).build())
+            .send()
+            .await?;
+        Ok(())
+    }
+}
+
+/// Applies an event to the status and history views. Both writes are idempotent, so an
+/// entry that is retried after a partial success does no harm.
+pub async fn project_event(
+    client: Arc<DynamoDbClient>,
+    event_store: Arc<dyn EventStore>,
+    event: &TransactionEvent,
+) -> Result<(), anyhow::Error> {
+    let latest = event_store.get_latest_event(&event.bundle_id).await?;
+    project(client, event_store, event, &latest).await
+}
+
+/// Writes the history row of `event`, and the status row from `latest`. The status view
+/// holds one row per bundle, so an append or retry that finishes after a later one must not
+/// overwrite it with an older state; whichever event is latest is projected instead.
+async fn project(
+    client: Arc<DynamoDbClient>,
+    event_store: Arc<dyn EventStore>,
+    event: &TransactionEvent,
+    latest: &TransactionEvent,
+) -> Result<(), anyhow::Error> {
+    // A read that has not caught up with the append yet
+    let latest = if latest.sequence >= event.sequence { latest } else { event };
+    let status_view = TransactionStatusViewManager::new(get_transaction_view_table(), client.clone(), event_store);
+    status_view.project_event(latest).await?;
+
+    let history_view = TransactionHistoryViewManager::new(get_history_view_table(), client);
+    history_view.project_from_event(event).await?;
+
+    Ok(())
+}
+
+/// Retries every due outbox entry once. Returns how many were applied.
+pub async fn drain(
+    outbox: &ProjectionOutbox,
+    client: Arc<DynamoDbClient>,
+    event_store: Arc<dyn EventStore>,
+    limit: i32,
+) -> Result<u32, DynamoDbError> {
+    let mut applied = 0;
+
+    for entry in outbox.due(limit).await? {
+        match apply(&entry, client.clone(), event_store.clone()).await {
+            Ok(()) => {
+                outbox.complete(&entry).await?;
+                applied += 1;
+            }
+            Err(e) => {
+                let failed = outbox.record_failure(&entry, &e.to_string()).await?;
+                if failed.attempts >= DEAD_LETTER_AFTER {
+                    error!(bundle_id = %entry.bundle_id, sequence = entry.sequence, attempts = failed.attempts, ?e, "☠️ Projection dead-lettered");
+                } else {
+                    warn!(bundle_id = %entry.bundle_id, sequence = entry.sequence, attempts = failed.attempts, ?e, "🔁 Projection failed, retrying later");
+                }
+            }
+        }
+    }
+
+    Ok(applied)
+}
+
+async fn apply(
+    entry: &OutboxEntry,
+    client: Arc<DynamoDbClient>,
+    event_store: Arc<dyn EventStore>,
+) -> Result<(), anyhow::Error> {
+    let events = event_store.get_events(&entry.bundle_id).await?;
+    let event = events
+        .iter()
+        .find(|e| e.sequence == entry.sequence)
+        .ok_or_else(|| anyhow::anyhow!("Event {} of bundle {} not found", entry.sequence, entry.bundle_id))?;
+
+    let latest = events.last().unwrap_or(event);
+    project(client, event_store, event, latest).await?;
+
+    info!(bundle_id = %entry.bundle_id, sequence = entry.sequence, "📬 Applied outbox projection");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(bundle_id: &str, sequence: u64, created_at: &str) -> OutboxEntry {
+        let created_at = DateTime::parse_from_rfc3339(created_at).unwrap().with_timezone(&Utc);
+        OutboxEntry {
+            bundle_id: bundle_id.into(),
+            sequence,
+            event_id: format!("event-{}", sequence),
+            created_at,
+            attempts: 0,
+            next_attempt_at: created_at,
+            last_error: None,
+        }
+    }
+
+    #[test]
+    fn round_trips_through_dynamo_item() {
+        let mut original = entry("bundle", 3, "2026-10-01T12:00:00Z");
+        original.attempts = 2;
+        original.last_error = Some("throttled".into());
+
+        let item = original.to_item(&original.partition());
+        assert_eq!(OutboxEntry::from_item(&item).unwrap(), original);
+        assert_eq!(item["SK"], original.to_item(DEAD_LETTER_PK)["SK"]);
+    }
+
+    #[test]
+    fn sorts_oldest_first_and_by_sequence() {
+        let first = entry("b", 9, "2026-10-01T12:00:00Z");
+        let second = entry("a", 1, "2026-10-01T12:00:01Z");
+        let third = entry("a", 2, "2026-10-01T12:00:01Z");
+
+        assert!(first.sort_key() < second.sort_key());
+        assert!(second.sort_key() < third.sort_key());
+    }
+
+    #[test]
+    fn spreads_bundles_over_partitions_and_keeps_each_in_one() {
+        let first = entry("bundle-a", 1, "2026-10-01T12:00:00Z");
+        let second = entry("bundle-a", 2, "2026-10-01T12:00:05Z");
+        assert_eq!(first.partition(), second.partition());
+
+        let shards: std::collections::HashSet<u32> = (0..200).map(|i| shard_of(&format!("bundle-{}", i))).collect();
+        assert!(shards.len() > 1);
+        assert!(shards.iter().all(|shard| *shard < OUTBOX_SHARDS));
+    }
+
+    #[test]
+    fn backoff_grows_and_is_capped() {
+        assert_eq!(OutboxEntry::backoff(1), Duration::seconds(20));
+        assert_eq!(OutboxEntry::backoff(3), Duration::seconds(80));
+        assert_eq!(OutboxEntry::backoff(40), Duration::seconds(MAX_BACKOFF_SECS));
+    }
+
+    #[test]
+    fn waits_until_next_attempt() {
+        let mut pending = entry("bundle", 1, "2026-10-01T12:00:00Z");
+        assert!(pending.is_due(pending.created_at));
+
+        pending.next_attempt_at = pending.created_at + OutboxEntry::backoff(1);
+        assert!(!pending.is_due(pending.created_at + Duration::seconds(5)));
+        assert!(pending.is_due(pending.created_at + Duration::seconds(20)));
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+use aws_sdk_dynamodb::{Client as DynamoDbClient, types::AttributeValue};
+use tracing::{error, info};
+use crate::database::event_store::EventStore;
+use crate::models::transactions::TransactionEvent;
+use crate::views::history_view::TransactionHistoryViewManager;
+use crate::views::status_view::TransactionStatusViewManager;
+
+/// Which bundles to regenerate view rows for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RebuildScope {
+    All,
+    /// Every bundle the user sent or received.
+    User(String),
+    Bundle(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowChange {
+    Added,
+    /// Attributes whose value would change or appear/disappear.
+    Changed(Vec<String>),
+    Unchanged,
+}
+
+#[derive(Debug, Clone)]
+pub struct RowDiff {
+    pub table: String,
+    pub key: String,
+    pub change: RowChange,
+}
+
+impl fmt::Display for RowDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.change {
+            RowChange::Added => write!(f, "+ {} {}", self.table, self.key),
+            RowChange::Changed(attrs) => write!(f, "~ {} {} [{}]", self.table, self.key, attrs.join(", ")),
+            RowChange::Unchanged => write!(f, "= {} {}", self.table, self.key),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RebuildReport {
+    pub bundles: usize,
+    pub rows: Vec<RowDiff>,
+    /// Bundles that could not be projected, with the reason.
+    pub failures: Vec<(String, String)>,
+}
+
+impl RebuildReport {
+    pub fn changed(&self) -> impl Iterator<Item = &RowDiff> {
+        self.rows.iter().filter(|r| r.change != RowChange::Unchanged)
+    }
+}
+
+/// Regenerates the status and history views from the event store. The projectors only
+/// ever put whole rows, so running it repeatedly is safe.
+pub struct ProjectionRebuilder {
+    client: Arc<DynamoDbClient>,
+    event_store: Arc<dyn EventStore>,
+    event_table: String,
+    status_table: String,
+    history_table: String,
+}
+
+impl ProjectionRebuilder {
+    pub fn new(
+        client: Arc<DynamoDbClient>,
+        event_store: Arc<dyn EventStore>,
+        event_table: String,
+        status_table: String,
+        history_table: String,
+    ) -> Self {
+        Self { client, event_store, event_table, status_table, history_table }
+    }
+
+    /// Rebuilds every bundle in `scope`. With `dry_run` nothing is written and the report
+    /// only says which rows would change.
+    pub async fn run(&self, scope: &RebuildScope, dry_run: bool) -> Result<RebuildReport, anyhow::Error> {
+        let bundle_ids = self.bundle_ids(scope).await?;
+        let mut report = RebuildReport { bundles: bundle_ids.len(), ..Default::default() };
+
+        for bundle_id in bundle_ids {
+            match self.rebuild_bundle(&bundle_id, dry_run).await {
+                Ok(rows) => report.rows.extend(rows),
+                Err(e) => {
+                    error!(%bundle_id, ?e, "❌ Failed to rebuild bundle views");
+                    report.failures.push((bundle_id, e.to_string()));
+                }
+            }
+        }
+
+        info!(bundles = report.bundles, changed = report.changed().count(), failures = report.failures.len(), dry_run, "🔧 Rebuild finished");
+        Ok(report)
+    }
+
+    async fn rebuild_bundle(&self, bundle_id: &str, dry_run: bool) -> Result<Vec<RowDiff>, anyhow::Error> {
+        let events = self.event_store.get_events(bundle_id).await?;
+        let mut diffs = Vec::new();
+
+        for (table, desired) in desired_rows(&events, &self.status_table, &self.history_table)? {
+            let current = self.current_row(table, &desired).await?;
+            let change = diff_row(current.as_ref(), &desired);
+
+            if !dry_run && change != RowChange::Unchanged {
+                self.client
+                    .put_item()
+                    .table_name(table)
+                    .set_item(Some(desired.clone()))
+                    .send()
+                    .await?;
+            }
+
+            diffs.push(RowDiff { table: table.to_string(), key: row_key(&desired), change });
+        }
+
+        Ok(diffs)
+    }
+
+    async fn current_row(
+        &self,
+        table: &str,
+        desired: &HashMap<String, AttributeValue>,
+    ) -> Result<Option<HashMap<String, AttributeValue>>, anyhow::Error> {
+        let mut request = self.client.get_item().table_name(table).consistent_read(true);
+        for key in ["PK", "SK"] {
+            if let Some(value) = desired.get(key) {
+                request = request.key(key, value.clone());
+            }
+        }
+        Ok(request.send().await?.item)
+    }
+
+    async fn bundle_ids(&self, scope: &RebuildScope) -> Result<Vec<String>, anyhow::Error> {
+        match scope {
+            RebuildScope::Bundle(bundle_id) => Ok(vec![bundle_id.clone()]),
+            RebuildScope::All => self.scan_bundle_ids(None).await,
+            RebuildScope::User(user_id) => {
+                // Sent bundles are found through the event log; received ones only through
+                // the user's existing history rows.
+                let mut ids: BTreeSet<String> = self.scan_bundle_ids(Some(user_id)).await?.into_iter().collect();
+                ids.extend(self.history_bundle_ids(user_id).await?);
+                Ok(ids.into_iter().collect())
+            }
+        }
+    }
+
+    async fn scan_bundle_ids(&self, user_id: Option<&str>) -> Result<Vec<String>, anyhow::Error> {
+        let mut ids = BTreeSet::new();
+        let mut exclusive_start_key = None;
+
+        loop {
+            let mut scan = self.client
+                .scan()
+                .table_name(&self.event_table)
+                .projection_expression("PK")
+                .expression_attribute_values(":event", AttributeValue::S("Event#".to_string()))
+                .set_exclusive_start_key(exclusive_start_key);
+
+            scan = match user_id {
+                Some(user_id) => scan
+                    .filter_expression("begins_with(SK, :event) AND UserID = :user")
+                    .expression_attribute_values(":user", AttributeValue::S(user_id.to_string())),
+                None => scan.filter_expression("begins_with(SK, :event)"),
+            };
+
+            let result = scan.send().await?;
+            ids.extend(
+                result.items()
+                    .iter()
+                    .filter_map(|item| item.get("PK")?.as_s().ok()?.strip_prefix("Bundle#").map(String::from)),
+            );
+
+            match result.last_evaluated_key {
+                Some(key) => exclusive_start_key = Some(key),
+                None => break,
+            }
+        }
+
+        Ok(ids.into_iter().collect())
+    }
+
+    async fn history_bundle_ids(&self, user_id: &str) -> Result<Vec<String>, anyhow::Error> {
+        let mut ids = BTreeSet::new();
+        let mut exclusive_start_key = None;
+
+        loop {
+            let result = self.client
+                .query()
+                .table_name(&self.history_table)
+                .key_condition_expression("PK = :pk")
+                .expression_attribute_values(":pk", AttributeValue::S(format!("User#{}", user_id)))
+                .projection_expression("BundleID")
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await?;
+
+            ids.extend(
+                result.items()
+                    .iter()
+                    .filter_map(|item| item.get("BundleID")?.as_s().ok().cloned()),
+            );
+
+            match result.last_evaluated_key {
+                Some(key) => exclusive_start_key = Some(key),
+                None => break,
+            }
+        }
+
+        Ok(ids.into_iter().collect())
+    }
+}
+
+/// A row to write, and the view table it belongs to.
+type ViewRow<'a> = (&'a str, HashMap<String, AttributeValue>);
+
+/// The rows the projectors produce for a bundle's history: the status rows of the latest
+/// event and the history rows of every event.
+fn desired_rows<'a>(
+    events: &[TransactionEvent],
+    status_table: &'a str,
+    history_table: &'a str,
+) -> Result<Vec<ViewRow<'a>>, anyhow::Error> {
+    let latest = events.last().ok_or_else(|| anyhow::anyhow!("Bundle has no events"))?;
+
+    let mut rows: Vec<ViewRow<'a>> = TransactionStatusViewManager::items_for_event(latest)?
+        .into_iter()
+        .map(|item| (status_table, item))
+        .collect();
+    for event in events {
+        for item in TransactionHistoryViewManager::items_for_event(event)? {
+            rows.push((history_table, item));
+        }
+    }
+    Ok(rows)
+}
+
+fn diff_row(
+    current: Option<&HashMap<String, AttributeValue>>,
+    desired: &HashMap<String, AttributeValue>,
+) -> RowChange {
+    let Some(current) = current else {
+        return RowChange::Added;
+    };
+
+    let names: BTreeSet<&String> = current.keys().chain(desired.keys()).collect();
+    let changed: Vec<String> = names
+        .into_iter()
+        .filter(|name| current.get(*name) != desired.get(*name))
+        .cloned()
+        .collect();
+
+    if changed.is_empty() {
+        RowChange::Unchanged
+    } else {
+        RowChange::Changed(changed)
+    }
+}
+
+fn row_key(item: &HashMap<String, AttributeValue>) -> String {
+    ["PK", "SK"]
+        .iter()
+        .filter_map(|key| item.get(*key)?.as_s().ok().cloned())
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::transactions::{BundleMetadata, PartyDetails, TokenType, Transaction, TransactionBundle};
+
+    fn history() -> Vec<TransactionEvent> {
+        let fee_tx = Transaction::new("alice".into(), "0xa".into(), "0xfoxy".into(), 100, TokenType::ETH, 1, "GBP".into(), 1);
+        let main_tx = Transaction::new("alice".into(), "0xa".into(), "0xb".into(), 1000, TokenType::ETH, 100, "GBP".into(), 0);
+        let metadata = BundleMetadata {
+            sender: Some(PartyDetails { user_id: "alice".into(), name: "Alice".into(), wallet: "0xa".into() }),
+            recipient: Some(PartyDetails { user_id: "bob".into(), name: "Bob".into(), wallet: "0xb".into() }),
+            ..Default::default()
+        };
+        let bundle = TransactionBundle::new("alice".into(), fee_tx, main_tx, Some(metadata));
+
+        let initiated = TransactionEvent::initiate(bundle).unwrap();
+        let signed = TransactionEvent::sign(&initiated, "0x01", "0x02", &[]).unwrap();
+        vec![initiated, signed]
+    }
+
+    #[test]
+    fn desires_one_status_row_and_history_rows_per_event() {
+        let events = history();
+        let rows = desired_rows(&events, "status", "history").unwrap();
+
+        let status_rows: Vec<_> = rows.iter().filter(|(t, _)| *t == "status").collect();
+        assert_eq!(status_rows.len(), 1);
+        assert_eq!(status_rows[0].1["Status"], AttributeValue::S("Signed".into()));
+
+        // Two events, two parties each
+        assert_eq!(rows.iter().filter(|(t, _)| *t == "history").count(), 4);
+    }
+
+    #[test]
+    fn diff_reports_added_changed_and_unchanged_rows() {
+        let events = history();
+        let (_, desired) = desired_rows(&events, "status", "history").unwrap().remove(0);
+
+        assert_eq!(diff_row(None, &desired), RowChange::Added);
+        assert_eq!(diff_row(Some(&desired), &desired), RowChange::Unchanged);
+
+        let mut stale = desired.clone();
+        stale.insert("Status".into(), AttributeValue::S("Created".into()));
+        stale.insert("Obsolete".into(), AttributeValue::S("x".into()));
+        assert_eq!(
+            diff_row(Some(&stale), &desired),
+            RowChange::Changed(vec!["Obsolete".into(), "Status".into()])
+        );
+    }
+
+    #[test]
+    fn describes_rows_by_key() {
+        let events = history();
+        let rows = desired_rows(&events, "status", "history").unwrap();
+        let diff = RowDiff { table: "history".into(), key: row_key(&rows[1].1), change: RowChange::Added };
+
+        assert!(diff.to_string().starts_with("+ history User#"));
+        assert!(diff.key.contains(" / Bundle#"));
+    }
+}
@@ -6,14 +6,14 @@ use aws_sdk_dynamodb::operation::query::QueryOutput;
 use aws_sdk_dynamodb::types::Select;
 use base64::Engine;
 use crate::models::transactions::{Transaction, TransactionEvent, TransactionStatus, TransactionStatusView};
-use crate::database::transaction_event::TransactionEventManager;
+use crate::database::event_store::EventStore;
 use tracing::{debug, info};
 use crate::models::errors::TransactionError;
 
 pub struct TransactionStatusViewManager {
     table_name: String,
     dynamo_db_client: Arc<DynamoDbClient>,
-    tem: Arc<TransactionEventManager>,
+    tem: Arc<dyn EventStore>,
 }
 
 pub struct WalletQueryResult {
@@ -22,29 +22,40 @@ pub struct WalletQueryResult {
 }
 
 impl TransactionStatusViewManager {
-    pub fn new(table_name: String, dynamo_db_client: Arc<DynamoDbClient>, tem: Arc<TransactionEventManager>) -> Self {
+    pub fn new(table_name: String, dynamo_db_client: Arc<DynamoDbClient>, tem: Arc<dyn EventStore>) -> Self {
         Self { table_name, dynamo_db_client, tem }
     }
 
     pub async fn project(&self, bundle_id: &str) -> Result<(), anyhow::Error> {
         let latest_event = self.tem.get_latest_event(bundle_id).await?;
-        let tx = &latest_event.bundle_snapshot.main_tx;
+        self.project_event(&latest_event).await
+    }
 
-        let item = self.to_dynamo_item(&latest_event, tx)?;
-        info!("{:?}", item);
-        self.dynamo_db_client
-            .put_item()
-            .table_name(&self.table_name)
-            .set_item(Some(item))
-            .send()
-            .await?;
+    /// Projects an event that is already in hand, without re-reading the event store.
+    pub async fn project_event(&self, event: &TransactionEvent) -> Result<(), anyhow::Error> {
+        for item in Self::items_for_event(event)? {
+            info!("{:?}", item);
+            self.dynamo_db_client
+                .put_item()
+                .table_name(&self.table_name)
+                .set_item(Some(item))
+                .send()
+                .await?;
+        }
 
-        info!(bundle_id = %bundle_id, status = ?latest_event.bundle_status, "📌 Projected status view");
+        info!(bundle_id = %event.bundle_id, status = ?event.bundle_status, "📌 Projected status view");
         Ok(())
     }
 
+    /// The rows `project_event` writes for an event, one per recipient leg.
+    pub fn items_for_event(event: &TransactionEvent) -> Result<Vec<HashMap<String, AttributeValue>>, anyhow::Error> {
+        let bundle = &event.bundle_snapshot;
+        bundle.recipient_legs()
+            .map(|leg| Self::to_dynamo_item(event, bundle.leg(leg)))
+            .collect()
+    }
+
     fn to_dynamo_item(
-        &self,
         event: &TransactionEvent,
         tx: &Transaction,
     ) -> Result<std::collections::HashMap<String, AttributeValue>, anyhow::Error> {
@@ -250,6 +261,7 @@ impl TransactionStatusViewManager {
 mod tests {
     use crate::utilities::config;
     use crate::utilities::config::get_transaction_event_table;
+    use crate::database::transaction_event::TransactionEventManager;
     use crate::utilities::test::{get_dynamodb_client_with_assumed_role, init_tracing};
     use super::*;
 
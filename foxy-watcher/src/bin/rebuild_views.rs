@@ -0,0 +1,122 @@
+//! Regenerates the status and history views from the event store.
+//!
+//! Usage: rebuild_views (--all | --user <user_id> | --bundle <bundle_id>) [--dry-run]
+
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use aws_sdk_dynamodb::Client as DynamoDbClient;
+use dotenv::dotenv;
+use foxy_shared::database::transaction_event::TransactionEventManager;
+use foxy_shared::utilities::config::{get_history_view_table, get_transaction_event_table, get_transaction_view_table};
+use foxy_shared::views::rebuild::{ProjectionRebuilder, RebuildScope};
+use tracing_subscriber::{EnvFilter, FmtSubscriber};
+
+const USAGE: &str = "Usage: rebuild_views (--all | --user <user_id> | --bundle <bundle_id>) [--dry-run]";
+
+fn parse_args(args: &[String]) -> Result<(RebuildScope, bool), String> {
+    let mut scope = None;
+    let mut dry_run = false;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let next = match arg.as_str() {
+            "--all" => Some(RebuildScope::All),
+            "--user" => Some(RebuildScope::User(args.next().ok_or("--user needs a user ID")?.clone())),
+            "--bundle" => Some(RebuildScope::Bundle(args.next().ok_or("--bundle needs a bundle ID")?.clone())),
+            "--dry-run" => {
+                dry_run = true;
+                None
+            }
+            other => return Err(format!("Unknown argument: {}", other)),
+        };
+
+        if let Some(next) = next {
+            if scope.replace(next).is_some() {
+                return Err("Choose one of --all, --user or --bundle".to_string());
+            }
+        }
+    }
+
+    scope.map(|s| (s, dry_run)).ok_or_else(|| "Choose one of --all, --user or --bundle".to_string())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenv().ok();
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn"));
+    let subscriber = FmtSubscriber::builder().with_env_filter(filter).finish();
+    let _ = tracing::subscriber::set_global_default(subscriber);
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (scope, dry_run) = match parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let config = aws_config::load_from_env().await;
+    let dynamo = Arc::new(DynamoDbClient::new(&config));
+    let tem = TransactionEventManager::new(dynamo.clone(), get_transaction_event_table());
+    let rebuilder = ProjectionRebuilder::new(
+        dynamo,
+        tem,
+        get_transaction_event_table(),
+        get_transaction_view_table(),
+        get_history_view_table(),
+    );
+
+    let report = match rebuilder.run(&scope, dry_run).await {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Rebuild failed: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for row in report.changed() {
+        println!("{}", row);
+    }
+    for (bundle_id, reason) in &report.failures {
+        println!("! {} {}", bundle_id, reason);
+    }
+
+    let verb = if dry_run { "would change" } else { "changed" };
+    println!(
+        "{} bundles, {} rows {}, {} unchanged, {} failed",
+        report.bundles,
+        report.changed().count(),
+        verb,
+        report.rows.len() - report.changed().count(),
+        report.failures.len(),
+    );
+
+    if report.failures.is_empty() { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_scopes_and_dry_run() {
+        assert_eq!(parse_args(&args(&["--all"])), Ok((RebuildScope::All, false)));
+        assert_eq!(parse_args(&args(&["--dry-run", "--user", "u1"])), Ok((RebuildScope::User("u1".into()), true)));
+        assert_eq!(parse_args(&args(&["--bundle", "b1"])), Ok((RebuildScope::Bundle("b1".into()), false)));
+    }
+
+    #[test]
+    fn rejects_missing_or_conflicting_scope() {
+        assert!(parse_args(&args(&[])).is_err());
+        assert!(parse_args(&args(&["--dry-run"])).is_err());
+        assert!(parse_args(&args(&["--all", "--bundle", "b1"])).is_err());
+        assert!(parse_args(&args(&["--user"])).is_err());
+    }
+}
@@ -28,6 +28,9 @@ pub enum WatcherError {
     #[error("Receipt retrieval failed: {0}")]
     ReceiptFetchFailure(String),
 
+    #[error("Block retrieval failed: {0}")]
+    BlockFetchFailure(String),
+
     #[error("Unexpected state: {0}")]
     InvalidState(String),
 
@@ -0,0 +1,157 @@
+use std::sync::Arc;
+use ethers_core::types::{BlockNumber, TransactionReceipt, H256, U256};
+use ethers_providers::{Http, Middleware, Provider};
+use foxy_shared::database::broadcast_claims::BroadcastClaims;
+use foxy_shared::database::event_store::retry_on_conflict;
+use foxy_shared::database::transaction_event::TransactionEventManager;
+use foxy_shared::models::transactions::{FinalityStage, Transaction, TransactionEvent, TransactionLeg};
+use foxy_shared::utilities::finality::{confirmations, stage_of};
+use tracing::{info, warn};
+use crate::errors::WatcherError;
+use crate::poll_confirmations::queue_broadcast;
+
+/// A leg's receipt from a block on the canonical chain, and how final that block is.
+pub struct Mined {
+    pub tx_hash: H256,
+    pub receipt: TransactionReceipt,
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub stage: FinalityStage,
+    pub confirmations: u64,
+}
+
+impl Mined {
+    /// `tx` as this receipt records it. The status is left to the event that records it.
+    pub fn apply_to(&self, tx: &Transaction) -> Transaction {
+        let gas_used = self.receipt.gas_used.and_then(|g| u64::try_from(g).ok());
+        let effective_gas_price = self.receipt.effective_gas_price.and_then(|p| u64::try_from(p).ok());
+        // Optimism receipts add the L1 data fee as `l1Fee`; other chains have none
+        let l1_fee = self.receipt.other.get_deserialized::<U256>("l1Fee")
+            .and_then(Result::ok)
+            .and_then(|fee| u64::try_from(fee).ok());
+
+        tx.clone()
+            .with_transaction_hash(&format!("{:#x}", self.tx_hash))
+            .with_block_number(Some(self.block_number))
+            .with_block_hash(Some(format!("{:#x}", self.block_hash)))
+            .with_finality(Some(self.stage))
+            .with_receipt_status(self.receipt.status.map(|s| s.as_u64()))
+            .with_gas_paid(gas_used, effective_gas_price, l1_fee)
+    }
+
+    /// Whether `tx` already records this block at this stage.
+    fn is_recorded_on(&self, tx: &Transaction) -> bool {
+        tx.block_hash.as_deref().is_some_and(|h| h.eq_ignore_ascii_case(&format!("{:#x}", self.block_hash)))
+            && tx.finality == Some(self.stage)
+    }
+}
+
+/// Finds whichever of `hashes` was mined. Nodes can still serve a receipt from a block that
+/// was reorged out, so one only counts if its block is still canonical.
+pub async fn find_mined(provider: &Provider<Http>, hashes: &[String]) -> Result<Option<Mined>, WatcherError> {
+    for candidate in hashes {
+        let tx_hash = candidate
+            .parse::<H256>()
+            .map_err(|_| WatcherError::InvalidTxHashFormat(candidate.clone()))?;
+
+        let receipt = provider.get_transaction_receipt(tx_hash).await
+            .map_err(|e| WatcherError::ReceiptFetchFailure(format!("Error fetching tx receipt: {e}")))?;
+        let Some(receipt) = receipt else { continue };
+        let (Some(block_number), Some(block_hash)) = (receipt.block_number.map(|b| b.as_u64()), receipt.block_hash) else {
+            continue;
+        };
+
+        if canonical_hash(provider, block_number).await? != Some(block_hash) {
+            warn!(%candidate, block_number, "🔀 Receipt is from a block no longer on the chain");
+            continue;
+        }
+
+        let latest = provider.get_block_number().await
+            .map_err(|e| WatcherError::BlockFetchFailure(format!("Error fetching latest block: {e}")))?;
+        let safe = head(provider, BlockNumber::Safe).await?;
+        let finalized = head(provider, BlockNumber::Finalized).await?;
+
+        return Ok(Some(Mined {
+            tx_hash,
+            block_number,
+            block_hash,
+            stage: stage_of(block_number, safe, finalized),
+            confirmations: confirmations(block_number, latest.as_u64()),
+            receipt,
+        }));
+    }
+
+    Ok(None)
+}
+
+/// Whether the block `tx` was recorded in has been replaced on the chain. A node that does
+/// not have the block yet says nothing either way.
+pub async fn reorged_out(provider: &Provider<Http>, tx: &Transaction) -> Result<bool, WatcherError> {
+    let (Some(block_number), Some(block_hash)) = (tx.block_number, tx.block_hash.as_deref()) else {
+        return Ok(false);
+    };
+
+    Ok(canonical_hash(provider, block_number).await?
+        .is_some_and(|canonical| !format!("{:#x}", canonical).eq_ignore_ascii_case(block_hash)))
+}
+
+/// Records `leg` as mined but not yet final enough to confirm, if its block or stage has
+/// changed since it was last recorded.
+pub async fn record_inclusion(
+    tem: &Arc<TransactionEventManager>,
+    latest_event: TransactionEvent,
+    leg: TransactionLeg,
+    mined: &Mined,
+) -> Result<(), WatcherError> {
+    let leg_tx = latest_event.bundle_snapshot.leg(leg);
+    if mined.is_recorded_on(leg_tx) {
+        return Ok(());
+    }
+    if leg_tx.block_hash.is_some() && leg_tx.block_number != Some(mined.block_number) {
+        warn!(bundle_id = %latest_event.bundle_id, "🔀 {} leg was mined again in block {}", leg, mined.block_number);
+    }
+
+    retry_on_conflict(tem.as_ref(), latest_event, |latest| {
+        let tem = tem.clone();
+        async move { TransactionEvent::on_included(&latest, &mined.apply_to(latest.bundle_snapshot.leg(leg)), tem).await }
+    })
+        .await
+        .map_err(|e| WatcherError::InvalidState(format!("on_included failed: {}", e)))?;
+
+    info!(block = mined.block_number, stage = %mined.stage, confirmations = mined.confirmations, "⏳ {} leg is mined, awaiting confirmation", leg);
+    Ok(())
+}
+
+/// Puts `leg` back to pending after its block was reorged out, and queues it for
+/// rebroadcast in case the transaction did not make it back into the mempool.
+pub async fn record_reorg(
+    tem: &Arc<TransactionEventManager>,
+    claims: &BroadcastClaims,
+    latest_event: TransactionEvent,
+    leg: TransactionLeg,
+) -> Result<(), WatcherError> {
+    warn!(bundle_id = %latest_event.bundle_id, "🔀 {} leg's block was reorged out", leg);
+
+    let reorged = retry_on_conflict(tem.as_ref(), latest_event, |latest| {
+        let tem = tem.clone();
+        async move { TransactionEvent::on_reorged(&latest, leg, tem).await }
+    })
+        .await
+        .map_err(|e| WatcherError::InvalidState(format!("on_reorged failed: {}", e)))?;
+
+    queue_broadcast(claims, &reorged).await;
+    Ok(())
+}
+
+async fn canonical_hash(provider: &Provider<Http>, block_number: u64) -> Result<Option<H256>, WatcherError> {
+    let block = provider.get_block(block_number).await
+        .map_err(|e| WatcherError::BlockFetchFailure(format!("Error fetching block {block_number}: {e}")))?;
+    Ok(block.and_then(|b| b.hash))
+}
+
+/// The number of the chain's `safe` or `finalized` head.
+async fn head(provider: &Provider<Http>, tag: BlockNumber) -> Result<Option<u64>, WatcherError> {
+    let block = provider.get_block(tag).await
+        .map_err(|e| WatcherError::BlockFetchFailure(format!("Error fetching {tag} block: {e}")))?;
+    Ok(block.and_then(|b| b.number).map(|n| n.as_u64()))
+}
@@ -6,21 +6,31 @@ use dotenv::dotenv;
 use ethers_providers::{Http, Provider};
 use foxy_shared::services::cloudwatch_services::OperationMetricTracker;
 use foxy_shared::models::errors::AppError;
+use foxy_shared::database::broadcast_claims::BroadcastClaims;
+use foxy_shared::database::nonce_leases::NonceLeases;
 use foxy_shared::database::transaction_event::TransactionEventManager;
-use foxy_shared::utilities::config::{get_rpc_url, get_transaction_event_table, get_transaction_view_table, get_user_device_table};
+use foxy_shared::utilities::config::{get_confirmation_policy, get_max_rebroadcasts, get_rpc_url_for, get_stuck_after, get_supported_networks, get_transaction_event_table, get_transaction_view_table, get_user_device_table};
 use tokio::signal;
 use tokio::sync::Notify;
 use tracing::{info, error};
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
 use foxy_shared::repositories::device_repository::DynamoDeviceRepository;
 use foxy_shared::services::notification_services::FirebaseClient;
+use foxy_shared::views::outbox::ProjectionOutbox;
 use foxy_shared::views::status_view::TransactionStatusViewManager;
 use crate::errors::WatcherError;
 use crate::poll_confirmations::poll_confirmations;
 use crate::poll_finalizations::poll_finalizations;
+use crate::poll_expirations::poll_expirations;
+use crate::poll_outbox::poll_outbox;
+use crate::stuck::StuckPolicy;
 
+mod finality;
 mod poll_confirmations;
 mod poll_finalizations;
+mod poll_expirations;
+mod poll_outbox;
+mod stuck;
 mod watcher_tests;
 mod errors;
 
@@ -37,7 +47,6 @@ async fn main() -> Result<(), WatcherError> {
 
     info!("🚀 Starting Foxy Watcher...");
 
-    let provider = Arc::new(Provider::<Http>::try_from(get_rpc_url())?);
     let config = aws_config::load_from_env().await;
     let dynamo = Arc::new(DynamoDbClient::new(&config));
     let tem = TransactionEventManager::new(dynamo.clone(), get_transaction_event_table());
@@ -57,62 +66,127 @@ async fn main() -> Result<(), WatcherError> {
             .await,
     );
 
+    let claims = BroadcastClaims::new(dynamo.clone(), get_transaction_event_table());
+    let policy = get_confirmation_policy();
+    info!(?policy, "🧱 Confirming legs once their block meets the policy");
+    let stuck = StuckPolicy { after: get_stuck_after(), max_rebroadcasts: get_max_rebroadcasts() };
+    info!(?stuck, "🧊 Rebroadcasting legs the node dropped");
+
     let shutdown_notify = Arc::new(Notify::new());
     let shutdown_signal = shutdown_notify.clone();
-    let tem1 = tem.clone();
-    let tsm1 = tsm.clone();
-    let provider1 = provider.clone();
-
-    let confirm_handle = {
+    let mut handles = Vec::new();
+
+    // Receipts live on the chain a leg was sent to, so each network gets its own polls
+    for network in get_supported_networks() {
+        let provider = Arc::new(Provider::<Http>::try_from(get_rpc_url_for(&network))?);
+
+        let tem1 = tem.clone();
+        let tsm1 = tsm.clone();
+        let provider1 = provider.clone();
+        let network1 = network.clone();
+        let claims1 = claims.clone();
+        let firebase1 = firebase.clone();
+        handles.push({
+            let shutdown = shutdown_notify.clone();
+
+            tokio::spawn(async move {
+                loop {
+                    let tracker = OperationMetricTracker::build("WatcherConfirmation").await;
+
+                    match poll_confirmations(&network1, &provider1, &tem1, &tsm1, &claims1, &policy, &stuck, firebase1.clone()).await {
+                        Ok(count) => info!("🔍 Confirmed {} transactions on {}", count, network1),
+                        Err(e) => error!(?e, %network1, "Watcher error during confirmation poll"),
+                    }
+
+                    tracker.track::<(), AppError>(&Ok(()), None).await;
+                    tokio::select! {
+                        _ = tokio::time::sleep(Duration::from_secs(15)) => {},
+                        _ = shutdown.notified() => break,
+                    }
+                }
+            })
+        });
+
+        let tem2 = tem.clone();
+        let tsm2 = tsm.clone();
+        let provider2 = provider.clone();
+        let network2 = network.clone();
+        let claims2 = claims.clone();
+        let firebase2 = firebase.clone();
+        handles.push({
+            let shutdown = shutdown_notify.clone();
+            tokio::spawn(async move {
+                loop {
+                    let tracker = OperationMetricTracker::build("WatcherFinalizer").await;
+
+                    match poll_finalizations(&network2, &provider2, &tem2, &tsm2, &claims2, &policy, &stuck, firebase2.clone()).await {
+                        Ok(count) => info!("🔒 Finalized {} transactions on {}", count, network2),
+                        Err(e) => error!(?e, %network2, "Watcher error during finalization poll"),
+                    }
+
+                    tracker.track::<(), AppError>(&Ok(()), None).await;
+                    tokio::select! {
+                        _ = tokio::time::sleep(Duration::from_secs(600)) => {},
+                        _ = shutdown.notified() => break,
+                    }
+                }
+            })
+        });
+    }
+
+    let tem3 = tem.clone();
+    let tsm3 = tsm.clone();
+    let leases = NonceLeases::new(dynamo.clone(), get_transaction_event_table());
+    handles.push({
         let shutdown = shutdown_notify.clone();
-
         tokio::spawn(async move {
             loop {
-                let tracker = OperationMetricTracker::build("WatcherConfirmation").await;
+                let tracker = OperationMetricTracker::build("WatcherExpiry").await;
 
-                match poll_confirmations(&provider1, &tem1, &tsm1, firebase.clone()).await {
-                    Ok(count) => info!("🔍 Confirmed {} transactions", count),
-                    Err(e) => error!(?e, "Watcher error during confirmation poll"),
+                match poll_expirations(&tem3, &tsm3, &leases).await {
+                    Ok(count) => info!("⌛ Expired {} bundles", count),
+                    Err(e) => error!(?e, "Watcher error during expiry poll"),
                 }
 
                 tracker.track::<(), AppError>(&Ok(()), None).await;
                 tokio::select! {
-                    _ = tokio::time::sleep(Duration::from_secs(15)) => {},
+                    _ = tokio::time::sleep(Duration::from_secs(60)) => {},
                     _ = shutdown.notified() => break,
                 }
             }
         })
-    };
+    });
 
-    let tem2 = tem.clone();
-    let tsm2 = tsm.clone();
-    let provider2 = provider.clone();
-    let finalize_handle = {
+    let tem4 = tem.clone();
+    let outbox = ProjectionOutbox::new(dynamo.clone(), get_transaction_event_table());
+    handles.push({
         let shutdown = shutdown_notify.clone();
         tokio::spawn(async move {
             loop {
-                let tracker = OperationMetricTracker::build("WatcherFinalizer").await;
+                let tracker = OperationMetricTracker::build("WatcherOutbox").await;
 
-                match poll_finalizations(&provider2, &tem2, &tsm2).await {
-                    Ok(count) => info!("🔒 Finalized {} transactions", count),
-                    Err(e) => error!(?e, "Watcher error during finalization poll"),
+                match poll_outbox(&tem4, &outbox).await {
+                    Ok(count) => info!("📬 Applied {} pending projections", count),
+                    Err(e) => error!(?e, "Watcher error during outbox poll"),
                 }
 
                 tracker.track::<(), AppError>(&Ok(()), None).await;
                 tokio::select! {
-                    _ = tokio::time::sleep(Duration::from_secs(600)) => {},
+                    _ = tokio::time::sleep(Duration::from_secs(10)) => {},
                     _ = shutdown.notified() => break,
                 }
             }
         })
-    };
+    });
 
     // Graceful shutdown
     signal::ctrl_c().await?;
     info!("🛑 Received shutdown signal, terminating...");
     shutdown_signal.notify_waiters();
 
-    let _ = tokio::try_join!(confirm_handle, finalize_handle);
+    for handle in handles {
+        let _ = handle.await;
+    }
 
     Ok(())
 }
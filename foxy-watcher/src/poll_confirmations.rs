@@ -1,20 +1,41 @@
 use std::sync::Arc;
-use ethers_core::types::H256;
-use ethers_providers::{Http, Middleware, Provider};
+use ethers_core::types::{BlockId, BlockNumber, TransactionRequest, H256, U64};
+use ethers_providers::{Http, Middleware, Provider, RpcError};
+use foxy_shared::database::broadcast_claims::BroadcastClaims;
+use foxy_shared::database::nonce_leases::NonceLeases;
+use foxy_shared::database::event_store::{retry_on_conflict, EventStore};
 use foxy_shared::database::transaction_event::TransactionEventManager;
 use foxy_shared::models::errors::AppError;
-use foxy_shared::models::transactions::{TransactionStatus, TransactionEvent, TransactionLeg};
+use foxy_shared::models::state_machine;
+use foxy_shared::models::transactions::{EventType, Network, TransactionStatus, TransactionEvent, TransactionLeg};
 use foxy_shared::services::cloudwatch_services::OperationMetricTracker;
 use tracing::{error, info};
 use foxy_shared::models::notifications::NotificationPayload;
 use foxy_shared::services::notification_services::FirebaseClient;
+use foxy_shared::services::queue_services::{get_sqs_client, push_to_broadcast_queue};
+use foxy_shared::utilities::config::{get_broadcast_queue, get_transaction_event_table};
+use foxy_shared::utilities::finality::ConfirmationPolicy;
+use foxy_shared::utilities::revert::decode_revert_reason;
 use foxy_shared::views::status_view::TransactionStatusViewManager;
 use crate::errors::WatcherError;
+use crate::finality::{find_mined, record_inclusion, record_reorg, reorged_out, Mined};
+use crate::stuck::{rebroadcast_if_stuck, StuckPolicy};
 
+/// Confirms pending recipient legs on `network`; other networks are left to their own poll.
+/// A mined leg is only settled once its block meets `policy`; until then it is recorded as
+/// included, and put back to pending if its block is reorged out. A leg the node dropped
+/// before it was mined is resent under `stuck`. Legs go out one at a time,
+/// so each confirmation queues the bundle's next leg, the fee leg last. A leg whose receipt
+/// reverted fails the bundle instead, and nothing after it is sent.
+#[allow(clippy::too_many_arguments)]
 pub async fn poll_confirmations(
+    network: &Network,
     provider: &Arc<Provider<Http>>,
     tem: &Arc<TransactionEventManager>,
     tsm: &Arc<TransactionStatusViewManager>,
+    claims: &BroadcastClaims,
+    policy: &ConfirmationPolicy,
+    stuck: &StuckPolicy,
     firebase: Arc<FirebaseClient>,
 ) -> Result<u32, WatcherError> {
     let mut count = 0;
@@ -48,53 +69,206 @@ pub async fn poll_confirmations(
             .await
             .map_err(|e| WatcherError::ReceiptFetchFailure(format!("Failed to load latest event: {}", e)))?;
 
-        if latest_event.bundle_snapshot.main_tx.status == TransactionStatus::Confirmed {
+        // Each recipient leg has its own row, keyed by its transaction id
+        let leg = view.pk.strip_prefix("Transaction#")
+            .and_then(|transaction_id| latest_event.bundle_snapshot.leg_of(transaction_id));
+        let Some(leg) = leg else {
+            error!(pk = %view.pk, %bundle_id, "⛔ View does not match a leg of its bundle");
+            continue;
+        };
+        let leg_tx = latest_event.bundle_snapshot.leg(leg);
+
+        if leg_tx.network != *network {
+            continue;
+        }
+
+        if leg_tx.status == TransactionStatus::Confirmed {
             info!("✅ Already confirmed, skipping");
+            // The view lags the log; queue the next leg in case the last poll stopped short of it
+            if latest_event.event_type == EventType::Confirm && latest_event.leg == Some(leg) {
+                queue_next_leg(claims, &latest_event).await;
+            }
             continue;
         }
 
-        let parsed_hash = tx_hash
-            .parse::<H256>()
-            .map_err(|_| WatcherError::InvalidTxHashFormat(tx_hash.clone()))?;
+        // A sped-up leg may be mined under any hash it has had
+        let mut candidates = vec![tx_hash.clone()];
+        for hash in leg_tx.transaction_hash.iter().chain(&leg_tx.replaced_tx_hashes) {
+            if !candidates.iter().any(|c| c.eq_ignore_ascii_case(hash)) {
+                candidates.push(hash.clone());
+            }
+        }
 
-        match provider.get_transaction_receipt(parsed_hash).await {
-            Ok(Some(receipt)) => {
-                if latest_event.leg != Some(TransactionLeg::Main) {
-                    info!("⏭️ Skipping non-main leg: {:?}", latest_event.leg);
-                    continue;
-                }
+        let Some(mined) = find_mined(provider, &candidates).await? else {
+            // In a block when last checked, and in none now
+            if latest_event.leg == Some(leg) && reorged_out(provider, leg_tx).await? {
+                record_reorg(tem, claims, latest_event, leg).await?;
+            } else {
+                rebroadcast_if_stuck(provider, tem, &tracker, stuck, latest_event, leg, &candidates).await?;
+            }
+            continue;
+        };
 
-                let tx = &latest_event.bundle_snapshot.main_tx;
+        if latest_event.leg != Some(leg) {
+            info!("⏭️ Skipping leg {} while the latest event is on {:?}", leg, latest_event.leg);
+            continue;
+        }
 
-                let updated_tx = tx
-                    .clone()
-                    .with_status(TransactionStatus::Confirmed)
-                    .with_block_number(receipt.block_number.map(|b| b.as_u64()));
+        // Nothing is settled, success or failure, until the block is final enough
+        if !policy.is_met(mined.stage, mined.confirmations) {
+            record_inclusion(tem, latest_event, leg, &mined).await?;
+            continue;
+        }
 
-                let confirmed_event = TransactionEvent::on_confirmed(&latest_event, &updated_tx, tem.clone())
-                    .await
-                    .map_err(|e| WatcherError::InvalidState(format!("on_confirmed failed: {}", e)))?;
+        if mined.receipt.status == Some(U64::zero()) {
+            fail_reverted(provider, tem, &firebase, latest_event, leg, &mined).await?;
+            continue;
+        }
 
-                count += 1;
+        if !leg_tx.transfer_logged(&mined.receipt.logs) {
+            error!(%bundle_id, %tx_hash, "❌ {} tx receipt has no matching Transfer log", leg);
+            let failed_event = retry_on_conflict(tem.as_ref(), latest_event, |latest| {
+                let tem = tem.clone();
+                async move { TransactionEvent::on_fail(&latest, leg, tem).await }
+            })
+                .await
+                .map_err(|e| WatcherError::InvalidState(format!("on_fail failed: {}", e)))?;
+            release_nonces(tem, &failed_event).await;
+            continue;
+        }
 
-                // 🔔 Attempt to notify the recipient
-                firebase
-                    .notify_transaction_confirmed(&confirmed_event.bundle_snapshot)
-                    .await
-                    .map_err(|e| WatcherError::PushFailed(format!("Push notification error: {e}")))?;
-            }
-            Ok(None) => {
-                // Still pending, do nothing
-                continue;
-            }
-            Err(err) => {
-                return Err(WatcherError::ReceiptFetchFailure(format!(
-                    "Error fetching tx receipt: {err}"
-                )));
-            }
+        let confirmed_event = retry_on_conflict(tem.as_ref(), latest_event, |latest| {
+            let tem = tem.clone();
+            let updated_tx = mined.apply_to(latest.bundle_snapshot.leg(leg)).with_status(TransactionStatus::Confirmed);
+            async move { TransactionEvent::on_confirmed(&latest, &updated_tx, tem).await }
+        })
+            .await
+            .map_err(|e| WatcherError::InvalidState(format!("on_confirmed failed: {}", e)))?;
+
+        info!(%bundle_id, block = mined.block_number, stage = %mined.stage, "✅ Confirmed {} leg", leg);
+        count += 1;
+
+        // How close the quote came to what the leg really cost
+        if let Some(percent) = confirmed_event.bundle_snapshot.leg(leg).fee_paid_percent_of_estimate() {
+            tracker.emit("NetworkFeeVsEstimate", percent, "Percent", &[("Network", network.to_string().as_str())]).await;
         }
+        queue_next_leg(claims, &confirmed_event).await;
+
+        if leg != TransactionLeg::Main {
+            continue;
+        }
+
+        // 🔔 Attempt to notify the recipient
+        firebase
+            .notify_transaction_confirmed(&confirmed_event.bundle_snapshot)
+            .await
+            .map_err(|e| WatcherError::PushFailed(format!("Push notification error: {e}")))?;
     }
 
     tracker.track::<(), AppError>(&Ok(()), None).await;
     Ok(count)
 }
+
+/// Hands the bundle back to the broadcaster for the leg after `event`, once per event. A
+/// zero-value fee leg is queued too; the broadcaster skips it.
+async fn queue_next_leg(claims: &BroadcastClaims, event: &TransactionEvent) {
+    if state_machine::next_leg(event, EventType::Broadcast).is_err() {
+        return;
+    }
+
+    queue_broadcast(claims, event).await;
+}
+
+/// Queues the bundle for the broadcaster once per event. A failure is only logged: the claim
+/// is released, so the bundle can be queued again.
+pub(crate) async fn queue_broadcast(claims: &BroadcastClaims, event: &TransactionEvent) {
+    match claims.claim(event).await {
+        Ok(true) => {}
+        Ok(false) => {
+            info!(bundle_id = %event.bundle_id, sequence = event.sequence, "⏭️ Already queued for broadcast");
+            return;
+        }
+        Err(e) => {
+            error!(bundle_id = %event.bundle_id, "❌ Failed to claim the bundle for broadcast: {}", e);
+            return;
+        }
+    }
+
+    let queued = match get_sqs_client().await {
+        Ok(sqs_client) => push_to_broadcast_queue(&sqs_client, &get_broadcast_queue(), &event.bundle_id, &event.user_id)
+            .await
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+
+    match queued {
+        Ok(()) => info!(bundle_id = %event.bundle_id, "📤 Queued for broadcast"),
+        Err(e) => {
+            error!(bundle_id = %event.bundle_id, "❌ Failed to queue for broadcast: {}", e);
+            if let Err(e) = claims.release(event).await {
+                error!(bundle_id = %event.bundle_id, "❌ Failed to release the broadcast claim: {}", e);
+            }
+        }
+    }
+}
+
+/// Fails the bundle over `leg`, whose receipt reverted, frees the nonces it will now never
+/// use and tells both parties.
+pub(crate) async fn fail_reverted(
+    provider: &Provider<Http>,
+    tem: &Arc<TransactionEventManager>,
+    firebase: &FirebaseClient,
+    latest_event: TransactionEvent,
+    leg: TransactionLeg,
+    mined: &Mined,
+) -> Result<(), WatcherError> {
+    let bundle_id = latest_event.bundle_id.clone();
+    let reason = revert_reason(provider, mined.tx_hash, Some(mined.block_number)).await;
+    error!(%bundle_id, tx_hash = %format!("{:#x}", mined.tx_hash), ?reason, "❌ {} tx reverted", leg);
+
+    let failed_event = retry_on_conflict(tem.as_ref(), latest_event, |latest| {
+        let tem = tem.clone();
+        let reverted_tx = mined.apply_to(latest.bundle_snapshot.leg(leg)).with_failure_reason(reason.clone());
+        async move { TransactionEvent::on_reverted(&latest, &reverted_tx, tem).await }
+    })
+        .await
+        .map_err(|e| WatcherError::InvalidState(format!("on_reverted failed: {}", e)))?;
+    release_nonces(tem, &failed_event).await;
+
+    // 🔔 Tell both parties the payment did not go through
+    firebase
+        .notify_transaction_failed(&failed_event.bundle_snapshot, leg)
+        .await
+        .map_err(|e| WatcherError::PushFailed(format!("Push notification error: {e}")))
+}
+
+/// Frees the nonces of the legs a failed or errored bundle will now never send. A failure
+/// is only logged: a later reservation drops the leases of finished bundles anyway.
+pub(crate) async fn release_nonces(tem: &Arc<TransactionEventManager>, event: &TransactionEvent) {
+    let leases = NonceLeases::new(tem.client(), get_transaction_event_table());
+    if let Err(e) = leases.release(&event.bundle_snapshot).await {
+        error!(bundle_id = %event.bundle_id, "❌ Failed to release nonces: {}", e);
+    }
+}
+
+/// Why `tx_hash` reverted, found by replaying it as a call against the block before it was
+/// mined. `None` when the node will not say or the revert data is not a readable reason.
+async fn revert_reason(provider: &Provider<Http>, tx_hash: H256, block: Option<u64>) -> Option<String> {
+    let tx = provider.get_transaction(tx_hash).await.ok()??;
+    let mut call = TransactionRequest::new()
+        .from(tx.from)
+        .value(tx.value)
+        .data(tx.input)
+        .gas(tx.gas);
+    if let Some(to) = tx.to {
+        call = call.to(to);
+    }
+    let at = block
+        .and_then(|b| b.checked_sub(1))
+        .map(|b| BlockId::Number(BlockNumber::Number(b.into())));
+
+    match provider.call(&call.into(), at).await {
+        Ok(_) => None,
+        Err(e) => decode_revert_reason(&e.as_error_response()?.as_revert_data()?),
+    }
+}
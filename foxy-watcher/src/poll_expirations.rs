@@ -0,0 +1,60 @@
+use std::sync::Arc;
+use chrono::Utc;
+use foxy_shared::database::event_store::{retry_on_conflict, EventStore};
+use foxy_shared::database::nonce_leases::NonceLeases;
+use foxy_shared::database::transaction_event::TransactionEventManager;
+use foxy_shared::models::errors::TransactionError;
+use foxy_shared::models::transactions::{TransactionEvent, TransactionStatus};
+use foxy_shared::utilities::config::get_bundle_ttl;
+use foxy_shared::views::status_view::TransactionStatusViewManager;
+use tracing::{error, info};
+use crate::errors::WatcherError;
+
+pub async fn poll_expirations(
+    tem: &Arc<TransactionEventManager>,
+    tsm: &Arc<TransactionStatusViewManager>,
+    leases: &NonceLeases,
+) -> Result<u32, WatcherError> {
+    let mut count = 0;
+    let ttl = get_bundle_ttl();
+
+    // Bundles that were initiated but never signed still have main_tx in Created
+    let created_views = tsm.query_by_transaction_status(TransactionStatus::Created).await?;
+
+    for view in created_views {
+        let Some(bundle_id) = view.bundle_id.clone() else {
+            error!(?view, "⛔ View is missing BundleID");
+            continue;
+        };
+
+        let Ok(latest_event) = tem.get_latest_event(&bundle_id).await else {
+            error!(%bundle_id, "⚠️ Failed to load latest event");
+            continue;
+        };
+
+        if !latest_event.bundle_snapshot.is_expired(Utc::now(), ttl) {
+            continue;
+        }
+
+        let expired = retry_on_conflict(tem.as_ref(), latest_event, |latest| {
+            let tem = tem.clone();
+            async move { TransactionEvent::on_expire(&latest, tem).await }
+        }).await;
+
+        match expired {
+            Ok(expired) => {
+                info!(%bundle_id, "⌛ Bundle expired before it was signed");
+                count += 1;
+
+                if let Err(e) = leases.release(&expired.bundle_snapshot).await {
+                    error!(%bundle_id, ?e, "❌ Failed to release nonces");
+                }
+            }
+            // Signed or cancelled while we were looking at it
+            Err(TransactionError::InvalidStateTransition { .. }) => continue,
+            Err(e) => error!(%bundle_id, ?e, "❌ Failed to expire bundle"),
+        }
+    }
+
+    Ok(count)
+}
@@ -1,28 +1,41 @@
 use std::sync::Arc;
-use ethers_core::types::H256;
-use ethers_providers::{Http, Middleware, Provider};
+use ethers_providers::{Http, Provider};
+use foxy_shared::database::broadcast_claims::BroadcastClaims;
+use foxy_shared::database::event_store::{retry_on_conflict, EventStore};
 use foxy_shared::database::transaction_event::TransactionEventManager;
-use foxy_shared::models::transactions::{TransactionStatus, TransactionEvent, TransactionLeg};
+use foxy_shared::models::transactions::{Network, TransactionStatus, TransactionEvent, TransactionLeg};
+use foxy_shared::services::cloudwatch_services::OperationMetricTracker;
+use foxy_shared::services::notification_services::FirebaseClient;
+use foxy_shared::utilities::finality::ConfirmationPolicy;
 use tracing::{error, info};
 use foxy_shared::views::status_view::TransactionStatusViewManager;
+use crate::finality::{find_mined, record_inclusion, record_reorg, reorged_out};
+use crate::poll_confirmations::{fail_reverted, release_nonces};
+use crate::stuck::{rebroadcast_if_stuck, StuckPolicy};
 use crate::WatcherError;
 
+/// Finalizes confirmed fee legs on `network`, once their block meets `policy`. A fee leg
+/// the node dropped before it was mined is resent under `stuck`, and one whose receipt
+/// reverted fails the bundle.
+#[allow(clippy::too_many_arguments)]
 pub async fn poll_finalizations(
+    network: &Network,
     provider: &Provider<Http>,
     tem: &Arc<TransactionEventManager>,
     tsm: &Arc<TransactionStatusViewManager>,
+    claims: &BroadcastClaims,
+    policy: &ConfirmationPolicy,
+    stuck: &StuckPolicy,
+    firebase: Arc<FirebaseClient>,
 ) -> Result<u32, WatcherError> {
     let mut count = 0;
+    let tracker = OperationMetricTracker::build("WatcherFinalizer").await;
 
     // Load all bundles where status is MainConfirmed (i.e., main_tx is confirmed, fee_tx is next)
     let confirmed_views = tsm.query_by_transaction_status(TransactionStatus::Confirmed).await?;
 
     for view in confirmed_views {
         let bundle_id = view.bundle_id.clone().unwrap_or_else(|| "<missing>".to_string());
-        let Some(tx_hash) = &view.tx_hash else {
-            error!(?view, "⛔ View is missing TxHash");
-            continue;
-        };
 
         let Ok(latest_event) = tem.get_latest_event(&bundle_id).await else {
             error!(bundle_id = %view.pk, "⚠️ Failed to load latest event");
@@ -34,56 +47,79 @@ pub async fn poll_finalizations(
             continue; // We're only interested in fee confirmations at this stage
         }
 
-        if latest_event.bundle_snapshot.fee_tx.status == TransactionStatus::Confirmed {
+        let fee_tx = &latest_event.bundle_snapshot.fee_tx;
+        if fee_tx.network != *network {
+            continue;
+        }
+
+        if fee_tx.status == TransactionStatus::Confirmed {
             info!("✅ Already finalised, skipping");
             continue;
         }
 
-        let parsed_hash = match tx_hash.parse::<H256>() {
-            Ok(h) => h,
-            Err(e) => {
-                error!(?e, "❌ Invalid tx hash: {}", tx_hash);
-                continue;
-            }
+        // The view is the recipient leg's; the fee leg has hashes of its own
+        let Some(tx_hash) = fee_tx.transaction_hash.clone() else {
+            continue; // Not broadcast yet
         };
+        let candidates: Vec<String> = std::iter::once(tx_hash.clone())
+            .chain(fee_tx.replaced_tx_hashes.iter().cloned())
+            .collect();
 
         // Look up the transaction receipt
-        match provider.get_transaction_receipt(parsed_hash).await {
-            Ok(Some(receipt)) => {
-                let block = receipt.block_number.map(|b| b.as_u64());
-                let status = receipt.status.map(|s| s.as_u64());
+        let mined = match find_mined(provider, &candidates).await {
+            Ok(mined) => mined,
+            Err(err) => {
+                error!(?err, "❌ Error fetching tx receipt");
+                continue;
+            }
+        };
+        let Some(mined) = mined else {
+            if reorged_out(provider, fee_tx).await? {
+                record_reorg(tem, claims, latest_event, TransactionLeg::Fee).await?;
+            } else {
+                rebroadcast_if_stuck(provider, tem, &tracker, stuck, latest_event, TransactionLeg::Fee, &candidates).await?;
+            }
+            continue; // Still pending
+        };
 
-                // Apply confirmation logic (e.g., status == 1)
-                if status != Some(1) {
-                    error!(tx_hash = %tx_hash, "❌ Fee tx receipt has failure status: {:?}", status);
-                    continue;
-                }
-                if latest_event.leg != Some(TransactionLeg::Fee) {
-                    info!("⏭️ Skipping non-fee leg: {:?}", latest_event.leg);
-                    continue;
-                }
+        if !policy.is_met(mined.stage, mined.confirmations) {
+            record_inclusion(tem, latest_event, TransactionLeg::Fee, &mined).await?;
+            continue;
+        }
 
-                let tx = &latest_event.bundle_snapshot.fee_tx;
+        // Apply confirmation logic (e.g., status == 1)
+        let status = mined.receipt.status.map(|s| s.as_u64());
+        if status == Some(0) {
+            fail_reverted(provider, tem, &firebase, latest_event, TransactionLeg::Fee, &mined).await?;
+            continue;
+        }
+        if status != Some(1) {
+            error!(tx_hash = %tx_hash, "❌ Fee tx receipt has failure status: {:?}", status);
+            continue;
+        }
+        if !fee_tx.transfer_logged(&mined.receipt.logs) {
+            error!(tx_hash = %tx_hash, "❌ Fee tx receipt has no matching Transfer log");
+            let failed_event = retry_on_conflict(tem.as_ref(), latest_event, |latest| {
+                let tem = tem.clone();
+                async move { TransactionEvent::on_fail(&latest, TransactionLeg::Fee, tem).await }
+            }).await?;
+            release_nonces(tem, &failed_event).await;
+            continue;
+        }
 
-                // Build new event for fee confirmation
-                let updated_tx = tx
-                    .clone()
-                    .with_status(TransactionStatus::Confirmed)
-                    .with_block_number(block)
-                    .with_receipt_status(status);
+        // Build new event for fee confirmation, rebuilding from the latest event on conflict
+        let confirmed_event = retry_on_conflict(tem.as_ref(), latest_event, |latest| {
+            let tem = tem.clone();
+            let updated_tx = mined.apply_to(&latest.bundle_snapshot.fee_tx).with_status(TransactionStatus::Confirmed);
+            async move { TransactionEvent::on_confirmed(&latest, &updated_tx, tem).await }
+        }).await?;
 
-                let confirmed_event = TransactionEvent::on_confirmed(&latest_event, &updated_tx, tem.clone()).await?;
-                tem.clone().persist(&confirmed_event).await?;
+        info!(tx_hash = %tx_hash, block = mined.block_number, stage = %mined.stage, "✅ Finalized fee leg");
+        count += 1;
 
-                info!(tx_hash = %tx_hash, block = ?block, "✅ Finalized fee leg");
-                count += 1;
-            }
-            Ok(None) => {
-                continue; // Still pending
-            }
-            Err(err) => {
-                error!(?err, "❌ Error fetching tx receipt");
-            }
+        // How close the quote came to what the leg really cost
+        if let Some(percent) = confirmed_event.bundle_snapshot.fee_tx.fee_paid_percent_of_estimate() {
+            tracker.emit("NetworkFeeVsEstimate", percent, "Percent", &[("Network", network.to_string().as_str())]).await;
         }
     }
 
@@ -0,0 +1,15 @@
+use std::sync::Arc;
+use foxy_shared::database::transaction_event::TransactionEventManager;
+use foxy_shared::views::outbox::{drain, ProjectionOutbox};
+use crate::errors::WatcherError;
+
+const DRAIN_BATCH: i32 = 100;
+
+/// Retries view projections that did not apply when their event was appended.
+pub async fn poll_outbox(
+    tem: &Arc<TransactionEventManager>,
+    outbox: &ProjectionOutbox,
+) -> Result<u32, WatcherError> {
+    let applied = drain(outbox, tem.client(), tem.clone(), DRAIN_BATCH).await?;
+    Ok(applied)
+}
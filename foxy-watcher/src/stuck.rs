@@ -0,0 +1,120 @@
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use ethers_core::types::{Bytes, H256};
+use ethers_providers::{Http, Middleware, Provider};
+use foxy_shared::database::event_store::retry_on_conflict;
+use foxy_shared::database::transaction_event::TransactionEventManager;
+use foxy_shared::models::transactions::{EventType, TransactionEvent, TransactionLeg};
+use foxy_shared::services::cloudwatch_services::OperationMetricTracker;
+use tracing::{error, info, warn};
+use crate::errors::WatcherError;
+use crate::poll_confirmations::release_nonces;
+
+/// When a sent leg with no receipt is checked for having been dropped, and how many times
+/// a bundle's dropped legs are resent before someone has to look at it.
+#[derive(Debug, Clone, Copy)]
+pub struct StuckPolicy {
+    pub after: Duration,
+    pub max_rebroadcasts: u32,
+}
+
+/// Resends `leg` if it has been pending for longer than `policy.after` since it was last
+/// sent, and the node knows none of its `hashes`. A leg the node still holds is only slow,
+/// and speeding it up is the sender's call. A resend the node rejects, or one past the
+/// bundle's allowance, errors the leg and raises the `StuckTransactionEscalated` alarm.
+pub async fn rebroadcast_if_stuck(
+    provider: &Provider<Http>,
+    tem: &Arc<TransactionEventManager>,
+    tracker: &OperationMetricTracker,
+    policy: &StuckPolicy,
+    latest_event: TransactionEvent,
+    leg: TransactionLeg,
+    hashes: &[String],
+) -> Result<(), WatcherError> {
+    // Only the event that last sent the leg says how long it has been waiting
+    let sent = matches!(
+        latest_event.event_type,
+        EventType::Broadcast | EventType::Replace | EventType::Reorg | EventType::Rebroadcast
+    );
+    if !sent || latest_event.leg != Some(leg) || Utc::now() - latest_event.created_at < policy.after {
+        return Ok(());
+    }
+
+    if is_known(provider, hashes).await? {
+        return Ok(());
+    }
+
+    let bundle_id = latest_event.bundle_id.clone();
+    let leg_tx = latest_event.bundle_snapshot.leg(leg);
+    let network = leg_tx.network.to_string();
+    let attempts = latest_event.bundle_snapshot.rebroadcast_attempts;
+
+    if attempts >= policy.max_rebroadcasts {
+        error!(%bundle_id, attempts, "🧊 {} leg was dropped again after every rebroadcast", leg);
+        return escalate(tem, tracker, latest_event, leg, &network).await;
+    }
+
+    let Some(tx_bytes) = leg_tx.signed_tx_bytes() else {
+        error!(%bundle_id, "🧊 {} leg was dropped and has no signed transaction to resend", leg);
+        return escalate(tem, tracker, latest_event, leg, &network).await;
+    };
+
+    warn!(%bundle_id, attempts, "🧊 {} leg was dropped by the node, rebroadcasting", leg);
+    match provider.send_raw_transaction(Bytes::from(tx_bytes)).await {
+        Ok(pending) => {
+            info!(%bundle_id, tx_hash = %format!("{:#x}", pending.tx_hash()), "📡 Rebroadcast {} leg", leg);
+            retry_on_conflict(tem.as_ref(), latest_event, |latest| {
+                let tem = tem.clone();
+                async move { TransactionEvent::on_rebroadcast(&latest, leg, tem).await }
+            })
+                .await
+                .map_err(|e| WatcherError::InvalidState(format!("on_rebroadcast failed: {}", e)))?;
+            Ok(())
+        }
+        Err(e) => {
+            // The node may have taken it back in meanwhile, e.g. from another watcher
+            if is_known(provider, hashes).await? {
+                return Ok(());
+            }
+            error!(%bundle_id, "❌ Rebroadcast of {} leg rejected: {}", leg, e);
+            escalate(tem, tracker, latest_event, leg, &network).await
+        }
+    }
+}
+
+/// Whether the node has any of `hashes`, in its mempool or in a block.
+async fn is_known(provider: &Provider<Http>, hashes: &[String]) -> Result<bool, WatcherError> {
+    for candidate in hashes {
+        let tx_hash = candidate
+            .parse::<H256>()
+            .map_err(|_| WatcherError::InvalidTxHashFormat(candidate.clone()))?;
+
+        let tx = provider.get_transaction(tx_hash).await
+            .map_err(|e| WatcherError::ReceiptFetchFailure(format!("Error fetching tx: {e}")))?;
+        if tx.is_some() {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+async fn escalate(
+    tem: &Arc<TransactionEventManager>,
+    tracker: &OperationMetricTracker,
+    latest_event: TransactionEvent,
+    leg: TransactionLeg,
+    network: &str,
+) -> Result<(), WatcherError> {
+    tracker.emit("StuckTransactionEscalated", 1.0, "Count", &[("Network", network)]).await;
+
+    let errored = retry_on_conflict(tem.as_ref(), latest_event, |latest| {
+        let tem = tem.clone();
+        async move { TransactionEvent::on_error(&latest, leg, tem).await }
+    })
+        .await
+        .map_err(|e| WatcherError::InvalidState(format!("on_error failed: {}", e)))?;
+
+    release_nonces(tem, &errored).await;
+    Ok(())
+}